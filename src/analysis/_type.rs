@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use crate::parser::ast::*;
+use crate::parser::ast::builtin::BUILTINS;
 use crate::parser::ast::span::Span;
 
 mod type_node;
@@ -16,6 +17,7 @@ pub fn type_error_check(mut prog: Box<Program>)  {
         scope: 0,
 		error: false,
     };
+    analyzer.add_builtins();
 
     prog.analyze_type(&mut analyzer);
     if analyzer.error {
@@ -132,6 +134,16 @@ impl TypeAnalyzer {
 		self.vars.retain(|k, _| k.scope != self.scope);
 	}
 
+	fn add_builtins(&mut self) {
+		for builtin in BUILTINS.iter() {
+			let value = FunctionKind {
+				arg_types: builtin.args.iter().map(|(_, arg_type)| *Type::new(TypeKind::Prim(*arg_type), false)).collect(),
+				return_type: *Type::new(TypeKind::Prim(builtin.ret), false)
+			};
+			self.functions.insert(builtin.name.to_string(), value);
+		}
+	}
+
 	pub fn add_fn(&mut self, func: &mut FnDecl) {
 		self.add_fn_helper(func, func.id.to_string());
 	}
@@ -168,7 +180,7 @@ impl TypeAnalyzer {
 				VarDecl(var_decl) => {
 					use TypeKind::*;
 					match *var_decl.var_type.kind.clone() {
-						Prim(_) => {
+						Prim(_) | Array { .. } => {
 							let var_field = Field {
 								id: var_decl.id.to_string(),
 								field_type: *var_decl.var_type
@@ -206,7 +218,7 @@ impl TypeAnalyzer {
 			//println!("{}", field);
 			use TypeKind::*;
 			match *field.field_type.kind.clone() {
-				Prim(_) => {
+				Prim(_) | Array { .. } => {
 					let curr = Field {
 						id: format!("{}--{}", outer_class_field_name, field.id),
 						field_type: field.field_type.clone()
@@ -255,6 +267,14 @@ impl TypeAnalyzer {
         Err(())
 	}
 
+	// fields of nested classes are flattened into the outer one, so `path` may be `inner--x`
+	pub fn get_field_type(&self, class_name: &String, path: &String) -> Result<Type, ()> {
+		match self.classes.get(class_name) {
+			Some(fields) => fields.iter().find(|field| field.id == *path).map(|field| field.field_type.clone()).ok_or(()),
+			None => Err(())
+		}
+	}
+
 	pub fn has_fn(&self, name: &String) -> bool {
 		if self.functions.get(name).is_some()  {
 			true
@@ -296,6 +316,18 @@ impl TypeAnalyzer {
 			BadAssignOne 	=> eprintln!("FATAL {span}: Invalid assignment operand"), 
             BadAssignTwo 	=> eprintln!("FATAL {span}: Invalid assignment operation"), 
 			NonLval 		=> eprintln!("FATAL {span}: Non-Lval assignment"), 
+			GiveArray 		=> eprintln!("FATAL {span}: Attempt to output an array"), 
+			ReadArray		=> eprintln!("FATAL {span}: Attempt to assign user input to array"), 
+			IndexNonArray 	=> eprintln!("FATAL {span}: Attempt to index a non-array"), 
+			IndexNonInt 	=> eprintln!("FATAL {span}: Non-int expression used as an array index"), 
+			NoField 		=> eprintln!("FATAL {span}: Undeclared field"), 
+			PushFixed 		=> eprintln!("FATAL {span}: Attempt to grow a fixed-size array"), 
+			MatchBadValue 	=> eprintln!("FATAL {span}: Match on a value that is not an int or bool"), 
+			MatchBadArm 	=> eprintln!("FATAL {span}: Match arm of the wrong type"), 
+			MatchEmptyRange => eprintln!("FATAL {span}: Match arm with an empty range"), 
+			MatchNotExhaustive => eprintln!("FATAL {span}: Match on a bool without both true and false arms"), 
+			TernaryBadBranch => eprintln!("FATAL {span}: Invalid ternary branch"), 
+			TernaryMismatch => eprintln!("FATAL {span}: Ternary branches of different types"), 
 		}
 	}
 }
@@ -320,5 +352,17 @@ enum ErrorType {
     BadEqualityTwo,
 	BadAssignOne,
     BadAssignTwo,
-	NonLval
+	NonLval,
+	GiveArray,
+	ReadArray,
+	IndexNonArray,
+	IndexNonInt,
+	NoField,
+	PushFixed,
+	MatchBadValue,
+	MatchBadArm,
+	MatchEmptyRange,
+	MatchNotExhaustive,
+	TernaryBadBranch,
+	TernaryMismatch
 }
\ No newline at end of file
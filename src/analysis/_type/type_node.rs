@@ -138,6 +138,55 @@ impl TypeAnalysisStmtNode for BlockStmt {
                 for stmt in body.iter_mut() {
                     stmt.analyze_type(analyzer, return_type);
                 }
+            },
+			For{ref mut init, ref mut cond, ref mut step, ref mut body} => {
+                if let Some(x) = init {
+                    x.analyze_type(analyzer, return_type);
+                }
+                cond.analyze_type(analyzer);
+                match is_condition_bool(cond) {
+                    Ok(cond_is_bool) => {
+                        if !cond_is_bool {
+                            analyzer.report_error(&CondNonBool, &cond.span);
+                        } 
+                    }
+                    Err(()) => ()
+                }
+                if let Some(x) = step {
+                    x.analyze_type(analyzer, return_type);
+                }
+
+                for stmt in body.iter_mut() {
+                    stmt.analyze_type(analyzer, return_type);
+                }
+            },
+			DoWhile{ref mut body, ref mut cond} => {
+                for stmt in body.iter_mut() {
+                    stmt.analyze_type(analyzer, return_type);
+                }
+                cond.analyze_type(analyzer);
+                match is_condition_bool(cond) {
+                    Ok(cond_is_bool) => {
+                        if !cond_is_bool {
+                            analyzer.report_error(&CondNonBool, &cond.span);
+                        } 
+                    }
+                    Err(()) => ()
+                }
+            },
+			Labeled{ref mut body, ..} => body.analyze_type(analyzer, return_type),
+			Match{ref mut value, ref mut arms, ref mut default} => {
+                value.analyze_type(analyzer);
+                check_arms(value, arms, default.is_some(), analyzer);
+
+                for arm in arms.iter_mut() {
+                    for stmt in arm.body.iter_mut() {
+                        stmt.analyze_type(analyzer, return_type);
+                    }
+                }
+                for stmt in default.iter_mut().flatten() {
+                    stmt.analyze_type(analyzer, return_type);
+                }
             },
 			IfElse{ref mut cond, ref mut true_branch, ref mut false_branch, } => {
                 cond.analyze_type(analyzer);
@@ -161,6 +210,38 @@ impl TypeAnalysisStmtNode for BlockStmt {
 	}
 }
 
+// arms have to fit the value's type, and a match on a bool has to cover both
+// values unless it has a default
+fn check_arms(value: &Exp, arms: &[MatchArm], has_default: bool, analyzer: &mut TypeAnalyzer) {
+    use TypeKind::*;
+    let is_bool = match value.expr_type.as_deref().map(|x| &*x.kind) {
+        Some(Prim(PrimType::Bool)) => true,
+        Some(Prim(PrimType::Int)) => false,
+        Some(_) => {
+            analyzer.report_error(&MatchBadValue, &value.span);
+            return;
+        },
+        None => return
+    };
+
+    let (mut seen_true, mut seen_false) = (false, false);
+    for arm in arms.iter() {
+        match arm.pattern {
+            Pattern::Bool(x) if is_bool => {
+                seen_true |= x;
+                seen_false |= !x;
+            },
+            Pattern::Int(_) | Pattern::Range(..) if is_bool => analyzer.report_error(&MatchBadArm, &arm.span),
+            Pattern::Bool(_) => analyzer.report_error(&MatchBadArm, &arm.span),
+            Pattern::Range(lo, hi) if lo > hi => analyzer.report_error(&MatchEmptyRange, &arm.span),
+            _ => ()
+        }
+    }
+    if is_bool && !has_default && !(seen_true && seen_false) {
+        analyzer.report_error(&MatchNotExhaustive, &value.span);
+    }
+}
+
 fn is_condition_bool(cond: &mut Box<Exp>) -> Result<bool, ()> {
     use TypeKind::*;
     match cond.expr_type.clone() {
@@ -206,6 +287,12 @@ impl TypeAnalysisStmtNode for LineStmt {
                 let dest_type = dest_type.unwrap();
                 let src_type = *src_type.clone().unwrap();
 
+                // an element of a class array is only reachable field by field
+                if matches!(*dest.kind, LocKind::Index { .. }) && matches!(*dest_type.kind, Class(_)) {
+                    analyzer.report_error(&BadAssignOne, &dest.span);
+                    invalid_operators = true;
+                }
+
                 if dest_type.perfect == true {
                     analyzer.report_error(&NonLval, &dest.span);
                     invalid_operators = true;
@@ -225,6 +312,30 @@ impl TypeAnalysisStmtNode for LineStmt {
                 }
 				
 			},
+			// ints have every compound assignment, floats the arithmetic ones
+			CompoundAssign{ ref mut dest, ref op, ref mut src } => {
+				let dest_type = dest.eval_type(analyzer);
+				src.analyze_type(analyzer);
+
+				let arithmetic = matches!(op, BinaryExpKind::Plus | BinaryExpKind::Minus | BinaryExpKind::Times | BinaryExpKind::Divide);
+				let operands = match &dest_type {
+					Ok(x) if arithmetic && *x.kind == Prim(PrimType::Float) => PrimType::Float,
+					_ => PrimType::Int
+				};
+
+				if let Ok(dest_type) = dest_type {
+					if dest_type.perfect {
+						analyzer.report_error(&NonLval, &dest.span);
+					} else if *dest_type.kind != Prim(operands) {
+						analyzer.report_error(&WrongOpMath, &dest.span);
+					}
+				}
+				if let Some(src_type) = &src.expr_type {
+					if *src_type.kind != Prim(operands) {
+						analyzer.report_error(&WrongOpMath, &src.span);
+					}
+				}
+			},
 			PostDec{ ref mut loc } | PostInc{ ref mut loc} => {
 				let loc_type = loc.eval_type(analyzer);
 				if loc_type.is_err() {
@@ -259,6 +370,11 @@ impl TypeAnalysisStmtNode for LineStmt {
 					},
 					_ => ()
 				}
+				match output.expr_type.as_ref().map(|output_type| &*output_type.kind) {
+					Some(Array { .. }) => analyzer.report_error(&GiveArray, &output.span),
+					Some(Class(_)) if !analyzer.has_class(&output.to_string()) => analyzer.report_error(&GiveClass, &output.span),
+					_ => ()
+				}
 			},
 			Take { ref mut recipient } => {
 				if analyzer.has_fn(&recipient.to_string()) {
@@ -267,6 +383,16 @@ impl TypeAnalysisStmtNode for LineStmt {
 				if analyzer.has_class(&recipient.to_string()) {
 					analyzer.report_error(&ReadClass, &recipient.span);
 				}
+				let recipient_type = recipient.eval_type(analyzer);
+				// characters of a string and elements of a perfect array can't be read into
+				if recipient_type.as_ref().is_ok_and(|x| x.perfect) && recipient.is_indexed() {
+					analyzer.report_error(&NonLval, &recipient.span);
+				}
+				match recipient_type.map(|recipient_type| *recipient_type.kind) {
+					Ok(Array { .. }) => analyzer.report_error(&ReadArray, &recipient.span),
+					Ok(Class(_)) if !analyzer.has_class(&recipient.to_string()) => analyzer.report_error(&ReadClass, &recipient.span),
+					_ => ()
+				}
 			},
 			Return { ref mut result} => {
                 
@@ -296,7 +422,7 @@ impl TypeAnalysisStmtNode for LineStmt {
 					}
 				}
 			},
-			Exit => (),
+			Exit | Break{..} | Continue{..} => (),
 			Call(ref mut fn_call) => {
 				let _ = fn_call.eval_type(analyzer);
 			},
@@ -311,6 +437,7 @@ impl TypeAnalysisNode for Exp {
 		let expr_type = match kind {
 			UnaryExp(mut x) => x.eval_type(analyzer),
 			BinaryExp(mut x) => x.eval_type(analyzer),
+			Ternary(mut x) => x.eval_type(analyzer),
 			CallExp(mut x) => x.eval_type(analyzer), //return type
 			Loc(mut x) => x.eval_type(analyzer),
 			_ => Err(())
@@ -338,7 +465,11 @@ impl EvaluateExpType for UnaryExp {
 		// error check
 		let kind = *self.kind.clone();
 		match kind {
-			Neg => {
+			Neg if *expr_type.kind == Prim(PrimType::Float) => {
+                self.expr_type = self.exp.expr_type.clone();
+                return Ok(expr_type);
+			},
+			Neg | BitNot => {
                 if &*expr_type.kind != &Prim(PrimType::Int) {
                     analyzer.report_error(&WrongOpMath, &self.exp.span);
                     return Err(());
@@ -360,6 +491,42 @@ impl EvaluateExpType for UnaryExp {
 	}
 }
 
+impl EvaluateExpType for TernaryExp {
+    fn eval_type(&mut self, analyzer: &mut TypeAnalyzer) -> Result<Type, ()> {
+		use TypeKind::*;
+		self.cond.analyze_type(analyzer);
+		self.true_branch.analyze_type(analyzer);
+		self.false_branch.analyze_type(analyzer);
+
+		if let Ok(false) = is_condition_bool(&mut self.cond) {
+			analyzer.report_error(&CondNonBool, &self.cond.span);
+		}
+
+		// a branch has to be a value that could be assigned
+		let mut invalid_branches = false;
+		for branch in [&self.true_branch, &self.false_branch] {
+			let is_void = matches!(branch.expr_type.as_deref().map(|x| &*x.kind), Some(Prim(PrimType::Void)));
+			if branch.is_fn_or_class(analyzer) || is_void {
+				analyzer.report_error(&TernaryBadBranch, &branch.span);
+				invalid_branches = true;
+			}
+		}
+
+		let (true_type, false_type) = match (&self.true_branch.expr_type, &self.false_branch.expr_type) {
+			(Some(x), Some(y)) if !invalid_branches => (x.clone(), y.clone()),
+			_ => return Err(())
+		};
+		if true_type.kind != false_type.kind {
+			analyzer.report_error(&TernaryMismatch, &self.span);
+			return Err(());
+		}
+
+		let result = *Type::new(*true_type.kind, false);
+		self.expr_type = Some(Box::new(result.clone()));
+		Ok(result)
+	}
+}
+
 impl EvaluateExpType for BinaryExp {
     fn eval_type(&mut self, analyzer: &mut TypeAnalyzer) -> Result<Type, ()> {
 		use BinaryExpKind::*;
@@ -386,8 +553,23 @@ impl EvaluateExpType for BinaryExp {
 		};
 
 		// error check
+		let string = Some(TypeKind::Prim(PrimType::String));
+		let float = Some(TypeKind::Prim(PrimType::Float));
 		match *self.kind {
-			Plus | Minus | Times | Divide => {
+			// + also joins two strings
+			Plus if lhs_type_kind == string && rhs_type_kind == string => {
+				self.expr_type = self.lhs.expr_type.clone();
+				lhs_type.ok_or(())
+			},
+			// floats do arithmetic among themselves, the int-only ops are left to ints
+			Plus | Minus | Times | Divide if lhs_type_kind == float => {
+				return self.eval_type_helper(lhs_type, 
+					lhs_type_kind, rhs_type_kind, 
+					PrimType::Float, WrongOpMath, analyzer
+				)
+			},
+			Plus | Minus | Times | Divide | Modulo
+			| BitAnd | BitOr | BitXor | ShiftLeft | ShiftRight => {
 				return self.eval_type_helper(lhs_type, 
 					lhs_type_kind, rhs_type_kind, 
 					PrimType::Int, WrongOpMath, analyzer
@@ -400,10 +582,15 @@ impl EvaluateExpType for BinaryExp {
 				)
 			},
 			Less | Greater | LessEq | GreaterEq  => {
-				return self.eval_type_helper(lhs_type, 
+				let operands = match lhs_type_kind {
+					Some(TypeKind::Prim(x @ (PrimType::Float | PrimType::Char))) => x,
+					_ => PrimType::Int
+				};
+				let result = self.eval_type_helper(lhs_type, 
 					lhs_type_kind, rhs_type_kind, 
-					PrimType::Int, WrongOpCmp, analyzer
-				)
+					operands, WrongOpCmp, analyzer
+				);
+				return self.compared(result)
 			},
 			Equals | NotEquals  => {
                 let mut invalid_operands = false;
@@ -428,6 +615,17 @@ impl EvaluateExpType for BinaryExp {
                     analyzer.report_error(&BadEqualityOne, &self.rhs.span);
                     invalid_operands = true;
                 }
+
+                // arrays would only compare by where they live
+                if matches!(lhs_type_kind, Some(Array { .. })) {
+                    analyzer.report_error(&BadEqualityOne, &self.lhs.span);
+                    invalid_operands = true;
+                }
+
+                if matches!(rhs_type_kind, Some(Array { .. })) {
+                    analyzer.report_error(&BadEqualityOne, &self.rhs.span);
+                    invalid_operands = true;
+                }
                 
                 if invalid_operands {
                     return Err(());
@@ -439,11 +637,9 @@ impl EvaluateExpType for BinaryExp {
                     return Err(());
                 }
 				
-                self.expr_type = self.lhs.expr_type.clone();
-                if lhs_type.is_some() {
-                    return Ok(lhs_type.unwrap());
-                } else {
-                    return Err(())
+                match lhs_type {
+                    Some(x) => return self.compared(Ok(x)),
+                    None => return Err(())
                 }
 			},
 		}
@@ -471,6 +667,14 @@ impl Loc {
 }
 
 impl BinaryExp {
+	// a comparison gives back a bool, whatever the type of its operands
+	fn compared(&mut self, operands: Result<Type, ()>) -> Result<Type, ()> {
+		let result = *Type::new(TypeKind::Prim(PrimType::Bool), false);
+		operands?;
+		self.expr_type = Some(Box::new(result.clone()));
+		Ok(result)
+	}
+
 	fn eval_type_helper(&mut self, lhs_type: Option<Type>, 
 			lhs_type_kind: Option<TypeKind>, rhs_type_kind: Option<TypeKind>,
 			correct_type: PrimType, err: ErrorType, analyzer: &mut TypeAnalyzer
@@ -504,6 +708,10 @@ impl BinaryExp {
 
 impl EvaluateExpType for CallExp {
     fn eval_type(&mut self, analyzer: &mut TypeAnalyzer) -> Result<Type, ()> {
+		if self.name.to_string() == "push" {
+			return self.eval_push(analyzer);
+		}
+
 		let binding = analyzer.clone();
 		let func = binding.get_fn(&self.name.to_string());
 		
@@ -525,7 +733,7 @@ impl EvaluateExpType for CallExp {
 			self.args[i].analyze_type(analyzer);
 			match self.args[i].expr_type.clone() {
 				Some(actual) => {
-					if *actual != func.arg_types[i] {
+					if *actual != func.arg_types[i] && !self.measures_array(&actual) {
 						analyzer.report_error(&FnWrongArgType, &self.args[i].span);
 						error = true;
 					}
@@ -543,13 +751,101 @@ impl EvaluateExpType for CallExp {
 	}
 }
 
+impl CallExp {
+	// `len` takes an array of anything as well as a string
+	fn measures_array(&self, actual: &Type) -> bool {
+		self.name.to_string() == "len" && matches!(*actual.kind, TypeKind::Array { .. })
+	}
+
+	// `push(a, x)` appends x to the growable array a
+	fn eval_push(&mut self, analyzer: &mut TypeAnalyzer) -> Result<Type, ()> {
+		use TypeKind::*;
+		if self.args.len() != 2 {
+			analyzer.report_error(&FnWrongArgNum, &self.span);
+			return Err(());
+		}
+		for arg in self.args.iter_mut() {
+			arg.analyze_type(analyzer);
+		}
+
+		let (array_type, value_type) = match (&self.args[0].expr_type, &self.args[1].expr_type) {
+			(Some(x), Some(y)) => (*x.kind.clone(), *y.clone()),
+			_ => return Err(())
+		};
+		let elem = match array_type {
+			Array { size: Some(_), .. } => {
+				analyzer.report_error(&PushFixed, &self.args[0].span);
+				return Err(());
+			},
+			Array { elem, size: None } => elem,
+			_ => {
+				analyzer.report_error(&FnWrongArgType, &self.args[0].span);
+				return Err(());
+			}
+		};
+		// a class element has no single value to push
+		if matches!(*elem.kind, Class(_)) || *elem.kind != *value_type.kind {
+			analyzer.report_error(&FnWrongArgType, &self.args[1].span);
+			return Err(());
+		}
+
+		let result = *Type::new(Prim(PrimType::Void), false);
+		self.fn_type = Some(result.clone());
+		Ok(result)
+	}
+}
+
 impl EvaluateExpType for Loc {
     fn eval_type(&mut self, analyzer: &mut TypeAnalyzer) -> Result<Type, ()> {
-		let my_type = analyzer.get_var_type(self.to_string());
+		let my_type = match self.indexed_field().map(|(elem, path)| (elem.clone(), path)) {
+			Some((mut elem, path)) => {
+				let span = elem.span;
+				match *elem.eval_type(analyzer)?.kind {
+					TypeKind::Class(class) => match analyzer.get_field_type(&class.name, &path) {
+						Ok(x) => Ok(x),
+						Err(()) => {
+							analyzer.report_error(&NoField, &self.span);
+							Err(())
+						}
+					},
+					_ => {
+						analyzer.report_error(&NoField, &span);
+						Err(())
+					}
+				}
+			},
+			None => match *self.kind {
+				LocKind::Index { ref mut base, ref mut index } => eval_index(base, index, analyzer),
+				_ => analyzer.get_var_type(self.to_string())
+			}
+		};
         match my_type.clone() {
             Ok(x) => self.loc_type = Some(x),
             Err(()) => ()
         };
         my_type
 	}
+}
+
+fn eval_index(base: &mut Loc, index: &mut Exp, analyzer: &mut TypeAnalyzer) -> Result<Type, ()> {
+	let base_type = base.eval_type(analyzer);
+	index.analyze_type(analyzer);
+
+	let index_is_int = match &index.expr_type {
+		Some(index_type) => *index_type.kind == TypeKind::Prim(PrimType::Int),
+		None => return Err(())
+	};
+	if !index_is_int {
+		analyzer.report_error(&IndexNonInt, &index.span);
+	}
+	match *base_type?.kind {
+		TypeKind::Array { elem, .. } if index_is_int => Ok(*elem),
+		// strings can be read a character at a time, but not written to
+		TypeKind::Prim(PrimType::String) if index_is_int => Ok(*Type::new(TypeKind::Prim(PrimType::Char), true)),
+		TypeKind::Array { .. } | TypeKind::Prim(PrimType::String) => Err(()),
+		_ => {
+			analyzer.report_error(&IndexNonArray, &base.span);
+			Err(())
+		}
+	}
 }
\ No newline at end of file
@@ -2,7 +2,8 @@ mod named_node;
 use named_node::NamedNode;
 
 use crate::parser::ast::*;
-use crate::parser::ast::span::{line_bytes, Span, node::SpanNode};
+use crate::parser::ast::builtin::{BUILTINS, ARRAY_BUILTINS};
+use crate::parser::ast::span::{line_lengths, Span, node::SpanNode};
 use crate::format::add_tabs;
 
 use std::collections::HashMap;
@@ -14,10 +15,13 @@ pub fn named_unparse(mut prog: Box<Program>, raw_input: String) -> String {
         scope: 0,
         table: HashMap::new(),
         classes: HashMap::new(),
+        loops: 0,
+        labels: Vec::new(),
         error: false
     };
+    unparser.add_builtins();
 
-    prog.correct_span_rec(&line_bytes(raw_input));
+    prog.correct_span_rec(&line_lengths(raw_input));
     let named_unparse = add_tabs(prog.named_unparse(&mut unparser));
 
     // println!("{}", unparser);
@@ -31,17 +35,38 @@ pub fn named_unparse(mut prog: Box<Program>, raw_input: String) -> String {
 pub enum NameError {
 	BadType,
 	MultipleDecl,
-	UndefinedDecl
+	UndefinedDecl,
+	OutsideLoop,
+	UndefinedLabel,
+	MultipleLabel
 }
 
 pub struct NamedUnparser {
     pub scope: usize,
     pub table: HashMap<SymbolKey, SymbolKind>,
     pub classes: HashMap<String, HashMap<String, SymbolKind>>,
+    // how many loops the statement being unparsed sits in
+    pub loops: usize,
+    // the labels of the loops it sits in, outermost first
+    pub labels: Vec<String>,
     pub error: bool
 }
 
 impl NamedUnparser {
+    // built-ins sit in the global scope, so declaring one again is an error
+    fn add_builtins(&mut self) {
+        for builtin in BUILTINS.iter().chain(ARRAY_BUILTINS.iter()) {
+            let args = builtin.args.iter()
+                .map(|(id, arg_type)| (id.to_string(), *Type::new(TypeKind::Prim(*arg_type), false)))
+                .collect::<HashMap<String, Type>>();
+            let key = SymbolKey {
+                id: builtin.name.to_string(),
+                scope: 0,
+            };
+            self.table.insert(key, SymbolKind::Function { args, ret: *Type::new(TypeKind::Prim(builtin.ret), false) });
+        }
+    }
+
     fn add_entry(&mut self, id: String, kind: SymbolKind, span: &Span) {
         let key : SymbolKey = SymbolKey {
             id: id,
@@ -51,7 +76,11 @@ impl NamedUnparser {
 		use crate::parser::ast::TypeKind::*;
 		match kind {
 			SymbolKind::Variable { ref var_type } => {
-				match *var_type.kind {
+				let elem = match *var_type.kind {
+					Array { ref elem, .. } => &*elem.kind,
+					ref kind => kind
+				};
+				match *elem {
 					Prim(PrimType::Void) => {
 						self.report_error(NameError::BadType, span);
 						error = true;
@@ -77,7 +106,10 @@ impl NamedUnparser {
 		match error {
 			NameError::BadType => eprintln!("FATAL {span}: Invalid type in declaration"),
 			NameError::MultipleDecl => eprintln!("FATAL {span}: Multiply declared identifier"),
-			NameError::UndefinedDecl => eprintln!("FATAL {span}: Undeclared identifier")
+			NameError::UndefinedDecl => eprintln!("FATAL {span}: Undeclared identifier"),
+			NameError::OutsideLoop => eprintln!("FATAL {span}: break or continue outside of a loop"),
+			NameError::UndefinedLabel => eprintln!("FATAL {span}: Undefined label"),
+			NameError::MultipleLabel => eprintln!("FATAL {span}: Label already names an enclosing loop")
 		}
 		self.error = true;
 	}
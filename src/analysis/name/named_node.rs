@@ -1,5 +1,6 @@
 use super::*;
 use crate::parser::ast::*;
+use crate::scanner::tokens::escape_char;
 
 pub trait NamedNode {
     fn named_unparse(&self, unparser: &mut NamedUnparser) -> String;
@@ -202,10 +203,35 @@ impl NamedNode for BlockStmt {
             While{cond, body} => {
                 let cond = cond.named_unparse(unparser);
                 unparser.scope += 1;
+                unparser.loops += 1;
                 let output = format!("while ({}) {{\n{}}}\n", 
                     cond,
                     body.clone().named_unparse_vec(unparser, "")
                 );
+                unparser.loops -= 1;
+				unparser.remove_scope(unparser.scope);
+                unparser.scope -= 1;
+                output 
+            },
+            For{init, cond, step, body} => {
+                let init = match init {
+                    Some(x) => x.named_unparse(unparser),
+                    None => "".to_string()
+                };
+                let cond = cond.named_unparse(unparser);
+                let step = match step {
+                    Some(x) => x.named_unparse(unparser),
+                    None => "".to_string()
+                };
+                unparser.scope += 1;
+                unparser.loops += 1;
+                let output = format!("for ({}; {}; {}) {{\n{}}}\n", 
+                    init,
+                    cond,
+                    step,
+                    body.clone().named_unparse_vec(unparser, "")
+                );
+                unparser.loops -= 1;
 				unparser.remove_scope(unparser.scope);
                 unparser.scope -= 1;
                 output 
@@ -221,6 +247,43 @@ impl NamedNode for BlockStmt {
                 unparser.scope -= 1;
                 output
             },
+            DoWhile{body, cond} => {
+                unparser.scope += 1;
+                unparser.loops += 1;
+                let body = body.clone().named_unparse_vec(unparser, "");
+                unparser.loops -= 1;
+				unparser.remove_scope(unparser.scope);
+                unparser.scope -= 1;
+                format!("do {{\n{}}}\nwhile ({});\n", body, cond.named_unparse(unparser))
+            },
+            // labels live apart from variables, and only inside the loop they name
+            Labeled{label, body} => {
+                if unparser.labels.contains(&label.name) {
+                    unparser.report_error(NameError::MultipleLabel, &label.span);
+                }
+                unparser.labels.push(label.name.clone());
+                let output = format!("{}: {}", label, body.named_unparse(unparser));
+                unparser.labels.pop();
+                output
+            },
+            Match{value, arms, default} => {
+                let value = value.named_unparse(unparser);
+                unparser.scope += 1;
+                let mut output = format!("match ({}) {{\n", value);
+                for arm in arms.iter() {
+                    output.push_str(&format!("{} => {{\n{}}}\n",
+                        arm.pattern,
+                        arm.body.clone().named_unparse_vec(unparser, "")
+                    ));
+                }
+                if let Some(x) = default {
+                    output.push_str(&format!("else => {{\n{}}}\n", x.clone().named_unparse_vec(unparser, "")));
+                }
+                output.push_str("}\n");
+				unparser.remove_scope(unparser.scope);
+                unparser.scope -= 1;
+                output
+            },
             IfElse{cond, true_branch, false_branch} => {
                 let cond = cond.named_unparse(unparser);
                 unparser.scope += 1;
@@ -243,6 +306,7 @@ impl NamedNode for LineStmt {
         let variant = *self.kind.clone();
         match variant {
             Assign{dest, src} => format!("{} = {}", dest.named_unparse(unparser), src.named_unparse(unparser)),
+            CompoundAssign{dest, op, src} => format!("{} {}= {}", dest.named_unparse(unparser), op, src.named_unparse(unparser)),
             PostDec{loc} => format!("{}--", loc.named_unparse(unparser)),
             PostInc{loc} => format!("{}++", loc.named_unparse(unparser)),
             Give{output} => format!("give {}", output.named_unparse(unparser)),
@@ -252,6 +316,16 @@ impl NamedNode for LineStmt {
                 None => format!("return"),}},
             Exit => format!("today I don't feel like doing any work"),
             Call(ref exp) => format!("{}", exp.named_unparse(unparser)),
+            Break{ref label} | Continue{ref label} => {
+                if unparser.loops == 0 {
+                    unparser.report_error(NameError::OutsideLoop, &self.span);
+                } else if let Some(x) = label {
+                    if !unparser.labels.contains(&x.name) {
+                        unparser.report_error(NameError::UndefinedLabel, &x.span);
+                    }
+                }
+                format!("{}", variant)
+            },
         }
     }
 }
@@ -272,8 +346,11 @@ impl NamedNode for ExpKind {
             Magic => "magic".to_string(),
             UnaryExp(ref exp) => format!("{}", exp.named_unparse(unparser)),
             BinaryExp(ref exp) => format!("{}", exp.named_unparse(unparser)),
+            Ternary(ref exp) => format!("{}", exp.named_unparse(unparser)),
             CallExp(ref exp) => format!("{}", exp.named_unparse(unparser)),
             IntLit(ref lit) => lit.to_string(),
+            FloatLit(ref lit) => format!("{:?}", lit),
+            CharLit(lit) => escape_char(*lit),
             StrLit(ref lit) => lit.to_string(),
             Loc(ref loc) => loc.named_unparse(unparser),
         }
@@ -292,6 +369,13 @@ impl NamedNode for BinaryExp {
     }
 }
 
+impl NamedNode for TernaryExp {
+    fn named_unparse(&self, unparser: &mut NamedUnparser) -> String {
+        format!("({} ? {} : {})", self.cond.named_unparse(unparser), 
+            self.true_branch.named_unparse(unparser), self.false_branch.named_unparse(unparser))
+    }
+}
+
 impl NamedNode for CallExp {
     fn named_unparse(&self, unparser: &mut NamedUnparser) -> String {
         // println!("in call exp... {}({:?})", &self.name, &self.args);
@@ -306,6 +390,7 @@ fn get_id_named_string(name: Loc, unparser: &mut NamedUnparser) -> (String, &mut
     let name = match *name.kind {
         Id(x) => x.clone(),
         Loc{base_class: _, field_name} => field_name.clone(),
+        Index{..} => return ("".to_string(), unparser),
     };
 
     let output = match unparser.find_entry(&name) {
@@ -328,6 +413,7 @@ impl Loc {
         match *self.kind.clone() {
             Id(_) => "Id",
             Loc{ base_class: _ , field_name: _} => "Loc",
+            Index{ .. } => "Index",
         }
     }
 
@@ -351,6 +437,15 @@ impl Loc {
                         let (output, _unparser) = get_id_named_string(base_class, unparser);
                         output
                     },
+                    // the element's class is only known to type analysis
+                    "Index" => {
+                        let base = base_class.named_unparse(unparser);
+                        if current.is_empty() {
+                            format!("{}--{}", base, field_name)
+                        } else {
+                            format!("{}--{}--{}", base, field_name, current)
+                        }
+                    },
                     "Loc" => {
                         if current == "" {
                             base_class.named_unparse_helper(unparser, format!("{}", field_name))
@@ -364,6 +459,9 @@ impl Loc {
                     }
                 }
             },
+            LocKind::Index { ref base, ref index } => {
+                format!("{}[{}]", base.named_unparse(unparser), index.named_unparse(unparser))
+            },
         }
     }
 }
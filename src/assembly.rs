@@ -1,2 +1,4 @@
 pub mod three_ac;
-pub mod x86;
\ No newline at end of file
+pub mod x86;
+pub mod ir;
+pub mod opt;
\ No newline at end of file
@@ -0,0 +1,603 @@
+use std::fmt::{Display, Formatter, Error};
+
+pub mod liveness;
+pub mod cfg;
+pub mod verify;
+pub mod ssa;
+
+#[cfg(test)]
+mod tests;
+
+use super::three_ac::Variable3ACType;
+use crate::scanner::tokens::{escape_char, unescape};
+
+/// The whole output of `convert_3ac`: the globals section, kept as text, and
+/// every function after it.
+pub struct IRProgram {
+    pub globals: String,
+    pub functions: Vec<IRFunction>,
+}
+
+/// Structured view of one function section of the text produced by `convert_3ac`.
+/// Parsing and printing round trip, so analyses can work on quads while the
+/// backend keeps reading text.
+#[derive(Clone)]
+pub struct IRFunction {
+    pub name: String,
+    pub locals: Vec<(String, Variable3ACType)>,
+    pub body: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub label: Option<String>,
+    pub quad: Quad,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Quad {
+    Enter(String),
+    Leave(String),
+    Nop,
+    Assign { dest: Operand, src: Operand },
+    Unary  { dest: Operand, op: UnaryOp, src: Operand },
+    Binary { dest: Operand, lhs: Operand, op: BinaryOp, rhs: Operand },
+    /// Reads slot `index` of an array, checking it is in bounds.
+    Load   { dest: Operand, array: Operand, index: Operand },
+    Store  { array: Operand, index: Operand, src: Operand },
+    Write(Operand),
+    Read(Operand),
+    IfZ    { cond: Operand, target: String },
+    Goto(String),
+    Call(String),
+    SetArg(usize, Operand),
+    GetArg(usize, Operand),
+    SetRet(Operand),
+    GetRet(Operand),
+    Exit,
+    /// Only present while a function is in SSA form. Each argument names the
+    /// predecessor block, by its label, that the value flows in from.
+    Phi    { dest: Operand, args: Vec<(Operand, String)> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Operand {
+    Var(String),
+    Int(i64),
+    /// A float literal, kept as its bits so operands stay hashable.
+    Float(u64),
+    Char(char),
+    True,
+    False,
+    Magic,
+    Str(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+    FNeg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mult,
+    Div,
+    Mod,
+    /// Bitwise operators on ints, And and Or are the logical ones.
+    BitAnd,
+    BitOr,
+    Xor,
+    /// Shifts take the count from the rhs, >> keeps the sign.
+    Shl,
+    Shr,
+    And,
+    Or,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Lte,
+    Gte,
+    /// The float versions of the arithmetic and comparisons, done in SSE.
+    FAdd,
+    FSub,
+    FMult,
+    FDiv,
+    FEq,
+    FNeq,
+    FLt,
+    FGt,
+    FLte,
+    FGte,
+    /// Compare two strings by their contents, through the runtime.
+    StrEq,
+    StrNeq,
+    /// Join two strings into a new one on the heap.
+    Cat,
+}
+
+impl IRProgram {
+    pub fn parse(three_ac: &str) -> IRProgram {
+        let mut sections = three_ac.split("[BEGIN ").skip(1);
+        let globals = match sections.next() {
+            Some(x) => format!("[BEGIN {}", x.trim_end()),
+            None => "".to_string()
+        };
+        IRProgram { globals, functions: sections.map(IRFunction::parse).collect() }
+    }
+}
+
+impl IRFunction {
+    pub fn parse(section: &str) -> IRFunction {
+        let mut lines = section.lines();
+        let name = match lines.next() {
+            Some(x) => x.trim_end_matches("LOCALS]").trim().to_string(),
+            None => "".to_string()
+        };
+
+        let mut locals = Vec::new();
+        for local in lines.by_ref() {
+            if local.starts_with("[END ") {
+                break;
+            }
+            if local.trim() != "" {
+                locals.push(parse_var(local));
+            }
+        }
+
+        let body = lines.filter_map(Line::parse).collect();
+        IRFunction { name, locals, body }
+    }
+
+    pub fn is_local(&self, name: &str) -> bool {
+        self.locals.iter().any(|(id, _)| id == name)
+    }
+
+    /// Index of the line carrying `label`, if any.
+    pub fn label_index(&self, label: &str) -> Option<usize> {
+        self.body.iter().position(|line| line.label.as_deref() == Some(label))
+    }
+
+    /// Lines control may reach right after line `i` executes.
+    pub fn successors(&self, i: usize) -> Vec<usize> {
+        let next = if i + 1 < self.body.len() { vec![i + 1] } else { Vec::new() };
+        match &self.body[i].quad {
+            Quad::Goto(target) => self.label_index(target).into_iter().collect(),
+            Quad::IfZ { target, .. } => {
+                let mut succ = next;
+                if let Some(t) = self.label_index(target) {
+                    if !succ.contains(&t) {
+                        succ.push(t);
+                    }
+                }
+                succ
+            },
+            Quad::Leave(_) | Quad::Exit => Vec::new(),
+            _ => next
+        }
+    }
+}
+
+fn parse_var(var: &str) -> (String, Variable3ACType) {
+    let (id, var_type) = var.rsplit_once('-').unwrap_or((var, "int"));
+    let var_type = match var_type {
+        "bool" => Variable3ACType::Bool,
+        "string" => Variable3ACType::String,
+        "float" => Variable3ACType::Float,
+        "char" => Variable3ACType::Char,
+        _ => Variable3ACType::Int,
+    };
+    (id.to_string(), var_type)
+}
+
+impl Line {
+    fn parse(line: &str) -> Option<Line> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        // labels never contain spaces, so a colon before the first space marks one
+        let (label, stmt) = match line.split_once(":\t") {
+            Some((lbl, stmt)) if !lbl.contains(' ') => (Some(lbl.to_string()), stmt.trim()),
+            _ => (None, line),
+        };
+
+        Some(Line { label, quad: Quad::parse(stmt)? })
+    }
+}
+
+impl Quad {
+    fn parse(stmt: &str) -> Option<Quad> {
+        let ops = stmt.split(' ').collect::<Vec<&str>>();
+
+        if ops.len() > 2 && ops[1] == ":=" && ops[2] == "PHI" {
+            let args = ops[3..].chunks(2)
+                .map(|arg| Some((Operand::parse(arg[0]), arg.get(1)?.to_string())))
+                .collect::<Option<Vec<(Operand, String)>>>()?;
+            return Some(Quad::Phi { dest: Operand::parse(ops[0]), args });
+        }
+
+        if ops.len() == 5 && ops[1] == ":=" && ops[2] == "LOAD" {
+            return Some(Quad::Load { dest: Operand::parse(ops[0]), array: Operand::parse(ops[3]), index: Operand::parse(ops[4]) });
+        }
+
+        if ops.len() > 1 && ops[1] == ":=" {
+            let dest = Operand::parse(ops[0]);
+            return match ops.len() {
+                3 => Some(Quad::Assign { dest, src: Operand::parse(ops[2]) }),
+                4 => Some(Quad::Unary { dest, op: UnaryOp::parse(ops[2])?, src: Operand::parse(ops[3]) }),
+                5 => Some(Quad::Binary {
+                    dest,
+                    lhs: Operand::parse(ops[2]),
+                    op: BinaryOp::parse(ops[3])?,
+                    rhs: Operand::parse(ops[4])
+                }),
+                _ => None
+            };
+        }
+
+        let arg = ops.get(1).copied().unwrap_or("");
+        match ops[0] {
+            "enter" => Some(Quad::Enter(arg.to_string())),
+            "leave" => Some(Quad::Leave(arg.to_string())),
+            "nop" => Some(Quad::Nop),
+            "WRITE" => Some(Quad::Write(Operand::parse_written(arg))),
+            "READ" => Some(Quad::Read(Operand::parse(arg))),
+            "STORE" => Some(Quad::Store {
+                array: Operand::parse(arg),
+                index: Operand::parse(ops.get(2)?),
+                src: Operand::parse(ops.get(3)?)
+            }),
+            "IFZ" => Some(Quad::IfZ { cond: Operand::parse(arg), target: ops.get(3)?.to_string() }),
+            "goto" => Some(Quad::Goto(arg.to_string())),
+            "call" => Some(Quad::Call(arg.to_string())),
+            "setarg" => Some(Quad::SetArg(arg.parse().ok()?, Operand::parse(ops.get(2)?))),
+            "getarg" => Some(Quad::GetArg(arg.parse().ok()?, Operand::parse(ops.get(2)?))),
+            "setret" => Some(Quad::SetRet(Operand::parse(arg))),
+            "getret" => Some(Quad::GetRet(Operand::parse(arg))),
+            "exit" => Some(Quad::Exit),
+            _ => None
+        }
+    }
+
+    /// The variable written by this quad, if any.
+    pub fn def(&self) -> Option<&String> {
+        use Quad::*;
+        match self {
+            Assign { dest, .. } | Unary { dest, .. } | Binary { dest, .. } | Load { dest, .. } |
+            Read(dest) | GetRet(dest) | GetArg(_, dest) | Phi { dest, .. } => dest.var(),
+            _ => None
+        }
+    }
+
+    /// The operand written by this quad, if any.
+    pub fn dest_mut(&mut self) -> Option<&mut Operand> {
+        use Quad::*;
+        match self {
+            Assign { dest, .. } | Unary { dest, .. } | Binary { dest, .. } | Load { dest, .. } |
+            Read(dest) | GetRet(dest) | GetArg(_, dest) | Phi { dest, .. } => Some(dest),
+            _ => None
+        }
+    }
+
+    /// The operands read by this quad.
+    pub fn uses_mut(&mut self) -> Vec<&mut Operand> {
+        use Quad::*;
+        match self {
+            Assign { src, .. } | Unary { src, .. } => vec![src],
+            Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Load { array, index, .. } => vec![array, index],
+            Store { array, index, src } => vec![array, index, src],
+            Write(x) | SetRet(x) | SetArg(_, x) => vec![x],
+            IfZ { cond, .. } => vec![cond],
+            Phi { args, .. } => args.iter_mut().map(|(arg, _)| arg).collect(),
+            _ => Vec::new()
+        }
+    }
+
+    /// The variables read by this quad.
+    pub fn uses(&self) -> Vec<&String> {
+        use Quad::*;
+        let operands = match self {
+            Assign { src, .. } | Unary { src, .. } => vec![src],
+            Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Load { array, index, .. } => vec![array, index],
+            Store { array, index, src } => vec![array, index, src],
+            Write(x) | SetRet(x) | SetArg(_, x) => vec![x],
+            IfZ { cond, .. } => vec![cond],
+            Phi { args, .. } => args.iter().map(|(arg, _)| arg).collect(),
+            _ => Vec::new()
+        };
+        operands.into_iter().filter_map(Operand::var).collect()
+    }
+
+    pub fn operands(&self) -> Vec<&Operand> {
+        use Quad::*;
+        match self {
+            Assign { dest, src } | Unary { dest, src, .. } => vec![dest, src],
+            Binary { dest, lhs, rhs, .. } => vec![dest, lhs, rhs],
+            Load { dest, array, index } => vec![dest, array, index],
+            Store { array, index, src } => vec![array, index, src],
+            Write(x) | Read(x) | SetRet(x) | GetRet(x) | SetArg(_, x) | GetArg(_, x) => vec![x],
+            IfZ { cond, .. } => vec![cond],
+            Phi { dest, args } => std::iter::once(dest).chain(args.iter().map(|(arg, _)| arg)).collect(),
+            _ => Vec::new()
+        }
+    }
+
+    /// Whether the backend lowers this quad to a `callq`.
+    pub fn is_call(&self) -> bool {
+        use Quad::*;
+        match self {
+            Write(_) | Read(_) | Call(_) => true,
+            Binary { op: BinaryOp::StrEq | BinaryOp::StrNeq | BinaryOp::Cat, .. } => true,
+            _ => self.calls_magic()
+        }
+    }
+
+    /// Whether the backend calls `magic` before reading the other operands.
+    pub fn calls_magic(&self) -> bool {
+        self.operands().contains(&&Operand::Magic)
+    }
+}
+
+impl Operand {
+    fn parse(op: &str) -> Operand {
+        if op.starts_with('[') && op.ends_with(']') {
+            return Operand::Var(op[1..op.len() - 1].to_string());
+        }
+        if let Some(x) = parse_char(op) {
+            return Operand::Char(x);
+        }
+        match op {
+            "true" => Operand::True,
+            "false" => Operand::False,
+            "24Kmagic" => Operand::Magic,
+            _ => match (op.parse::<i64>(), op.parse::<f64>()) {
+                (Ok(x), _) => Operand::Int(x),
+                (_, Ok(x)) if is_float_literal(op) => Operand::Float(x.to_bits()),
+                _ => Operand::Str(op.to_string()),
+            }
+        }
+    }
+
+    // WRITE drops the brackets around variables, only string labels stay bare
+    fn parse_written(op: &str) -> Operand {
+        match Operand::parse(op) {
+            Operand::Str(x) if !is_string_label(&x) => Operand::Var(x),
+            x => x
+        }
+    }
+
+    pub fn var(&self) -> Option<&String> {
+        match self {
+            Operand::Var(x) => Some(x),
+            _ => None
+        }
+    }
+}
+
+// float literals are printed with a point or an exponent, never as inf or NaN
+pub fn is_float_literal(op: &str) -> bool {
+    op.trim_start_matches('-').starts_with(|c: char| c.is_ascii_digit()) && op.contains(['.', 'e'])
+}
+
+/// A char literal as the 3AC writes it, whitespace and control characters
+/// by their code so the quad stays one word.
+pub fn parse_char(op: &str) -> Option<char> {
+    let inner = unescape(op.strip_prefix('\'')?.strip_suffix('\'')?)?;
+    let mut chars = inner.chars();
+    match (chars.next(), chars.next()) {
+        (Some(x), None) => Some(x),
+        _ => None
+    }
+}
+
+fn write_char(c: char) -> String {
+    match c {
+        _ if c.is_whitespace() && !c.is_control() => format!("'\\u{{{:x}}}'", c as u32),
+        _ => escape_char(c)
+    }
+}
+
+fn is_string_label(op: &str) -> bool {
+    op.len() > 3 && op.starts_with("str") && op[3..].chars().all(|c| c.is_ascii_digit())
+}
+
+impl UnaryOp {
+    fn parse(op: &str) -> Option<UnaryOp> {
+        match op {
+            "NEG64" => Some(UnaryOp::Neg),
+            "NOT64" => Some(UnaryOp::Not),
+            "BITNOT64" => Some(UnaryOp::BitNot),
+            "NEGF64" => Some(UnaryOp::FNeg),
+            _ => None
+        }
+    }
+}
+
+impl BinaryOp {
+    fn parse(op: &str) -> Option<BinaryOp> {
+        use BinaryOp::*;
+        match op {
+            "ADD64" => Some(Add),
+            "SUB64" => Some(Sub),
+            "MULT64" => Some(Mult),
+            "DIV64" => Some(Div),
+            "MOD64" => Some(Mod),
+            "BITAND64" => Some(BitAnd),
+            "BITOR64" => Some(BitOr),
+            "XOR64" => Some(Xor),
+            "SHL64" => Some(Shl),
+            "SHR64" => Some(Shr),
+            "AND64" => Some(And),
+            "OR64" => Some(Or),
+            "EQ64" => Some(Eq),
+            "NEQ64" => Some(Neq),
+            "LT64" => Some(Lt),
+            "GT64" => Some(Gt),
+            "LTE64" => Some(Lte),
+            "GTE64" => Some(Gte),
+            "ADDF64" => Some(FAdd),
+            "SUBF64" => Some(FSub),
+            "MULTF64" => Some(FMult),
+            "DIVF64" => Some(FDiv),
+            "EQF64" => Some(FEq),
+            "NEQF64" => Some(FNeq),
+            "LTF64" => Some(FLt),
+            "GTF64" => Some(FGt),
+            "LTEF64" => Some(FLte),
+            "GTEF64" => Some(FGte),
+            "STREQ64" => Some(StrEq),
+            "STRNEQ64" => Some(StrNeq),
+            "CAT64" => Some(Cat),
+            _ => None
+        }
+    }
+}
+
+impl Display for IRProgram {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        writeln!(fmt, "{}", self.globals)?;
+        for func in self.functions.iter() {
+            writeln!(fmt, "{}", func)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for IRFunction {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        let mut output : Vec<String> = Vec::new();
+        output.push("".to_string());
+        output.push(format!("[BEGIN {} LOCALS]", self.name));
+        output.push(self.locals.iter()
+            .map(|(id, t)| format!("{}-{}", id, t))
+            .collect::<Vec<String>>().join("\n"));
+        output.push(format!("[END {} LOCALS]", self.name));
+        output.push("".to_string());
+
+        for line in self.body.iter() {
+            if matches!(line.quad, Quad::Nop) {
+                output.push("".to_string());
+            }
+            output.push(line.to_string());
+        }
+        write!(fmt, "{}", output.join("\n"))
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match &self.label {
+            Some(lbl) => write!(fmt, "{}:\t{}", lbl, self.quad),
+            None => write!(fmt, "\t{}", self.quad),
+        }
+    }
+}
+
+impl Display for Quad {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        use Quad::*;
+        match self {
+            Enter(id) => write!(fmt, "enter {}", id),
+            Leave(id) => write!(fmt, "leave {}", id),
+            Nop => write!(fmt, "nop"),
+            Assign { dest, src } => write!(fmt, "{} := {}", dest, src),
+            Unary { dest, op, src } => write!(fmt, "{} := {} {}", dest, op, src),
+            Binary { dest, lhs, op, rhs } => write!(fmt, "{} := {} {} {}", dest, lhs, op, rhs),
+            Load { dest, array, index } => write!(fmt, "{} := LOAD {} {}", dest, array, index),
+            Store { array, index, src } => write!(fmt, "STORE {} {} {}", array, index, src),
+            Write(Operand::Var(x)) => write!(fmt, "WRITE {}", x),
+            Write(x) => write!(fmt, "WRITE {}", x),
+            Read(x) => write!(fmt, "READ {}", x),
+            IfZ { cond, target } => write!(fmt, "IFZ {} goto {}", cond, target),
+            Goto(lbl) => write!(fmt, "goto {}", lbl),
+            Call(id) => write!(fmt, "call {}", id),
+            SetArg(i, x) => write!(fmt, "setarg {} {}", i, x),
+            GetArg(i, x) => write!(fmt, "getarg {} {}", i, x),
+            SetRet(x) => write!(fmt, "setret {}", x),
+            GetRet(x) => write!(fmt, "getret {}", x),
+            Exit => write!(fmt, "exit"),
+            Phi { dest, args } => {
+                write!(fmt, "{} := PHI", dest)?;
+                for (arg, pred) in args.iter() {
+                    write!(fmt, " {} {}", arg, pred)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        use Operand::*;
+        match self {
+            Var(x) => write!(fmt, "[{}]", x),
+            Int(x) => write!(fmt, "{}", x),
+            Float(x) => write!(fmt, "{:?}", f64::from_bits(*x)),
+            Char(x) => write!(fmt, "{}", write_char(*x)),
+            True => write!(fmt, "true"),
+            False => write!(fmt, "false"),
+            Magic => write!(fmt, "24Kmagic"),
+            Str(x) => write!(fmt, "{}", x),
+        }
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match self {
+            UnaryOp::Neg => write!(fmt, "NEG64"),
+            UnaryOp::Not => write!(fmt, "NOT64"),
+            UnaryOp::BitNot => write!(fmt, "BITNOT64"),
+            UnaryOp::FNeg => write!(fmt, "NEGF64"),
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        use BinaryOp::*;
+        match self {
+            Add => write!(fmt, "ADD64"),
+            Sub => write!(fmt, "SUB64"),
+            Mult => write!(fmt, "MULT64"),
+            Div => write!(fmt, "DIV64"),
+            Mod => write!(fmt, "MOD64"),
+            BitAnd => write!(fmt, "BITAND64"),
+            BitOr => write!(fmt, "BITOR64"),
+            Xor => write!(fmt, "XOR64"),
+            Shl => write!(fmt, "SHL64"),
+            Shr => write!(fmt, "SHR64"),
+            And => write!(fmt, "AND64"),
+            Or => write!(fmt, "OR64"),
+            Eq => write!(fmt, "EQ64"),
+            Neq => write!(fmt, "NEQ64"),
+            Lt => write!(fmt, "LT64"),
+            Gt => write!(fmt, "GT64"),
+            Lte => write!(fmt, "LTE64"),
+            Gte => write!(fmt, "GTE64"),
+            FAdd => write!(fmt, "ADDF64"),
+            FSub => write!(fmt, "SUBF64"),
+            FMult => write!(fmt, "MULTF64"),
+            FDiv => write!(fmt, "DIVF64"),
+            FEq => write!(fmt, "EQF64"),
+            FNeq => write!(fmt, "NEQF64"),
+            FLt => write!(fmt, "LTF64"),
+            FGt => write!(fmt, "GTF64"),
+            FLte => write!(fmt, "LTEF64"),
+            FGte => write!(fmt, "GTEF64"),
+            StrEq => write!(fmt, "STREQ64"),
+            StrNeq => write!(fmt, "STRNEQ64"),
+            Cat => write!(fmt, "CAT64"),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use super::{IRFunction, Quad};
+
+/// A maximal run of lines `start..end` that control enters only at the top and
+/// leaves only at the bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub end: usize,
+    pub preds: Vec<usize>,
+    pub succs: Vec<usize>,
+}
+
+/// A natural loop: the header every block of the loop is dominated by, and
+/// the blocks, header included, that can reach a back edge into it without
+/// passing through the header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    pub header: usize,
+    pub blocks: Vec<usize>,
+}
+
+/// The control-flow graph of one function. Blocks are numbered in program
+/// order, so block 0 is always the entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Cfg {
+    /// Splits the body before every labelled line and after every `goto`,
+    /// `IFZ`, `leave` and `exit`, then links the blocks along the jumps.
+    pub fn build(func: &IRFunction) -> Cfg {
+        let mut leaders = vec![0];
+        for (i, line) in func.body.iter().enumerate() {
+            if i > 0 && line.label.is_some() {
+                leaders.push(i);
+            }
+            if matches!(line.quad, Quad::Goto(_) | Quad::IfZ { .. } | Quad::Leave(_) | Quad::Exit) {
+                leaders.push(i + 1);
+            }
+        }
+        leaders.retain(|&i| i < func.body.len());
+        leaders.dedup();
+
+        let mut blocks = leaders.iter().enumerate().map(|(b, &start)| BasicBlock {
+            start,
+            end: leaders.get(b + 1).copied().unwrap_or(func.body.len()),
+            preds: Vec::new(),
+            succs: Vec::new(),
+        }).collect::<Vec<BasicBlock>>();
+
+        for b in 0..blocks.len() {
+            let last = blocks[b].end - 1;
+            for succ in func.successors(last) {
+                let s = block_containing(&blocks, succ);
+                if !blocks[b].succs.contains(&s) {
+                    blocks[b].succs.push(s);
+                    blocks[s].preds.push(b);
+                }
+            }
+        }
+        Cfg { blocks }
+    }
+
+    /// Blocks reachable from the entry, in reverse postorder.
+    pub fn reverse_postorder(&self) -> Vec<usize> {
+        let mut order = Vec::new();
+        let mut visited = vec![false; self.blocks.len()];
+        // (block, index of the next successor to visit)
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        while let Some((b, next)) = stack.pop() {
+            match self.blocks[b].succs.get(next) {
+                Some(&succ) => {
+                    stack.push((b, next + 1));
+                    if !visited[succ] {
+                        visited[succ] = true;
+                        stack.push((succ, 0));
+                    }
+                },
+                None => order.push(b),
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /// The immediate dominator of every block, following Cooper, Harvey and
+    /// Kennedy. The entry is its own dominator, unreachable blocks have none.
+    pub fn dominators(&self) -> Vec<Option<usize>> {
+        let order = self.reverse_postorder();
+        let mut position = vec![usize::MAX; self.blocks.len()];
+        for (i, b) in order.iter().enumerate() {
+            position[*b] = i;
+        }
+
+        let mut idom : Vec<Option<usize>> = vec![None; self.blocks.len()];
+        idom[0] = Some(0);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for b in order.iter().skip(1) {
+                let mut new_idom : Option<usize> = None;
+                for pred in self.blocks[*b].preds.iter() {
+                    if idom[*pred].is_none() {
+                        continue;
+                    }
+                    new_idom = match new_idom {
+                        None => Some(*pred),
+                        Some(other) => Some(intersect(&idom, &position, *pred, other)),
+                    };
+                }
+                if new_idom != idom[*b] {
+                    idom[*b] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        idom
+    }
+
+    /// The blocks where each block's dominance stops, i.e. where definitions
+    /// made in it meet values coming in from elsewhere.
+    pub fn dominance_frontiers(&self, idom: &[Option<usize>]) -> Vec<Vec<usize>> {
+        let mut frontiers : Vec<Vec<usize>> = vec![Vec::new(); self.blocks.len()];
+        for (b, block) in self.blocks.iter().enumerate() {
+            if block.preds.len() < 2 || idom[b].is_none() {
+                continue;
+            }
+            for pred in block.preds.iter() {
+                let mut runner = *pred;
+                while idom[runner].is_some() && Some(runner) != idom[b] {
+                    if !frontiers[runner].contains(&b) {
+                        frontiers[runner].push(b);
+                    }
+                    runner = idom[runner].unwrap();
+                }
+            }
+        }
+        frontiers
+    }
+
+    /// Whether every path from the entry to block `b` passes through block `a`.
+    pub fn dominates(idom: &[Option<usize>], a: usize, b: usize) -> bool {
+        let mut runner = b;
+        loop {
+            if runner == a {
+                return true;
+            }
+            match idom[runner] {
+                Some(x) if x != runner => runner = x,
+                _ => return false
+            }
+        }
+    }
+
+    /// Every natural loop, innermost (smallest) first. Back edges into the same
+    /// header make up one loop.
+    pub fn natural_loops(&self) -> Vec<Loop> {
+        let idom = self.dominators();
+        let mut loops : Vec<Loop> = Vec::new();
+        for (b, block) in self.blocks.iter().enumerate() {
+            for header in block.succs.iter().copied() {
+                if idom[b].is_none() || !Cfg::dominates(&idom, header, b) {
+                    continue;
+                }
+
+                let index = match loops.iter().position(|l| l.header == header) {
+                    Some(x) => x,
+                    None => {
+                        loops.push(Loop { header, blocks: vec![header] });
+                        loops.len() - 1
+                    }
+                };
+                let mut worklist = vec![b];
+                while let Some(x) = worklist.pop() {
+                    // unreachable code can jump into a loop too, but isn't part of it
+                    if loops[index].blocks.contains(&x) || idom[x].is_none() {
+                        continue;
+                    }
+                    loops[index].blocks.push(x);
+                    worklist.extend(self.blocks[x].preds.iter().copied());
+                }
+            }
+        }
+        for l in loops.iter_mut() {
+            l.blocks.sort();
+        }
+        loops.sort_by_key(|l| l.blocks.len());
+        loops
+    }
+
+    /// The block holding line `line` of the function body.
+    pub fn block_of(&self, line: usize) -> usize {
+        block_containing(&self.blocks, line)
+    }
+
+    /// Renders the graph as a Graphviz cluster named after the function, with
+    /// one box per block listing its quads.
+    pub fn to_dot(&self, func: &IRFunction) -> String {
+        let mut output : Vec<String> = Vec::new();
+        output.push(format!("\tsubgraph \"cluster_{}\" {{", func.name));
+        output.push(format!("\t\tlabel=\"{}\";", escape(&func.name)));
+
+        for (b, block) in self.blocks.iter().enumerate() {
+            let code = func.body[block.start..block.end].iter()
+                .map(|line| format!("{}\\l", escape(&line.to_string().replace('\t', "    "))))
+                .collect::<String>();
+            output.push(format!("\t\t\"{}_B{}\" [label=\"B{}\\l{}\"];", func.name, b, b, code));
+        }
+
+        for (b, block) in self.blocks.iter().enumerate() {
+            let branch = matches!(func.body[block.end - 1].quad, Quad::IfZ { .. }) && block.succs.len() == 2;
+            for (n, succ) in block.succs.iter().enumerate() {
+                // IFZ falls through when its condition holds and jumps when it doesn't
+                let label = match (branch, n) {
+                    (true, 0) => " [label=\"true\"]",
+                    (true, _) => " [label=\"false\"]",
+                    _ => ""
+                };
+                output.push(format!("\t\t\"{}_B{}\" -> \"{}_B{}\"{};", func.name, b, func.name, succ, label));
+            }
+        }
+
+        output.push("\t}".to_string());
+        output.join("\n")
+    }
+}
+
+// walks both blocks up the dominator tree until they meet
+fn intersect(idom: &[Option<usize>], position: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while position[a] > position[b] {
+            a = idom[a].unwrap();
+        }
+        while position[b] > position[a] {
+            b = idom[b].unwrap();
+        }
+    }
+    a
+}
+
+fn block_containing(blocks: &[BasicBlock], line: usize) -> usize {
+    blocks.iter().position(|block| block.start <= line && line < block.end).unwrap()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Wraps the clusters of every function into one graph `dot` can render.
+pub fn program_dot(functions: &[IRFunction]) -> String {
+    let mut output : Vec<String> = Vec::new();
+    output.push("digraph program {".to_string());
+    output.push("\tnode [shape=box, fontname=\"monospace\"];".to_string());
+    for func in functions.iter() {
+        output.push(Cfg::build(func).to_dot(func));
+    }
+    output.push("}".to_string());
+    output.push("".to_string());
+    output.join("\n")
+}
@@ -0,0 +1,65 @@
+use std::collections::HashSet;
+
+use super::IRFunction;
+
+/// Per-line liveness of the variables local to one function.
+pub struct Liveness {
+    pub live_in: Vec<HashSet<String>>,
+    pub live_out: Vec<HashSet<String>>,
+}
+
+impl Liveness {
+    /// Backwards dataflow over the function's lines until nothing changes.
+    /// Globals are left out since they outlive every function.
+    pub fn analyze(func: &IRFunction) -> Liveness {
+        let n = func.body.len();
+        let mut live_in: Vec<HashSet<String>> = vec![HashSet::new(); n];
+        let mut live_out: Vec<HashSet<String>> = vec![HashSet::new(); n];
+        let successors = (0..n).map(|i| func.successors(i)).collect::<Vec<Vec<usize>>>();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in (0..n).rev() {
+                let mut out: HashSet<String> = HashSet::new();
+                for succ in successors[i].iter() {
+                    out.extend(live_in[*succ].iter().cloned());
+                }
+
+                let quad = &func.body[i].quad;
+                let mut new_in = out.clone();
+                if let Some(def) = quad.def() {
+                    new_in.remove(def);
+                }
+                for var in quad.uses() {
+                    if func.is_local(var) {
+                        new_in.insert(var.clone());
+                    }
+                }
+
+                if new_in != live_in[i] || out != live_out[i] {
+                    changed = true;
+                    live_in[i] = new_in;
+                    live_out[i] = out;
+                }
+            }
+        }
+
+        Liveness { live_in, live_out }
+    }
+
+    /// Variables whose values must survive line `i`, i.e. live on both sides
+    /// of it without being redefined there. `magic` is called before the
+    /// line's operands are read, so those have to survive it too.
+    pub fn live_across(&self, i: usize, func: &IRFunction) -> HashSet<String> {
+        let quad = &func.body[i].quad;
+        let mut across = self.live_out[i].clone();
+        if quad.calls_magic() {
+            across.extend(quad.uses().into_iter().filter(|var| func.is_local(var)).cloned());
+        }
+        if let Some(def) = quad.def() {
+            across.remove(def);
+        }
+        across
+    }
+}
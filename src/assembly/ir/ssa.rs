@@ -0,0 +1,373 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{IRProgram, IRFunction, Line, Quad, Operand};
+use super::cfg::Cfg;
+use super::liveness::Liveness;
+use crate::assembly::three_ac::Variable3ACType;
+
+/// Puts every function of the program into SSA form.
+pub fn construct_program(program: &mut IRProgram) {
+    for func in program.functions.iter_mut() {
+        if !in_ssa(func) {
+            construct(func);
+        }
+    }
+}
+
+/// Takes every function of the program back out of SSA form.
+pub fn destruct_program(program: &mut IRProgram) {
+    for func in program.functions.iter_mut() {
+        if in_ssa(func) {
+            destruct(func);
+        }
+    }
+}
+
+pub fn in_ssa(func: &IRFunction) -> bool {
+    func.body.iter().any(|line| matches!(line.quad, Quad::Phi { .. }))
+}
+
+/// Renames every local so it is assigned exactly once, placing phi nodes on
+/// the dominance frontiers of its definitions wherever it is still live.
+/// The first version of a variable keeps its name, later ones are `name.N`.
+pub fn construct(func: &mut IRFunction) {
+    label_blocks(func);
+    let cfg = Cfg::build(func);
+    let idom = cfg.dominators();
+    let frontiers = cfg.dominance_frontiers(&idom);
+    let liveness = Liveness::analyze(func);
+
+    // blocks defining each local
+    let mut def_blocks : HashMap<String, Vec<usize>> = HashMap::new();
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        if idom[b].is_none() {
+            continue;
+        }
+        for line in func.body[block.start..block.end].iter() {
+            if let Some(var) = line.quad.def() {
+                if func.is_local(var) {
+                    def_blocks.entry(var.clone()).or_default().push(b);
+                }
+            }
+        }
+    }
+
+    // variables needing a phi at the top of each block
+    let mut phis : Vec<Vec<String>> = vec![Vec::new(); cfg.blocks.len()];
+    let mut vars = def_blocks.keys().cloned().collect::<Vec<String>>();
+    vars.sort();
+    for var in vars.iter() {
+        let mut worklist = def_blocks[var].clone();
+        let mut placed : HashSet<usize> = HashSet::new();
+        while let Some(b) = worklist.pop() {
+            for y in frontiers[b].iter() {
+                if placed.contains(y) || !liveness.live_in[cfg.blocks[*y].start].contains(var) {
+                    continue;
+                }
+                placed.insert(*y);
+                phis[*y].push(var.clone());
+                worklist.push(*y);
+            }
+        }
+    }
+
+    let mut blocks : Vec<Vec<Line>> = cfg.blocks.iter()
+        .map(|block| func.body[block.start..block.end].to_vec())
+        .collect();
+    let labels = blocks.iter().map(|lines| lines[0].label.clone().unwrap_or_default()).collect::<Vec<String>>();
+    for (b, vars) in phis.iter().enumerate() {
+        if vars.is_empty() {
+            continue;
+        }
+        let at = phi_position(&mut blocks[b]);
+        for (i, var) in vars.iter().enumerate() {
+            let args = cfg.blocks[b].preds.iter()
+                .filter(|pred| idom[**pred].is_some())
+                .map(|pred| (Operand::Var(var.clone()), labels[*pred].clone()))
+                .collect();
+            blocks[b].insert(at + i, Line { label: None, quad: Quad::Phi { dest: Operand::Var(var.clone()), args } });
+        }
+    }
+
+    let mut children : Vec<Vec<usize>> = vec![Vec::new(); cfg.blocks.len()];
+    for (b, dom) in idom.iter().enumerate() {
+        if let Some(dom) = dom {
+            if *dom != b {
+                children[*dom].push(b);
+            }
+        }
+    }
+
+    let mut renamer = Renamer {
+        is_local: func.locals.iter().map(|(id, _)| id.clone()).collect(),
+        versions: HashMap::new(),
+        stacks: HashMap::new(),
+        new_locals: Vec::new(),
+    };
+    renamer.rename(0, &mut blocks, &cfg, &children, &labels);
+
+    for (var, version) in renamer.new_locals {
+        let var_type = func.locals.iter().find(|(id, _)| *id == var).unwrap().1;
+        func.locals.push((version, var_type));
+    }
+    func.body = blocks.into_iter().flatten().collect();
+}
+
+/// Replaces every phi with copies at the end of the predecessors it names,
+/// splitting the edges out of an `IFZ` so the copies only run on their edge.
+pub fn destruct(func: &mut IRFunction) {
+    let cfg = Cfg::build(func);
+    let labels = cfg.blocks.iter().map(|block| func.body[block.start].label.clone()).collect::<Vec<Option<String>>>();
+    let mut next_label = next_label_index(func);
+
+    let mut inserts : Vec<(usize, Vec<Line>)> = Vec::new();
+    let mut retargets : Vec<(usize, String)> = Vec::new();
+    let mut tails : Vec<Line> = Vec::new();
+
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        let phis = func.body[block.start..block.end].iter()
+            .filter_map(|line| match &line.quad {
+                Quad::Phi { dest, args } => Some((dest.clone(), args.clone())),
+                _ => None
+            })
+            .collect::<Vec<(Operand, Vec<(Operand, String)>)>>();
+        if phis.is_empty() {
+            continue;
+        }
+        let label = labels[b].clone().unwrap();
+
+        for pred in block.preds.iter() {
+            let pred_label = match &labels[*pred] {
+                Some(x) => x,
+                None => continue
+            };
+            let moves = phis.iter()
+                .filter_map(|(dest, args)| {
+                    let (arg, _) = args.iter().find(|(_, lbl)| lbl == pred_label)?;
+                    Some((dest.clone(), arg.clone()))
+                })
+                .filter(|(dest, arg)| dest != arg)
+                .collect::<Vec<(Operand, Operand)>>();
+            if moves.is_empty() {
+                continue;
+            }
+            let copies = sequentialize(moves, &mut func.locals);
+
+            let last = cfg.blocks[*pred].end - 1;
+            match &func.body[last].quad {
+                Quad::IfZ { target, .. } if *target == label && cfg.blocks[*pred].end != block.start => {
+                    // the jump gets its own block at the bottom of the function
+                    let split = format!("lbl_{}.{}", func.name, next_label);
+                    next_label += 1;
+                    retargets.push((last, split.clone()));
+                    tails.push(Line { label: Some(split), quad: Quad::Nop });
+                    tails.extend(copies);
+                    tails.push(Line { label: None, quad: Quad::Goto(label.clone()) });
+                },
+                Quad::IfZ { target, .. } if *target == label => inserts.push((last, copies)),
+                Quad::Goto(_) => inserts.push((last, copies)),
+                _ => inserts.push((last + 1, copies)),
+            }
+        }
+    }
+
+    for (line, target) in retargets {
+        if let Quad::IfZ { target: old, .. } = &mut func.body[line].quad {
+            *old = target;
+        }
+    }
+    inserts.sort_by_key(|(at, _)| *at);
+    for (at, copies) in inserts.into_iter().rev() {
+        func.body.splice(at..at, copies);
+    }
+    func.body.retain(|line| !matches!(line.quad, Quad::Phi { .. }));
+
+    if !tails.is_empty() {
+        let leave = func.body.len() - 1;
+        let leave_label = match &func.body[leave].label {
+            Some(x) => x.clone(),
+            None => {
+                let lbl = format!("lbl_{}.{}", func.name, next_label);
+                func.body[leave].label = Some(lbl.clone());
+                lbl
+            }
+        };
+        // whatever fell through into the leave has to jump over the new blocks
+        if !matches!(func.body[leave - 1].quad, Quad::Goto(_) | Quad::Exit) {
+            tails.insert(0, Line { label: None, quad: Quad::Goto(leave_label) });
+        }
+        func.body.splice(leave..leave, tails);
+    }
+
+    remove_block_labels(func);
+}
+
+/// Drops phi arguments from blocks that no longer jump to the phi.
+pub fn prune_phis(func: &mut IRFunction) {
+    let cfg = Cfg::build(func);
+    for block in cfg.blocks.iter() {
+        let preds = block.preds.iter()
+            .filter_map(|pred| func.body[cfg.blocks[*pred].start].label.clone())
+            .collect::<Vec<String>>();
+        for line in func.body[block.start..block.end].iter_mut() {
+            if let Quad::Phi { args, .. } = &mut line.quad {
+                args.retain(|(_, lbl)| preds.contains(lbl));
+            }
+        }
+    }
+}
+
+struct Renamer {
+    is_local: HashSet<String>,
+    versions: HashMap<String, usize>,
+    stacks: HashMap<String, Vec<String>>,
+    new_locals: Vec<(String, String)>,
+}
+
+impl Renamer {
+    fn current(&self, var: &str) -> String {
+        match self.stacks.get(var).and_then(|stack| stack.last()) {
+            Some(x) => x.clone(),
+            None => var.to_string()
+        }
+    }
+
+    fn define(&mut self, var: &str) -> String {
+        let version = self.versions.entry(var.to_string()).or_insert(0);
+        let name = if *version == 0 { var.to_string() } else { format!("{}.{}", var, version) };
+        *version += 1;
+        if name != var {
+            self.new_locals.push((var.to_string(), name.clone()));
+        }
+        self.stacks.entry(var.to_string()).or_default().push(name.clone());
+        name
+    }
+
+    fn rename(&mut self, b: usize, blocks: &mut [Vec<Line>], cfg: &Cfg, children: &[Vec<usize>], labels: &[String]) {
+        let mut defined : Vec<String> = Vec::new();
+
+        for line in blocks[b].iter_mut() {
+            if !matches!(line.quad, Quad::Phi { .. }) {
+                for op in line.quad.uses_mut() {
+                    if let Operand::Var(var) = op {
+                        if self.is_local.contains(var) {
+                            *var = self.current(var);
+                        }
+                    }
+                }
+            }
+            if let Some(Operand::Var(var)) = line.quad.dest_mut() {
+                if self.is_local.contains(var) {
+                    defined.push(var.clone());
+                    *var = self.define(&var.clone());
+                }
+            }
+        }
+
+        for succ in cfg.blocks[b].succs.iter() {
+            for line in blocks[*succ].iter_mut() {
+                if let Quad::Phi { args, .. } = &mut line.quad {
+                    for (arg, pred) in args.iter_mut() {
+                        if *pred != labels[b] {
+                            continue;
+                        }
+                        // phi arguments still carry the original name until here
+                        if let Operand::Var(var) = arg {
+                            *var = self.current(&var.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        for child in children[b].iter() {
+            self.rename(*child, blocks, cfg, children, labels);
+        }
+
+        for var in defined {
+            self.stacks.get_mut(&var).unwrap().pop();
+        }
+    }
+}
+
+/// Where phis go in a block: after its labelled `nop`, which is added if the
+/// label sits on some other quad.
+fn phi_position(lines: &mut Vec<Line>) -> usize {
+    match (&lines[0].label, &lines[0].quad) {
+        (Some(_), Quad::Nop) => 1,
+        (Some(_), _) => {
+            let label = lines[0].label.take();
+            lines.insert(0, Line { label, quad: Quad::Nop });
+            1
+        },
+        _ => 0
+    }
+}
+
+/// Gives every block a label for phi arguments to refer to.
+fn label_blocks(func: &mut IRFunction) {
+    let cfg = Cfg::build(func);
+    let mut next = next_label_index(func);
+    for block in cfg.blocks.iter() {
+        if func.body[block.start].label.is_none() {
+            func.body[block.start].label = Some(format!("lbl_{}.{}", func.name, next));
+            next += 1;
+        }
+    }
+}
+
+/// Block labels nothing jumps to any more go away again, along with the
+/// `nop`s that only carried them.
+fn remove_block_labels(func: &mut IRFunction) {
+    let prefix = format!("lbl_{}.", func.name);
+    let targets = func.body.iter().filter_map(|line| match &line.quad {
+        Quad::Goto(target) | Quad::IfZ { target, .. } => Some(target.clone()),
+        _ => None
+    }).collect::<HashSet<String>>();
+
+    for line in func.body.iter_mut() {
+        let unused = match &line.label {
+            Some(lbl) => lbl.starts_with(&prefix) && !targets.contains(lbl),
+            None => false
+        };
+        if unused {
+            line.label = None;
+        }
+    }
+    func.body.retain(|line| line.label.is_some() || line.quad != Quad::Nop);
+}
+
+fn next_label_index(func: &IRFunction) -> usize {
+    let prefix = format!("lbl_{}.", func.name);
+    func.body.iter()
+        .filter_map(|line| line.label.as_ref()?.strip_prefix(&prefix)?.parse::<usize>().ok())
+        .map(|n| n + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Orders the copies of one edge so they behave as if they all happened at
+/// once. When a copy would clobber another's source, every source is first
+/// saved in a fresh temporary.
+fn sequentialize(moves: Vec<(Operand, Operand)>, locals: &mut Vec<(String, Variable3ACType)>) -> Vec<Line> {
+    let copy = |dest: Operand, src: Operand| Line { label: None, quad: Quad::Assign { dest, src } };
+    let clobbers = moves.iter().any(|(_, src)| moves.iter().any(|(dest, _)| dest == src));
+    if !clobbers {
+        return moves.into_iter().map(|(dest, src)| copy(dest, src)).collect();
+    }
+
+    let mut saves = Vec::new();
+    let mut restores = Vec::new();
+    for (dest, src) in moves {
+        let var = dest.var().unwrap().clone();
+        let saved = format!("{}.in", var);
+        if !locals.iter().any(|(id, _)| *id == saved) {
+            let var_type = locals.iter().find(|(id, _)| *id == var).unwrap().1;
+            locals.push((saved.clone(), var_type));
+        }
+        saves.push(copy(Operand::Var(saved.clone()), src));
+        restores.push(copy(dest, Operand::Var(saved)));
+    }
+    saves.extend(restores);
+    saves
+}
@@ -0,0 +1,269 @@
+use super::{IRFunction, IRProgram, Quad, Operand, BinaryOp};
+use super::cfg::Cfg;
+use super::verify::verify;
+use super::ssa::{construct, destruct, in_ssa};
+use super::liveness::Liveness;
+use std::collections::HashSet;
+
+// while (i < 3) { if (i == 1) { x = 1; } else { x = 2; } i = i + 1; }
+const LOOP : &str = "[BEGIN GLOBALS]
+x-int
+[END GLOBALS]
+\t
+[BEGIN main LOCALS]
+i-int
+tmp0-bool
+tmp1-bool
+tmp2-int
+[END main LOCALS]
+
+main:\tenter main
+\t[i] := 0
+\t
+lbl_1:\tnop
+\t[tmp0] := [i] LT64 3
+\tIFZ [tmp0] goto lbl_2
+\t[tmp1] := [i] EQ64 1
+\tIFZ [tmp1] goto lbl_4
+\t[x] := 1
+\tgoto lbl_3
+
+lbl_4:\tnop
+\t[x] := 2
+
+lbl_3:\tnop
+\t[tmp2] := [i] ADD64 1
+\t[i] := [tmp2]
+\tgoto lbl_1
+
+lbl_2:\tnop
+\tsetret 0
+\tgoto lbl_0
+
+lbl_0:\tleave main
+";
+
+fn main_fn() -> IRFunction {
+    IRProgram::parse(LOOP).functions.remove(0)
+}
+
+#[test]
+fn program_round_trip() {
+    let program = IRProgram::parse(LOOP);
+    assert_eq!(program.functions.len(), 1);
+    assert_eq!(program.globals, "[BEGIN GLOBALS]\nx-int\n[END GLOBALS]");
+
+    let reparsed = IRProgram::parse(&program.to_string());
+    assert_eq!(reparsed.functions[0].body, program.functions[0].body);
+    assert_eq!(reparsed.functions[0].locals, program.functions[0].locals);
+}
+
+#[test]
+fn cfg_blocks() {
+    let func = main_fn();
+    let cfg = Cfg::build(&func);
+    let ranges = cfg.blocks.iter().map(|b| (b.start, b.end)).collect::<Vec<(usize, usize)>>();
+    assert_eq!(ranges, vec![(0, 2), (2, 5), (5, 7), (7, 9), (9, 11), (11, 15), (15, 18), (18, 19)]);
+
+    // every block but the entry starts at a label or right after a jump
+    assert!(matches!(func.body[cfg.blocks[2].start].quad, Quad::Binary { .. }));
+    assert_eq!(func.body[cfg.blocks[7].start].quad, Quad::Leave("main".to_string()));
+}
+
+#[test]
+fn cfg_edges() {
+    let cfg = Cfg::build(&main_fn());
+    let succs = cfg.blocks.iter().map(|b| b.succs.clone()).collect::<Vec<Vec<usize>>>();
+    assert_eq!(succs, vec![vec![1], vec![2, 6], vec![3, 4], vec![5], vec![5], vec![1], vec![7], vec![]]);
+
+    let preds = cfg.blocks.iter().map(|b| b.preds.clone()).collect::<Vec<Vec<usize>>>();
+    assert_eq!(preds, vec![vec![], vec![0, 5], vec![1], vec![2], vec![2], vec![3, 4], vec![1], vec![6]]);
+    assert_eq!(cfg.block_of(12), 5);
+}
+
+#[test]
+fn cfg_dot() {
+    let func = main_fn();
+    let dot = Cfg::build(&func).to_dot(&func);
+    assert!(dot.starts_with("\tsubgraph \"cluster_main\" {"));
+    assert!(dot.contains("\"main_B1\" -> \"main_B2\" [label=\"true\"];"));
+    assert!(dot.contains("\"main_B1\" -> \"main_B6\" [label=\"false\"];"));
+    assert!(dot.contains("\"main_B5\" -> \"main_B1\";"));
+}
+
+#[test]
+fn verifies_well_formed_program() {
+    assert_eq!(verify(&IRProgram::parse(LOOP)), Ok(()));
+}
+
+#[test]
+fn verifies_builtin_calls() {
+    let mut program = IRProgram::parse(LOOP);
+    program.functions[0].body[1].quad = Quad::Call("len".to_string());
+    assert_eq!(verify(&program), Ok(()));
+
+    program.functions[0].body[1].quad = Quad::Call("length".to_string());
+    assert!(verify(&program).unwrap_err().contains("missing function length"));
+}
+
+#[test]
+fn verifier_catches_broken_ir() {
+    let mut program = IRProgram::parse(LOOP);
+    program.functions[0].body[8].quad = Quad::Goto("lbl_9".to_string());
+    assert!(verify(&program).unwrap_err().contains("missing label lbl_9"));
+
+    let mut program = IRProgram::parse(LOOP);
+    program.functions[0].body.pop();
+    assert!(verify(&program).unwrap_err().contains("leave"));
+
+    let mut program = IRProgram::parse(LOOP);
+    program.functions[0].locals.retain(|(id, _)| id != "tmp2");
+    assert!(verify(&program).unwrap_err().contains("undeclared variable tmp2"));
+
+    let mut program = IRProgram::parse(LOOP);
+    program.functions[0].body[1].quad = Quad::SetArg(1, Operand::Int(3));
+    assert!(verify(&program).unwrap_err().contains("passes an argument to no call"));
+}
+
+#[test]
+fn parses_arguments() {
+    let func = IRFunction::parse("f LOCALS]\nn-int\n[END f LOCALS]\n\nf:\tenter f\n\tgetarg 1 [n]\n\tsetarg 2 [n]\n\tsetarg 1 str0\n\tcall f\nlbl_0:\tleave f\n");
+    assert_eq!(func.body[1].quad, Quad::GetArg(1, Operand::Var("n".to_string())));
+    assert_eq!(func.body[2].quad.uses(), vec!["n"]);
+    assert_eq!(func.body[3].quad, Quad::SetArg(1, Operand::Str("str0".to_string())));
+    assert_eq!(func.body[1].to_string(), "\tgetarg 1 [n]");
+}
+
+#[test]
+fn parses_string_compares() {
+    let func = IRFunction::parse("f LOCALS]\ns-string\ntmp0-bool\n[END f LOCALS]\n\nf:\tenter f\n\t[tmp0] := [s] STRNEQ64 str0\nlbl_0:\tleave f\n");
+    assert_eq!(func.locals[0], ("s".to_string(), crate::assembly::three_ac::Variable3ACType::String));
+    assert!(matches!(func.body[1].quad, Quad::Binary { op: BinaryOp::StrNeq, .. }));
+    // the runtime compares them, so registers are saved around it like a call
+    assert!(func.body[1].quad.is_call());
+    assert_eq!(func.body[1].to_string(), "\t[tmp0] := [s] STRNEQ64 str0");
+}
+
+#[test]
+fn parses_array_accesses() {
+    let func = IRFunction::parse("f LOCALS]\na-int\ntmp0-int\n[END f LOCALS]\n\nf:\tenter f\n\t[tmp0] := LOAD [a] 2\n\tSTORE [a] [tmp0] 7\nlbl_0:\tleave f\n");
+    assert_eq!(func.body[1].quad, Quad::Load {
+        dest: Operand::Var("tmp0".to_string()),
+        array: Operand::Var("a".to_string()),
+        index: Operand::Int(2)
+    });
+    assert_eq!(func.body[1].quad.def(), Some(&"tmp0".to_string()));
+    // a store writes memory, not a variable
+    assert_eq!(func.body[2].quad.def(), None);
+    assert_eq!(func.body[2].quad.uses(), vec!["a", "tmp0"]);
+    assert_eq!(func.body[2].to_string(), "\tSTORE [a] [tmp0] 7");
+}
+
+#[test]
+fn dominators() {
+    let cfg = Cfg::build(&main_fn());
+    let idom = cfg.dominators();
+    assert_eq!(idom, vec![Some(0), Some(0), Some(1), Some(2), Some(2), Some(2), Some(1), Some(6)]);
+
+    let frontiers = cfg.dominance_frontiers(&idom);
+    assert_eq!(frontiers[3], vec![5]);
+    assert_eq!(frontiers[5], vec![1]);
+    assert!(frontiers[0].is_empty());
+}
+
+#[test]
+fn natural_loops() {
+    let cfg = Cfg::build(&main_fn());
+    let loops = cfg.natural_loops();
+    assert_eq!(loops.len(), 1);
+    assert_eq!(loops[0].header, 1);
+    assert_eq!(loops[0].blocks, vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn ssa_assigns_once() {
+    let mut func = main_fn();
+    construct(&mut func);
+
+    let mut defs = func.body.iter().filter_map(|line| line.quad.def()).filter(|var| func.is_local(var)).collect::<Vec<&String>>();
+    let count = defs.len();
+    defs.sort();
+    defs.dedup();
+    assert_eq!(defs.len(), count);
+
+    // i meets its increment at the loop head
+    let head = func.label_index("lbl_1").unwrap();
+    assert_eq!(func.body[head + 1].quad.to_string(), "[i.1] := PHI [i] main [i.2] lbl_3");
+    assert!(func.is_local("i.2"));
+
+    let program = IRProgram { globals: IRProgram::parse(LOOP).globals, functions: vec![func] };
+    assert_eq!(verify(&program), Ok(()));
+}
+
+#[test]
+fn ssa_round_trip() {
+    let mut func = main_fn();
+    construct(&mut func);
+    destruct(&mut func);
+
+    assert!(!in_ssa(&func));
+    let text = func.body.iter().map(|line| line.to_string()).collect::<Vec<String>>().join("\n");
+    assert!(text.contains("\t[i.1] := [i]\nlbl_1:\tnop"));
+    assert!(text.contains("\t[i.2] := [tmp2]\n\t[i.1] := [i.2]\n\tgoto lbl_1"));
+
+    let program = IRProgram { globals: IRProgram::parse(LOOP).globals, functions: vec![func] };
+    assert_eq!(verify(&program), Ok(()));
+}
+
+#[test]
+fn ssa_destruction_swaps_in_parallel() {
+    let program = IRProgram::parse("[BEGIN GLOBALS]
+[END GLOBALS]
+\t
+[BEGIN main LOCALS]
+a-int
+b-int
+a.1-int
+b.1-int
+[END main LOCALS]
+
+main:\tenter main
+\t[a] := 1
+\t[b] := 2
+
+lbl_1:\tnop
+\t[a.1] := PHI [a] main [b.1] lbl_1
+\t[b.1] := PHI [b] main [a.1] lbl_1
+\tWRITE a.1
+\tIFZ [b.1] goto lbl_1
+lbl_0:\tleave main
+");
+    let mut func = program.functions.into_iter().next().unwrap();
+    destruct(&mut func);
+
+    let text = func.body.iter().map(|line| line.to_string()).collect::<Vec<String>>().join("\n");
+    // the back edge leaves through IFZ, so it gets a block of its own
+    assert!(text.contains("\tIFZ [b.1] goto lbl_main.0"));
+    assert!(text.contains("lbl_main.0:\tnop\n\t[a.1.in] := [b.1]\n\t[b.1.in] := [a.1]\n\t[a.1] := [a.1.in]\n\t[b.1] := [b.1.in]\n\tgoto lbl_1"));
+}
+
+#[test]
+fn liveness() {
+    let func = main_fn();
+    let liveness = Liveness::analyze(&func);
+    let set = |vars: &[&str]| vars.iter().map(|x| x.to_string()).collect::<HashSet<String>>();
+
+    // i is live around the whole loop, from its first assignment to the exit test
+    assert_eq!(liveness.live_in[1], set(&[]));
+    for line in 2..=11 {
+        assert!(liveness.live_in[line].contains("i"), "i is dead on line {}", line);
+    }
+    // temporaries only live from their definition to their one use
+    assert_eq!(liveness.live_out[3], set(&["i", "tmp0"]));
+    assert_eq!(liveness.live_out[4], set(&["i"]));
+    assert_eq!(liveness.live_out[12], set(&["tmp2"]));
+    assert_eq!(liveness.live_across(12, &func), set(&[]));
+    // the global x is never tracked, and nothing is live once the loop is left
+    assert!(liveness.live_in.iter().chain(liveness.live_out.iter()).all(|vars| !vars.contains("x")));
+    assert_eq!(liveness.live_in[15], set(&[]));
+}
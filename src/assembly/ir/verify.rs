@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+use super::{IRProgram, IRFunction, Quad, Operand};
+use crate::parser::ast::builtin::builtin;
+
+/// Checks the invariants every pass relies on and the backend assumes: each
+/// function is bracketed by its own `enter`/`leave`, labels are unique and
+/// every jump, call, variable and string refers to something that exists.
+/// Arguments are passed as a run of `setarg`s straight above their `call`.
+pub fn verify(program: &IRProgram) -> Result<(), String> {
+    let mut globals : HashSet<&str> = HashSet::new();
+    for line in program.globals.lines().skip(1) {
+        if line.starts_with("[END ") {
+            break;
+        }
+        // `name-type` for variables, `strN "..."` for strings
+        match line.split_once(' ') {
+            Some((lbl, _)) => globals.insert(lbl),
+            None => globals.insert(line.rsplit_once('-').map(|(id, _)| id).unwrap_or(line)),
+        };
+    }
+    let functions = program.functions.iter().map(|func| func.name.as_str()).collect::<HashSet<&str>>();
+
+    for func in program.functions.iter() {
+        verify_function(func, &globals, &functions).map_err(|err| format!("in {}: {}", func.name, err))?;
+    }
+    Ok(())
+}
+
+fn verify_function(func: &IRFunction, globals: &HashSet<&str>, functions: &HashSet<&str>) -> Result<(), String> {
+    match func.body.first().map(|line| &line.quad) {
+        Some(Quad::Enter(id)) if *id == func.name => (),
+        _ => return Err("the body doesn't start with its enter quad".to_string())
+    }
+    match func.body.last().map(|line| &line.quad) {
+        Some(Quad::Leave(id)) if *id == func.name => (),
+        _ => return Err("the body doesn't end with its leave quad".to_string())
+    }
+
+    let mut labels : HashSet<&str> = HashSet::new();
+    for line in func.body.iter() {
+        if let Some(lbl) = &line.label {
+            if !labels.insert(lbl) {
+                return Err(format!("label {} is defined twice", lbl));
+            }
+        }
+    }
+
+    for (i, line) in func.body.iter().enumerate() {
+        match &line.quad {
+            Quad::Enter(_) if i > 0 => return Err(format!("line {} enters the function again", i)),
+            Quad::Leave(_) if i + 1 < func.body.len() => return Err(format!("line {} leaves before the end", i)),
+            Quad::Goto(target) | Quad::IfZ { target, .. } if !labels.contains(target.as_str()) => {
+                return Err(format!("line {} jumps to missing label {}", i, target));
+            },
+            Quad::Call(callee) if !functions.contains(callee.as_str()) && builtin(callee).is_none() => {
+                return Err(format!("line {} calls missing function {}", i, callee));
+            },
+            Quad::SetArg(..) => {
+                let next = &func.body[i + 1];
+                if next.label.is_some() || !matches!(next.quad, Quad::SetArg(..) | Quad::Call(_)) {
+                    return Err(format!("line {} passes an argument to no call", i));
+                }
+            },
+            Quad::Phi { args, .. } => {
+                if let Some((_, pred)) = args.iter().find(|(_, pred)| !labels.contains(pred.as_str())) {
+                    return Err(format!("line {} takes a phi argument from missing block {}", i, pred));
+                }
+            },
+            _ => ()
+        }
+
+        for op in line.quad.operands() {
+            match op {
+                Operand::Var(var) if !func.is_local(var) && !globals.contains(var.as_str()) => {
+                    return Err(format!("line {} uses undeclared variable {}", i, var));
+                },
+                Operand::Str(lbl) if !globals.contains(lbl.as_str()) => {
+                    return Err(format!("line {} uses undeclared string {}", i, lbl));
+                },
+                _ => ()
+            }
+        }
+    }
+    Ok(())
+}
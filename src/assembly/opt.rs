@@ -0,0 +1,120 @@
+use super::ir::IRProgram;
+use super::ir::verify::verify;
+use super::ir::ssa;
+
+mod dataflow;
+pub mod const_prop;
+pub mod dce;
+pub mod cse;
+pub mod copy_prop;
+pub mod licm;
+pub mod inline;
+pub mod tail_call;
+
+#[cfg(test)]
+mod tests;
+
+/// A named transformation over the whole program.
+#[derive(Clone, Copy)]
+pub struct Pass {
+    pub name: &'static str,
+    pub run: fn(&mut IRProgram),
+}
+
+/// Every pass `--passes=` can ask for, by name.
+pub const PASSES: [Pass; 9] = [
+    Pass { name: "const-prop", run: const_prop::propagate_program },
+    Pass { name: "cse", run: cse::eliminate_program },
+    Pass { name: "copy-prop", run: copy_prop::propagate_program },
+    Pass { name: "licm", run: licm::hoist_program },
+    Pass { name: "inline", run: inline::inline_program },
+    Pass { name: "tail-call", run: tail_call::eliminate_program },
+    Pass { name: "dce", run: dce::eliminate_program },
+    Pass { name: "ssa", run: ssa::construct_program },
+    Pass { name: "out-of-ssa", run: ssa::destruct_program },
+];
+
+/// The names of every pass in `PASSES`, as listed in help and error messages.
+pub fn pass_list() -> String {
+    PASSES.iter().map(|pass| pass.name).collect::<Vec<&str>>().join(", ")
+}
+
+/// Runs a pipeline of passes over the 3AC. Debug builds verify the IR after
+/// every pass so a broken transformation is caught where it happens.
+pub struct PassManager {
+    passes: Vec<Pass>,
+    print_after: Vec<String>,
+}
+
+impl PassManager {
+    /// The pipeline behind `-O<opt_level>`. Anything above 2 gets the -O2 pipeline.
+    /// Inlining only happens from -O2 up, since it trades code size for speed.
+    pub fn preset(opt_level: u8) -> PassManager {
+        let names : &[&str] = match opt_level {
+            0 => &[],
+            1 => &["tail-call", "const-prop", "cse", "copy-prop", "licm", "dce"],
+            _ => &["tail-call", "inline", "ssa", "const-prop", "cse", "copy-prop", "licm", "dce", "out-of-ssa", "copy-prop", "const-prop", "dce"],
+        };
+        PassManager::from_names(names).unwrap()
+    }
+
+    /// A custom pipeline, run in the given order. Fails on the first unknown name.
+    pub fn from_names(names: &[&str]) -> Result<PassManager, String> {
+        let mut passes = Vec::new();
+        for name in names.iter() {
+            match PASSES.iter().find(|pass| pass.name == *name) {
+                Some(pass) => passes.push(*pass),
+                None => return Err(format!("Unknown pass {}", name))
+            }
+        }
+        Ok(PassManager { passes, print_after: Vec::new() })
+    }
+
+    /// Dumps the IR to stderr each time the named pass finishes.
+    pub fn print_after(&mut self, name: String) -> Result<(), String> {
+        match PASSES.iter().find(|pass| pass.name == name) {
+            Some(_) => self.print_after.push(name),
+            None => return Err(format!("Unknown pass {}", name))
+        }
+        Ok(())
+    }
+
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|pass| pass.name).collect()
+    }
+
+    /// Runs the pipeline over the text produced by `convert_3ac`. An empty
+    /// pipeline hands it back untouched, and 3AC that is malformed to begin with
+    /// is an error. The backend can't read phis, so a pipeline left in SSA form
+    /// is taken out of it.
+    pub fn run(&self, three_ac: String) -> Result<String, String> {
+        if self.passes.is_empty() {
+            return Ok(three_ac);
+        }
+
+        // passes count on well-formed IR, so a lowering bug is reported here
+        // instead of the optimizations quietly being skipped
+        let mut program = IRProgram::parse(&three_ac);
+        if let Err(err) = verify(&program) {
+            return Err(format!("IR verification failed before optimizing: {}", err));
+        }
+        for pass in self.passes.iter() {
+            (pass.run)(&mut program);
+            self.check(&program, pass.name);
+            if self.print_after.iter().any(|name| name == pass.name) {
+                eprintln!("; IR after {}\n{}", pass.name, program);
+            }
+        }
+        ssa::destruct_program(&mut program);
+        self.check(&program, "out-of-ssa");
+        Ok(program.to_string())
+    }
+
+    fn check(&self, program: &IRProgram, after: &str) {
+        if cfg!(debug_assertions) {
+            if let Err(err) = verify(program) {
+                panic!("IR verification failed after {}: {}", after, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use crate::assembly::ir::{IRProgram, IRFunction, Line, Quad, Operand, UnaryOp, BinaryOp};
+use crate::assembly::ir::cfg::Cfg;
+use super::dataflow::forward;
+
+/// Variables known to hold one literal value at some point of the program.
+type Consts = HashMap<String, Operand>;
+
+/// Folds constant expressions and propagates known values through every
+/// function, then repeats while simplified branches keep uncovering more.
+pub fn propagate_program(program: &mut IRProgram) {
+    let globals = constant_globals(program);
+    for func in program.functions.iter_mut() {
+        while propagate(func, &globals) {}
+    }
+}
+
+/// A global is constant when its only definition in the whole program is a
+/// literal assignment at the top of `main`, which is where initializers
+/// (and so every `perfect` global) end up.
+fn constant_globals(program: &IRProgram) -> Consts {
+    let mut defs : HashMap<String, usize> = HashMap::new();
+    for func in program.functions.iter() {
+        for line in func.body.iter() {
+            if let Some(var) = line.quad.def() {
+                if !func.is_local(var) {
+                    *defs.entry(var.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    let mut globals = Consts::new();
+    let main = match program.functions.iter().find(|func| func.name == "main") {
+        Some(x) => x,
+        None => return globals
+    };
+    let mut read = Vec::new();
+    for line in main.body.iter().skip(1) {
+        if line.label.is_some() || line.quad.is_call() || line.quad.def().is_none() {
+            break;
+        }
+        read.extend(line.quad.uses().into_iter().cloned());
+        if let Quad::Assign { dest: Operand::Var(var), src } = &line.quad {
+            if is_const(src) && defs.get(var) == Some(&1) && !read.contains(var) && !main.is_local(var) {
+                globals.insert(var.clone(), src.clone());
+            }
+        }
+    }
+    globals
+}
+
+/// One round of forward dataflow over the CFG followed by a rewrite of every
+/// reachable block. Returns whether anything changed.
+fn propagate(func: &mut IRFunction, globals: &Consts) -> bool {
+    let cfg = Cfg::build(func);
+    let block_in = forward(func, &cfg, |quad, state| transfer(quad, state, func));
+
+    let mut body : Vec<Line> = Vec::new();
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        let lines = &func.body[block.start..block.end];
+        let mut state = match &block_in[b] {
+            Some(x) => x.clone(),
+            None => {
+                body.extend(lines.iter().cloned());
+                continue;
+            }
+        };
+        for line in lines.iter() {
+            if let Some(line) = rewrite(line, &state, globals) {
+                transfer(&line.quad, &mut state, func);
+                body.push(line);
+            }
+        }
+    }
+
+    let changed = body != func.body;
+    func.body = body;
+    changed
+}
+
+fn transfer(quad: &Quad, state: &mut Consts, func: &IRFunction) {
+    let var = match quad.def() {
+        Some(x) => x,
+        None => return
+    };
+    match quad {
+        Quad::Assign { src, .. } if is_const(src) && func.is_local(var) => {
+            state.insert(var.clone(), src.clone());
+        },
+        _ => { state.remove(var); }
+    }
+}
+
+/// Substitutes known values into the quad's operands and folds what it can.
+/// `None` means the line can go entirely.
+fn rewrite(line: &Line, state: &Consts, globals: &Consts) -> Option<Line> {
+    let value = |op: &Operand| match op.var().and_then(|var| state.get(var).or(globals.get(var))) {
+        Some(x) => x.clone(),
+        None => op.clone()
+    };
+
+    let quad = match &line.quad {
+        Quad::Assign { dest, src } => Quad::Assign { dest: dest.clone(), src: value(src) },
+        Quad::Unary { dest, op, src } => {
+            let src = value(src);
+            match fold_unary(*op, &src) {
+                Some(x) => Quad::Assign { dest: dest.clone(), src: x },
+                None => Quad::Unary { dest: dest.clone(), op: *op, src }
+            }
+        },
+        Quad::Binary { dest, lhs, op, rhs } => {
+            let (lhs, rhs) = (value(lhs), value(rhs));
+            match fold_binary(*op, &lhs, &rhs) {
+                Some(x) => Quad::Assign { dest: dest.clone(), src: x },
+                None => Quad::Binary { dest: dest.clone(), lhs, op: *op, rhs }
+            }
+        },
+        Quad::Load { dest, array, index } => Quad::Load { dest: dest.clone(), array: value(array), index: value(index) },
+        Quad::Store { array, index, src } => Quad::Store { array: value(array), index: value(index), src: value(src) },
+        Quad::Write(x) => Quad::Write(value(x)),
+        Quad::SetRet(x) => Quad::SetRet(value(x)),
+        Quad::SetArg(i, x) => Quad::SetArg(*i, value(x)),
+        Quad::IfZ { cond, target } => match value(cond) {
+            Operand::False | Operand::Int(0) => Quad::Goto(target.clone()),
+            Operand::True | Operand::Int(_) => {
+                // the branch is never taken, but a label on it may still be jumped to
+                line.label.as_ref()?;
+                Quad::Nop
+            },
+            cond => Quad::IfZ { cond, target: target.clone() }
+        },
+        quad => quad.clone()
+    };
+    Some(Line { label: line.label.clone(), quad })
+}
+
+fn is_const(op: &Operand) -> bool {
+    matches!(op, Operand::Int(_) | Operand::Float(_) | Operand::Char(_) | Operand::True | Operand::False)
+}
+
+fn from_bool(b: bool) -> Operand {
+    if b { Operand::True } else { Operand::False }
+}
+
+fn as_bool(op: &Operand) -> Option<bool> {
+    match op {
+        Operand::True => Some(true),
+        Operand::False => Some(false),
+        _ => None
+    }
+}
+
+// a result that overflows is left for the program to compute, so it wraps or
+// traps the way the program was compiled to
+fn from_int(x: Option<i64>) -> Option<Operand> {
+    x.map(Operand::Int)
+}
+
+// infinities and NaN have no literal to write them as, so those are computed at runtime
+fn from_float(x: f64) -> Option<Operand> {
+    x.is_finite().then(|| Operand::Float(x.to_bits()))
+}
+
+fn fold_unary(op: UnaryOp, src: &Operand) -> Option<Operand> {
+    match (op, src) {
+        (UnaryOp::Neg, Operand::Int(x)) => from_int(x.checked_neg()),
+        (UnaryOp::BitNot, Operand::Int(x)) => from_int(Some(!x)),
+        (UnaryOp::FNeg, Operand::Float(x)) => from_float(-f64::from_bits(*x)),
+        (UnaryOp::Not, x) => Some(from_bool(!as_bool(x)?)),
+        _ => None
+    }
+}
+
+fn fold_binary(op: BinaryOp, lhs: &Operand, rhs: &Operand) -> Option<Operand> {
+    use BinaryOp::*;
+    if let (Operand::Int(a), Operand::Int(b)) = (lhs, rhs) {
+        let (a, b) = (*a, *b);
+        return match op {
+            Add => from_int(a.checked_add(b)),
+            Sub => from_int(a.checked_sub(b)),
+            Mult => from_int(a.checked_mul(b)),
+            Div => from_int(a.checked_div(b)),
+            Mod => from_int(a.checked_rem(b)),
+            BitAnd => from_int(Some(a & b)),
+            BitOr => from_int(Some(a | b)),
+            Xor => from_int(Some(a ^ b)),
+            // the hardware masks the count, so only fold counts it would keep
+            Shl | Shr if !(0..64).contains(&b) => None,
+            Shl => from_int(Some(a << b)),
+            Shr => from_int(Some(a >> b)),
+            Eq => Some(from_bool(a == b)),
+            Neq => Some(from_bool(a != b)),
+            Lt => Some(from_bool(a < b)),
+            Gt => Some(from_bool(a > b)),
+            Lte => Some(from_bool(a <= b)),
+            Gte => Some(from_bool(a >= b)),
+            _ => None
+        };
+    }
+
+    // chars are compared by their codes, with the int comparisons
+    if let (Operand::Char(a), Operand::Char(b)) = (lhs, rhs) {
+        return match op {
+            Eq => Some(from_bool(a == b)),
+            Neq => Some(from_bool(a != b)),
+            Lt => Some(from_bool(a < b)),
+            Gt => Some(from_bool(a > b)),
+            Lte => Some(from_bool(a <= b)),
+            Gte => Some(from_bool(a >= b)),
+            _ => None
+        };
+    }
+
+    if let (Operand::Float(a), Operand::Float(b)) = (lhs, rhs) {
+        let (a, b) = (f64::from_bits(*a), f64::from_bits(*b));
+        return match op {
+            FAdd => from_float(a + b),
+            FSub => from_float(a - b),
+            FMult => from_float(a * b),
+            FDiv => from_float(a / b),
+            FEq => Some(from_bool(a == b)),
+            FNeq => Some(from_bool(a != b)),
+            FLt => Some(from_bool(a < b)),
+            FGt => Some(from_bool(a > b)),
+            FLte => Some(from_bool(a <= b)),
+            FGte => Some(from_bool(a >= b)),
+            _ => None
+        };
+    }
+
+    // one known side can be enough, as long as dropping the other has no side effects
+    let pure = |op: &Operand| *op != Operand::Magic;
+    match (op, as_bool(lhs), as_bool(rhs)) {
+        (And, Some(false), _) if pure(rhs) => return Some(Operand::False),
+        (And, _, Some(false)) if pure(lhs) => return Some(Operand::False),
+        (Or, Some(true), _) if pure(rhs) => return Some(Operand::True),
+        (Or, _, Some(true)) if pure(lhs) => return Some(Operand::True),
+        _ => ()
+    }
+
+    let (a, b) = (as_bool(lhs)?, as_bool(rhs)?);
+    match op {
+        And => Some(from_bool(a && b)),
+        Or => Some(from_bool(a || b)),
+        Eq => Some(from_bool(a == b)),
+        Neq => Some(from_bool(a != b)),
+        _ => None
+    }
+}
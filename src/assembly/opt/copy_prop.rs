@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use crate::assembly::ir::{IRProgram, IRFunction, Line, Quad, Operand};
+use crate::assembly::ir::cfg::Cfg;
+use super::dataflow::forward;
+
+/// Variables known to hold a copy of another variable.
+type Copies = HashMap<String, Operand>;
+
+/// Folds single-use temporaries into the copy that reads them, so
+/// `getret [tmp0]` followed by `[x] := [tmp0]` becomes `getret [x]`, then
+/// reads the source of every remaining copy in place of its destination.
+pub fn propagate_program(program: &mut IRProgram) {
+    for func in program.functions.iter_mut() {
+        coalesce(func);
+        propagate(func);
+    }
+}
+
+fn coalesce(func: &mut IRFunction) {
+    let mut uses : HashMap<String, usize> = HashMap::new();
+    for line in func.body.iter() {
+        for var in line.quad.uses() {
+            *uses.entry(var.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let mut i = 0;
+    while i + 1 < func.body.len() {
+        let (line, next) = (&func.body[i], &func.body[i + 1]);
+        let folds = match (&line.quad, &next.quad) {
+            (Quad::Assign { .. } | Quad::Unary { .. } | Quad::Binary { .. } | Quad::GetRet(_) | Quad::Read(_),
+             Quad::Assign { dest, src: Operand::Var(src) }) => {
+                next.label.is_none() && line.quad.def() == Some(src) && func.is_local(src)
+                    && uses.get(src) == Some(&1) && dest.var() != Some(src)
+            },
+            _ => false
+        };
+        if folds {
+            let next = func.body.remove(i + 1);
+            if let (Some(dest), Quad::Assign { dest: copy, .. }) = (func.body[i].quad.dest_mut(), next.quad) {
+                *dest = copy;
+            }
+        }
+        i += 1;
+    }
+}
+
+fn propagate(func: &mut IRFunction) {
+    let cfg = Cfg::build(func);
+    let block_in = forward(func, &cfg, |quad, state| transfer(quad, state, func));
+
+    let mut body : Vec<Line> = Vec::new();
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        let lines = &func.body[block.start..block.end];
+        let mut state = match &block_in[b] {
+            Some(x) => x.clone(),
+            None => {
+                body.extend(lines.iter().cloned());
+                continue;
+            }
+        };
+        for line in lines.iter() {
+            let mut line = line.clone();
+            // phi arguments flow in along an edge, not from this block's state
+            if !matches!(line.quad, Quad::Phi { .. }) {
+                for op in line.quad.uses_mut() {
+                    if let Some(src) = op.var().and_then(|var| state.get(var)) {
+                        *op = src.clone();
+                    }
+                }
+            }
+            transfer(&line.quad, &mut state, func);
+            body.push(line);
+        }
+    }
+    func.body = body;
+}
+
+fn transfer(quad: &Quad, state: &mut Copies, func: &IRFunction) {
+    // the callee may write any global
+    if let Quad::Call(_) = quad {
+        state.retain(|dest, src| func.is_local(dest) && src.var().is_none_or(|var| func.is_local(var)));
+    }
+
+    let var = match quad.def() {
+        Some(x) => x,
+        None => return
+    };
+    state.retain(|dest, src| dest != var && src.var() != Some(var));
+    if let Quad::Assign { src: Operand::Var(src), .. } = quad {
+        if src != var {
+            state.insert(var.clone(), Operand::Var(src.clone()));
+        }
+    }
+}
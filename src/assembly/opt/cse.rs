@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use crate::assembly::ir::{IRProgram, IRFunction, Line, Quad, Operand, UnaryOp, BinaryOp};
+use crate::assembly::ir::cfg::Cfg;
+use super::dataflow::forward;
+
+/// A computation the pass can reuse. Commutative operators keep their
+/// operands in a fixed order so `a + b` and `b + a` meet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Expr {
+    Unary(UnaryOp, Operand),
+    Binary(BinaryOp, Operand, Operand),
+}
+
+/// Expressions known to be sitting in a variable, and which one.
+type Available = HashMap<Expr, String>;
+
+/// Replaces every recomputation of an expression that is already available,
+/// in the same block or on every path into it, with a copy of the variable
+/// holding it. Copy propagation and dce clean up the copies afterwards.
+pub fn eliminate_program(program: &mut IRProgram) {
+    for func in program.functions.iter_mut() {
+        eliminate(func);
+    }
+}
+
+fn eliminate(func: &mut IRFunction) {
+    let cfg = Cfg::build(func);
+    let block_in = forward(func, &cfg, |quad, state| transfer(quad, state, func));
+
+    let mut body : Vec<Line> = Vec::new();
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        let lines = &func.body[block.start..block.end];
+        let mut state = match &block_in[b] {
+            Some(x) => x.clone(),
+            None => {
+                body.extend(lines.iter().cloned());
+                continue;
+            }
+        };
+        for line in lines.iter() {
+            let mut line = line.clone();
+            let holder = expr(&line.quad).and_then(|e| state.get(&e)).cloned();
+            if let (Some(holder), Some(dest)) = (holder, line.quad.def()) {
+                if holder != *dest {
+                    line.quad = Quad::Assign { dest: Operand::Var(dest.clone()), src: Operand::Var(holder) };
+                }
+            }
+            transfer(&line.quad, &mut state, func);
+            body.push(line);
+        }
+    }
+    func.body = body;
+}
+
+fn transfer(quad: &Quad, state: &mut Available, func: &IRFunction) {
+    // the callee may write any global
+    if let Quad::Call(_) = quad {
+        state.retain(|e, holder| func.is_local(holder) && e.vars().iter().all(|var| func.is_local(var)));
+    }
+
+    let var = match quad.def() {
+        Some(x) => x,
+        None => return
+    };
+    state.retain(|e, holder| holder != var && !e.vars().contains(&var));
+    if let Some(e) = expr(quad) {
+        if !e.vars().contains(&var) {
+            state.entry(e).or_insert_with(|| var.clone());
+        }
+    }
+}
+
+/// The expression a quad computes, if it is worth remembering. Anything
+/// touching `24Kmagic` gives a new answer every time.
+fn expr(quad: &Quad) -> Option<Expr> {
+    if quad.operands().contains(&&Operand::Magic) {
+        return None;
+    }
+    match quad {
+        Quad::Unary { op, src, .. } => Some(Expr::Unary(*op, src.clone())),
+        Quad::Binary { lhs, op, rhs, .. } => {
+            let commutative = matches!(op, BinaryOp::Add | BinaryOp::Mult | BinaryOp::And |
+                BinaryOp::Or | BinaryOp::Eq | BinaryOp::Neq | BinaryOp::StrEq | BinaryOp::StrNeq |
+                BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::Xor |
+                BinaryOp::FAdd | BinaryOp::FMult | BinaryOp::FEq | BinaryOp::FNeq);
+            if commutative && format!("{:?}", lhs) > format!("{:?}", rhs) {
+                Some(Expr::Binary(*op, rhs.clone(), lhs.clone()))
+            } else {
+                Some(Expr::Binary(*op, lhs.clone(), rhs.clone()))
+            }
+        },
+        _ => None
+    }
+}
+
+impl Expr {
+    fn vars(&self) -> Vec<&String> {
+        match self {
+            Expr::Unary(_, src) => src.var().into_iter().collect(),
+            Expr::Binary(_, lhs, rhs) => lhs.var().into_iter().chain(rhs.var()).collect(),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::assembly::ir::{IRFunction, Quad};
+use crate::assembly::ir::cfg::Cfg;
+
+/// Forward "must" dataflow over the CFG: a fact holds on entry to a block only
+/// if it holds at the end of every predecessor reaching it. Facts are maps, so
+/// meeting two states keeps the entries they agree on. Blocks the analysis
+/// never reaches get `None`.
+pub fn forward<K, V, F>(func: &IRFunction, cfg: &Cfg, transfer: F) -> Vec<Option<HashMap<K, V>>>
+where
+    K: Clone + Eq + Hash,
+    V: Clone + PartialEq,
+    F: Fn(&Quad, &mut HashMap<K, V>),
+{
+    let mut block_in : Vec<Option<HashMap<K, V>>> = vec![None; cfg.blocks.len()];
+    block_in[0] = Some(HashMap::new());
+    let mut worklist = vec![0];
+
+    while let Some(b) = worklist.pop() {
+        let mut state = block_in[b].clone().unwrap();
+        for line in func.body[cfg.blocks[b].start..cfg.blocks[b].end].iter() {
+            transfer(&line.quad, &mut state);
+        }
+
+        for succ in cfg.blocks[b].succs.iter() {
+            let merged = match &block_in[*succ] {
+                Some(old) => old.iter()
+                    .filter(|(key, val)| state.get(*key) == Some(*val))
+                    .map(|(key, val)| (key.clone(), val.clone()))
+                    .collect::<HashMap<K, V>>(),
+                None => state.clone(),
+            };
+            if block_in[*succ].as_ref() != Some(&merged) {
+                block_in[*succ] = Some(merged);
+                worklist.push(*succ);
+            }
+        }
+    }
+    block_in
+}
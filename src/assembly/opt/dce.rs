@@ -0,0 +1,166 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::assembly::ir::{IRProgram, IRFunction, Line, Quad, Operand, BinaryOp};
+use crate::assembly::ir::cfg::Cfg;
+use crate::assembly::ir::liveness::Liveness;
+use crate::assembly::ir::ssa;
+
+/// Drops the functions `main` can never call, then the unreachable blocks and
+/// dead quads of what's left.
+pub fn eliminate_program(program: &mut IRProgram) {
+    remove_uncalled_functions(program);
+    for func in program.functions.iter_mut() {
+        remove_unreachable_blocks(func);
+        while remove_dead_quads(func) {}
+        remove_redundant_jumps(func);
+        remove_unused_locals(func);
+    }
+}
+
+fn remove_uncalled_functions(program: &mut IRProgram) {
+    if !program.functions.iter().any(|func| func.name == "main") {
+        return;
+    }
+
+    let mut called : HashSet<String> = HashSet::new();
+    let mut worklist = vec!["main".to_string()];
+    while let Some(name) = worklist.pop() {
+        if !called.insert(name.clone()) {
+            continue;
+        }
+        let func = match program.functions.iter().find(|func| func.name == name) {
+            Some(x) => x,
+            None => continue
+        };
+        for line in func.body.iter() {
+            if let Quad::Call(callee) = &line.quad {
+                worklist.push(callee.clone());
+            }
+        }
+    }
+    program.functions.retain(|func| called.contains(&func.name));
+}
+
+/// Keeps only the blocks reachable from the entry. The `leave` line always
+/// stays, since the backend hangs the epilogue off it.
+fn remove_unreachable_blocks(func: &mut IRFunction) -> bool {
+    let cfg = Cfg::build(func);
+    let mut reachable = vec![false; cfg.blocks.len()];
+    let mut worklist = vec![0];
+    while let Some(b) = worklist.pop() {
+        if reachable[b] {
+            continue;
+        }
+        reachable[b] = true;
+        worklist.extend(cfg.blocks[b].succs.iter().copied());
+    }
+
+    let before = func.body.len();
+    let body = func.body.iter().enumerate()
+        .filter(|(i, line)| reachable[cfg.block_of(*i)] || matches!(line.quad, Quad::Leave(_)))
+        .map(|(_, line)| line.clone())
+        .collect::<Vec<Line>>();
+    func.body = body;
+    if ssa::in_ssa(func) {
+        ssa::prune_phis(func);
+    }
+    func.body.len() != before
+}
+
+/// Removes quads whose result is never read. Anything that prints, reads,
+/// calls or rolls `24Kmagic` stays regardless.
+fn remove_dead_quads(func: &mut IRFunction) -> bool {
+    let liveness = Liveness::analyze(func);
+    let mut changed = false;
+
+    for i in 0..func.body.len() {
+        let dead = match func.body[i].quad.def() {
+            Some(var) => func.is_local(var) && !liveness.live_out[i].contains(var),
+            None => false
+        };
+        if !dead || !removable(&func.body[i].quad) {
+            continue;
+        }
+        // a labelled line may still be jumped to
+        func.body[i].quad = Quad::Nop;
+        changed = true;
+    }
+
+    let before = func.body.len();
+    func.body.retain(|line| line.label.is_some() || line.quad != Quad::Nop);
+    changed || func.body.len() != before
+}
+
+/// Drops jumps to the very next line, then, outside SSA form where phis name
+/// blocks by their labels, the labels nothing jumps to any more.
+fn remove_redundant_jumps(func: &mut IRFunction) {
+    let mut i = 0;
+    while i + 1 < func.body.len() {
+        match (&func.body[i].quad, &func.body[i + 1].label) {
+            (Quad::Goto(target), Some(next)) if target == next => {
+                if func.body[i].label.is_some() {
+                    func.body[i].quad = Quad::Nop;
+                } else {
+                    func.body.remove(i);
+                    continue;
+                }
+            },
+            _ => ()
+        }
+        i += 1;
+    }
+
+    if ssa::in_ssa(func) {
+        return;
+    }
+    let targets = func.body.iter().filter_map(|line| match &line.quad {
+        Quad::Goto(target) | Quad::IfZ { target, .. } => Some(target.clone()),
+        _ => None
+    }).collect::<HashSet<String>>();
+    func.body.retain(|line| match &line.label {
+        Some(label) => line.quad != Quad::Nop || targets.contains(label),
+        None => true
+    });
+}
+
+fn removable(quad: &Quad) -> bool {
+    match quad {
+        // dividing by zero traps, and so does dividing i64::MIN by -1, so only
+        // a known divisor other than those two is safe to drop
+        Quad::Binary { op: BinaryOp::Div | BinaryOp::Mod, rhs, .. } => matches!(rhs, Operand::Int(x) if *x != 0 && *x != -1),
+        Quad::Assign { .. } | Quad::Unary { .. } | Quad::Binary { .. } | Quad::GetRet(_) | Quad::GetArg(..) | Quad::Phi { .. } => {
+            !quad.operands().contains(&&Operand::Magic)
+        },
+        _ => false
+    }
+}
+
+/// Drops the locals nothing refers to any more and renumbers the surviving
+/// `tmpN`s from zero, so the listing only shows what the frame holds.
+fn remove_unused_locals(func: &mut IRFunction) {
+    let used = func.body.iter()
+        .flat_map(|line| line.quad.operands())
+        .filter_map(Operand::var)
+        .cloned()
+        .collect::<HashSet<String>>();
+    func.locals.retain(|(id, _)| used.contains(id));
+
+    let mut renamed : HashMap<String, String> = HashMap::new();
+    for (id, _) in func.locals.iter_mut() {
+        if id.strip_prefix("tmp").is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())) {
+            let name = format!("tmp{}", renamed.len());
+            renamed.insert(std::mem::replace(id, name.clone()), name);
+        }
+    }
+    let rename = |op: &mut Operand| {
+        if let Some(name) = op.var().and_then(|var| renamed.get(var)) {
+            *op = Operand::Var(name.clone());
+        }
+    };
+    for line in func.body.iter_mut() {
+        if let Some(dest) = line.quad.dest_mut() {
+            rename(dest);
+        }
+        line.quad.uses_mut().into_iter().for_each(rename);
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::assembly::ir::{IRProgram, IRFunction, Line, Quad, Operand};
+use crate::assembly::ir::ssa::in_ssa;
+
+/// Callees with more quads than this, not counting `enter`, `leave`, `nop`s
+/// and `getarg`s, are left as calls.
+const MAX_SIZE: usize = 12;
+
+/// Replaces calls to small functions that can't reach themselves with a
+/// copy of their body, one call site at a time until none is left. A callee
+/// with no calls left to it is dce's to remove.
+pub fn inline_program(program: &mut IRProgram) {
+    let recursive = recursive_functions(program);
+    let mut sites = 0;
+    loop {
+        let small = program.functions.iter()
+            .filter(|func| !recursive.contains(&func.name) && !in_ssa(func) && size(func) <= MAX_SIZE)
+            .map(|func| func.name.clone())
+            .collect::<HashSet<String>>();
+
+        let site = program.functions.iter().enumerate()
+            .filter(|(_, caller)| !in_ssa(caller))
+            .find_map(|(c, caller)| caller.body.iter().position(|line| match &line.quad {
+                Quad::Call(callee) => *callee != caller.name && small.contains(callee),
+                _ => false
+            }).map(|i| (c, i)));
+        let (c, i) = match site {
+            Some(x) => x,
+            None => return
+        };
+
+        let callee = match &program.functions[c].body[i].quad {
+            Quad::Call(callee) => program.functions.iter().find(|func| func.name == *callee).unwrap().clone(),
+            _ => unreachable!()
+        };
+        inline_call(&mut program.functions[c], i, &callee, sites);
+        sites += 1;
+    }
+}
+
+/// Functions that can call themselves, directly or through others.
+fn recursive_functions(program: &IRProgram) -> HashSet<String> {
+    let mut calls : HashMap<&str, Vec<&str>> = HashMap::new();
+    for func in program.functions.iter() {
+        let callees = func.body.iter().filter_map(|line| match &line.quad {
+            Quad::Call(callee) => Some(callee.as_str()),
+            _ => None
+        }).collect();
+        calls.insert(func.name.as_str(), callees);
+    }
+
+    let mut recursive = HashSet::new();
+    for func in program.functions.iter() {
+        let mut seen : HashSet<&str> = HashSet::new();
+        let mut worklist = calls[func.name.as_str()].clone();
+        while let Some(name) = worklist.pop() {
+            if name == func.name {
+                recursive.insert(func.name.clone());
+                break;
+            }
+            if seen.insert(name) {
+                worklist.extend(calls.get(name).into_iter().flatten());
+            }
+        }
+    }
+    recursive
+}
+
+fn size(func: &IRFunction) -> usize {
+    func.body.iter()
+        .filter(|line| !matches!(line.quad, Quad::Enter(_) | Quad::Leave(_) | Quad::Nop | Quad::GetArg(..)))
+        .count()
+}
+
+/// Swaps the call on line `i`, along with the `setarg`s above it and the
+/// `getret` below it, for the callee's body. Its locals and labels get a
+/// prefix unique to this call site, arguments become copies into its
+/// formals and `setret` a copy into the caller's result.
+fn inline_call(caller: &mut IRFunction, i: usize, callee: &IRFunction, site: usize) {
+    let prefix = format!("{}.{}.", callee.name, site);
+    let mut start = i;
+    while start > 0 && matches!(caller.body[start - 1].quad, Quad::SetArg(..)) {
+        start -= 1;
+    }
+    let args = caller.body[start..i].iter().filter_map(|line| match &line.quad {
+        Quad::SetArg(n, arg) => Some((*n, arg.clone())),
+        _ => None
+    }).collect::<HashMap<usize, Operand>>();
+    let ret = match caller.body.get(i + 1) {
+        Some(Line { label: None, quad: Quad::GetRet(dest) }) => Some(dest.clone()),
+        _ => None
+    };
+    let end = if ret.is_some() { i + 2 } else { i + 1 };
+
+    let rename = |op: &Operand| match op {
+        Operand::Var(var) if callee.is_local(var) => Operand::Var(format!("{}{}", prefix, var)),
+        op => op.clone()
+    };
+
+    // a jump may still land on the call sequence
+    let mut body = caller.body[start..=i].iter()
+        .filter_map(|line| line.label.clone())
+        .map(|label| Line { label: Some(label), quad: Quad::Nop })
+        .collect::<Vec<Line>>();
+    for line in callee.body.iter().skip(1) {
+        let label = line.label.as_ref().map(|label| format!("{}{}", prefix, label));
+        let quad = match &line.quad {
+            // a call short of arguments never got past type checking
+            Quad::GetArg(n, dest) => Quad::Assign { dest: rename(dest), src: args.get(n).cloned().unwrap_or(Operand::Int(0)) },
+            Quad::SetRet(x) => match &ret {
+                Some(dest) => Quad::Assign { dest: dest.clone(), src: rename(x) },
+                None => Quad::Nop
+            },
+            Quad::Leave(_) => Quad::Nop,
+            Quad::Goto(target) => Quad::Goto(format!("{}{}", prefix, target)),
+            Quad::IfZ { cond, target } => Quad::IfZ { cond: rename(cond), target: format!("{}{}", prefix, target) },
+            quad => {
+                let mut quad = quad.clone();
+                if let Some(dest) = quad.dest_mut() {
+                    *dest = rename(dest);
+                }
+                for op in quad.uses_mut() {
+                    *op = rename(op);
+                }
+                quad
+            }
+        };
+        if quad != Quad::Nop || label.is_some() {
+            body.push(Line { label, quad });
+        }
+    }
+
+    caller.body.splice(start..end, body);
+    for (var, var_type) in callee.locals.iter() {
+        caller.locals.push((format!("{}{}", prefix, var), *var_type));
+    }
+}
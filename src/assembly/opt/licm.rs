@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use crate::assembly::ir::{IRProgram, IRFunction, Line, Quad, Operand, BinaryOp};
+use crate::assembly::ir::cfg::{Cfg, Loop};
+use crate::assembly::ir::liveness::Liveness;
+
+/// Moves computations whose value can't change from one iteration to the next
+/// out of every natural loop, into the code that falls into the loop header.
+pub fn hoist_program(program: &mut IRProgram) {
+    for func in program.functions.iter_mut() {
+        while hoist(func) {}
+    }
+}
+
+/// Hoists out of the first loop that has anything to give, innermost first,
+/// and reports whether it did. The CFG is stale afterwards.
+fn hoist(func: &mut IRFunction) -> bool {
+    let cfg = Cfg::build(func);
+    let liveness = Liveness::analyze(func);
+    for l in cfg.natural_loops() {
+        let preheader = match preheader(func, &cfg, &l) {
+            Some(x) => x,
+            None => continue
+        };
+        let invariant = invariant_lines(func, &cfg, &l, &liveness);
+        if invariant.is_empty() {
+            continue;
+        }
+
+        let hoisted = invariant.iter().map(|i| Line { label: None, quad: func.body[*i].quad.clone() }).collect::<Vec<Line>>();
+        let mut lines = invariant.clone();
+        lines.sort();
+        for i in lines.iter().rev() {
+            if func.body[*i].label.is_some() {
+                func.body[*i].quad = Quad::Nop;
+            } else {
+                func.body.remove(*i);
+            }
+        }
+        func.body.splice(preheader..preheader, hoisted);
+        return true;
+    }
+    false
+}
+
+/// Where hoisted code can go: right above the header, as long as the only way
+/// into the loop is falling through from the block above it.
+fn preheader(func: &IRFunction, cfg: &Cfg, l: &Loop) -> Option<usize> {
+    let outside = cfg.blocks[l.header].preds.iter()
+        .filter(|pred| !l.blocks.contains(pred))
+        .collect::<Vec<&usize>>();
+    let start = cfg.blocks[l.header].start;
+    match outside[..] {
+        [pred] if cfg.blocks[*pred].end == start => {
+            let falls_through = !matches!(func.body[start - 1].quad,
+                Quad::Goto(_) | Quad::IfZ { .. } | Quad::Leave(_) | Quad::Exit);
+            if falls_through { Some(start) } else { None }
+        },
+        _ => None
+    }
+}
+
+/// Lines of the loop, each after the ones it reads from, that compute the same value every
+/// iteration and can safely run once before it instead.
+fn invariant_lines(func: &IRFunction, cfg: &Cfg, l: &Loop, liveness: &Liveness) -> Vec<usize> {
+    let lines = l.blocks.iter()
+        .flat_map(|b| cfg.blocks[*b].start..cfg.blocks[*b].end)
+        .collect::<Vec<usize>>();
+    // a callee may write any global
+    let calls = lines.iter().any(|i| matches!(func.body[*i].quad, Quad::Call(_)));
+
+    let mut defs : Vec<&String> = Vec::new();
+    for i in lines.iter() {
+        defs.extend(func.body[*i].quad.def());
+    }
+    let header_live = &liveness.live_in[cfg.blocks[l.header].start];
+
+    let mut invariant : Vec<usize> = Vec::new();
+    let mut hoisted : HashSet<&String> = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in lines.iter() {
+            let quad = &func.body[*i].quad;
+            let dest = match quad.def() {
+                Some(x) => x,
+                None => continue
+            };
+            // the value has to be unused until this line sets it, and set nowhere
+            // else in the loop, or running it up front would change what is read
+            let safe = !invariant.contains(i) && pure(quad) && func.is_local(dest)
+                && !header_live.contains(dest) && defs.iter().filter(|var| **var == dest).count() == 1;
+            let operands_fixed = quad.uses().iter().all(|var| {
+                hoisted.contains(var) || (!defs.contains(var) && (func.is_local(var) || !calls))
+            });
+            if safe && operands_fixed {
+                invariant.push(*i);
+                hoisted.insert(dest);
+                changed = true;
+            }
+        }
+    }
+    invariant
+}
+
+/// Quads that only compute a value, and can't trap or roll `24Kmagic`. Dividing
+/// by 0 traps, and so does dividing i64::MIN by -1.
+fn pure(quad: &Quad) -> bool {
+    match quad {
+        Quad::Binary { op: BinaryOp::Div | BinaryOp::Mod, rhs, .. } => matches!(rhs, Operand::Int(x) if *x != 0 && *x != -1),
+        Quad::Assign { .. } | Quad::Unary { .. } | Quad::Binary { .. } => {
+            !quad.operands().contains(&&Operand::Magic)
+        },
+        _ => false
+    }
+}
@@ -0,0 +1,131 @@
+use crate::assembly::ir::{IRProgram, IRFunction, Line, Quad, Operand};
+
+/// Turns every call a function makes to itself as the last thing before
+/// returning into a jump back to its top, after copying the new arguments
+/// into its parameters. Deep recursion then runs in a single frame.
+pub fn eliminate_program(program: &mut IRProgram) {
+    for func in program.functions.iter_mut() {
+        eliminate(func);
+    }
+}
+
+fn eliminate(func: &mut IRFunction) {
+    let leave = match func.body.last() {
+        Some(Line { label: Some(label), quad: Quad::Leave(_) }) => label.clone(),
+        _ => return
+    };
+    let top = format!("lbl_{}.tail", func.name);
+    // parameters are only known by the getargs that read them
+    let params = func.body.iter().filter_map(|line| match &line.quad {
+        Quad::GetArg(n, Operand::Var(param)) => Some((*n, param.clone())),
+        _ => None
+    }).collect::<Vec<(usize, String)>>();
+
+    let mut found = false;
+    let mut i = 0;
+    while i < func.body.len() {
+        let end = match tail_call(func, i, &leave) {
+            Some(x) => x,
+            None => {
+                i += 1;
+                continue;
+            }
+        };
+        let mut start = i;
+        while start > 0 && matches!(func.body[start - 1].quad, Quad::SetArg(..)) {
+            start -= 1;
+        }
+        let args = func.body[start..i].iter().filter_map(|line| match &line.quad {
+            Quad::SetArg(n, arg) => Some((*n, arg.clone())),
+            _ => None
+        }).collect::<Vec<(usize, Operand)>>();
+
+        // a jump may still land on the call sequence
+        let mut body = func.body[start..end].iter()
+            .filter_map(|line| line.label.clone())
+            .map(|label| Line { label: Some(label), quad: Quad::Nop })
+            .collect::<Vec<Line>>();
+        body.extend(pass_arguments(func, &params, &args));
+        body.push(Line { label: None, quad: Quad::Goto(top.clone()) });
+
+        i = start + body.len();
+        func.body.splice(start..end, body);
+        found = true;
+    }
+
+    if found {
+        let entry = func.body.iter().position(|line| !matches!(line.quad, Quad::Enter(_) | Quad::GetArg(..))).unwrap();
+        func.body.insert(entry, Line { label: Some(top), quad: Quad::Nop });
+    }
+}
+
+/// Whether line `i` is a call to the function itself whose result, if any,
+/// goes straight back to its own caller. Gives the end of the call, past its
+/// `getret` and `setret`.
+fn tail_call(func: &IRFunction, i: usize, leave: &str) -> Option<usize> {
+    match &func.body[i].quad {
+        Quad::Call(callee) if *callee == func.name => (),
+        _ => return None
+    }
+
+    let mut j = i + 1;
+    let result = match &func.body[j] {
+        Line { label: None, quad: Quad::GetRet(Operand::Var(x)) } => {
+            j += 1;
+            Some(x)
+        },
+        _ => None
+    };
+    if let Line { label: None, quad: Quad::SetRet(Operand::Var(x)) } = &func.body[j] {
+        if Some(x) != result {
+            return None;
+        }
+        j += 1;
+    }
+
+    // labels in between are only more ways into the return, and stay where they are
+    let end = j;
+    while matches!(func.body[j].quad, Quad::Nop) {
+        j += 1;
+    }
+    match &func.body[j].quad {
+        Quad::Goto(target) if target == leave => Some(end),
+        Quad::Leave(_) => Some(end),
+        _ => None
+    }
+}
+
+/// Copies the new arguments into the parameters, in order. An argument that
+/// reads a parameter already overwritten by then is saved to a temporary first.
+fn pass_arguments(func: &mut IRFunction, params: &[(usize, String)], args: &[(usize, Operand)]) -> Vec<Line> {
+    let mut saved : Vec<Line> = Vec::new();
+    let mut copies : Vec<Line> = Vec::new();
+    let mut written : Vec<&String> = Vec::new();
+    for (n, param) in params.iter() {
+        let arg = match args.iter().find(|(m, _)| m == n) {
+            Some((_, arg)) => arg.clone(),
+            None => continue
+        };
+        if arg.var() == Some(param) {
+            continue;
+        }
+
+        let clobbered = arg.var().is_some_and(|var| written.contains(&var));
+        let src = match arg.var() {
+            Some(var) if clobbered => {
+                let tmp = format!("{}.tail", var);
+                if !func.is_local(&tmp) {
+                    let var_type = func.locals.iter().find(|(id, _)| id == var).map(|(_, t)| *t).unwrap();
+                    func.locals.push((tmp.clone(), var_type));
+                }
+                saved.push(Line { label: None, quad: Quad::Assign { dest: Operand::Var(tmp.clone()), src: arg.clone() } });
+                Operand::Var(tmp)
+            },
+            _ => arg
+        };
+        copies.push(Line { label: None, quad: Quad::Assign { dest: Operand::Var(param.clone()), src } });
+        written.push(param);
+    }
+    saved.extend(copies);
+    saved
+}
@@ -0,0 +1,403 @@
+use super::PassManager;
+use super::{const_prop, cse, copy_prop, licm, inline, tail_call, dce};
+use crate::assembly::ir::IRProgram;
+
+fn program(body: &str) -> String {
+    format!("[BEGIN GLOBALS]
+g-int
+[END GLOBALS]
+\t
+[BEGIN main LOCALS]
+x-int
+b-bool
+tmp0-int
+tmp1-int
+tmp2-bool
+[END main LOCALS]
+
+main:\tenter main
+{}
+lbl_0:\tleave main
+", body)
+}
+
+fn run_pass(three_ac: &str, pass: fn(&mut IRProgram)) -> String {
+    let mut program = IRProgram::parse(three_ac);
+    pass(&mut program);
+    program.to_string()
+}
+
+fn main_body(three_ac: &str, pass: fn(&mut IRProgram)) -> String {
+    let optimized = run_pass(three_ac, pass);
+    let start = optimized.find("enter main").unwrap();
+    let end = optimized.find("lbl_0:").unwrap();
+    optimized[start + "enter main".len()..end].trim().to_string()
+}
+
+#[test]
+fn level_zero_is_untouched() {
+    let input = program("\t[tmp0] := 2 MULT64 3");
+    assert_eq!(PassManager::preset(0).run(input.clone()), Ok(input));
+}
+
+#[test]
+fn malformed_input_is_an_error() {
+    let input = program("\tgoto lbl_9");
+    let err = PassManager::preset(1).run(input).unwrap_err();
+    assert!(err.contains("jumps to missing label lbl_9"), "{err}");
+}
+
+#[test]
+fn folds_arithmetic() {
+    let input = program("\t[tmp0] := 2 MULT64 3\n\t[tmp1] := [tmp0] ADD64 1\n\t[x] := [tmp1]\n\tWRITE x");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[tmp0] := 6\n\t[tmp1] := 7\n\t[x] := 7\n\tWRITE 7");
+}
+
+#[test]
+fn folds_comparisons_and_logic() {
+    let input = program("\t[tmp2] := 3 LT64 4\n\t[b] := [tmp2] AND64 false\n\tWRITE b");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[tmp2] := true\n\t[b] := false\n\tWRITE false");
+}
+
+#[test]
+fn folds_bitwise_and_shifts() {
+    let input = program("\t[tmp0] := 17 MOD64 5\n\t[tmp1] := [tmp0] SHL64 4\n\t[x] := [tmp1] XOR64 3\n\t[tmp0] := BITNOT64 [x]\n\tWRITE [tmp0]");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[tmp0] := 2\n\t[tmp1] := 32\n\t[x] := 35\n\t[tmp0] := -36\n\tWRITE -36");
+}
+
+#[test]
+fn keeps_out_of_range_shifts() {
+    let input = program("\t[tmp0] := 1 SHL64 64\n\t[tmp1] := 7 MOD64 0");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[tmp0] := 1 SHL64 64\n\t[tmp1] := 7 MOD64 0");
+}
+
+#[test]
+fn folds_wide_ints_but_not_overflow() {
+    let input = program("\t[tmp0] := 3000000000 MULT64 3\n\t[tmp1] := 9223372036854775807 ADD64 1\n\t[x] := [tmp0] ADD64 [tmp1]");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[tmp0] := 9000000000\n\t[tmp1] := 9223372036854775807 ADD64 1\n\t[x] := 9000000000 ADD64 [tmp1]");
+}
+
+#[test]
+fn folds_floats_but_not_nan() {
+    let input = program("\t[tmp0] := 1.5 MULTF64 4.0\n\t[tmp1] := 0.0 DIVF64 0.0\n\t[b] := [tmp0] LTF64 6.5\n\t[x] := NEGF64 [tmp0]");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[tmp0] := 6.0\n\t[tmp1] := 0.0 DIVF64 0.0\n\t[b] := true\n\t[x] := -6.0");
+}
+
+#[test]
+fn folds_char_comparisons() {
+    let input = program("\t[x] := '\\u{20}'\n\t[b] := [x] LT64 'a'\n\tWRITE x");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[x] := '\\u{20}'\n\t[b] := true\n\tWRITE '\\u{20}'");
+}
+
+#[test]
+fn keeps_division_by_zero() {
+    let input = program("\t[tmp0] := 1 DIV64 0");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[tmp0] := 1 DIV64 0");
+}
+
+#[test]
+fn keeps_magic() {
+    let input = program("\t[b] := 24Kmagic AND64 false");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[b] := 24Kmagic AND64 false");
+}
+
+#[test]
+fn merges_at_joins() {
+    // x is 1 on one path and 2 on the other, so it isn't constant after the join
+    let input = program("\t[b] := 24Kmagic\n\tIFZ [b] goto lbl_1\n\t[x] := 1\n\t[tmp0] := 5\n\tgoto lbl_2\n\nlbl_1:\tnop\n\t[x] := 2\n\t[tmp0] := 5\n\nlbl_2:\tnop\n\tWRITE x\n\tWRITE tmp0");
+    assert!(main_body(&input, const_prop::propagate_program).ends_with("WRITE x\n\tWRITE 5"));
+}
+
+#[test]
+fn simplifies_known_branches() {
+    let input = program("\t[x] := 0\n\tIFZ [x] goto lbl_1\n\tWRITE 1\n\nlbl_1:\tnop\n\t[tmp2] := 1 LT64 2\n\tIFZ [tmp2] goto lbl_0");
+    assert_eq!(main_body(&input, const_prop::propagate_program), "[x] := 0\n\tgoto lbl_1\n\tWRITE 1\n\nlbl_1:\tnop\n\t[tmp2] := true");
+}
+
+#[test]
+fn propagates_constant_globals() {
+    let input = "[BEGIN GLOBALS]
+g-int
+[END GLOBALS]
+\t
+[BEGIN main LOCALS]
+[END main LOCALS]
+
+main:\tenter main
+\t[g] := 4
+\tcall f
+lbl_0:\tleave main
+
+[BEGIN f LOCALS]
+tmp0-int
+[END f LOCALS]
+
+f:\tenter f
+\t[tmp0] := [g] ADD64 1
+\tWRITE tmp0
+lbl_1:\tleave f
+".to_string();
+    let optimized = run_pass(&input, const_prop::propagate_program);
+    assert!(optimized.contains("\t[tmp0] := 5\n\tWRITE 5"));
+}
+
+#[test]
+fn removes_dead_quads() {
+    let input = program("\t[tmp0] := 2 MULT64 3\n\t[tmp1] := [tmp0] ADD64 1\n\t[x] := 4\n\tWRITE x");
+    assert_eq!(main_body(&input, dce::eliminate_program), "[x] := 4\n\tWRITE x");
+}
+
+#[test]
+fn keeps_side_effects() {
+    let input = program("\t[b] := 24Kmagic\n\tREAD [x]\n\t[tmp0] := [x] DIV64 [g]\n\t[g] := 1");
+    assert_eq!(main_body(&input, dce::eliminate_program), "[b] := 24Kmagic\n\tREAD [x]\n\t[tmp0] := [x] DIV64 [g]\n\t[g] := 1");
+}
+
+#[test]
+fn keeps_divisions_that_can_trap() {
+    let input = program("\tREAD [x]\n\t[tmp0] := [x] DIV64 -1\n\t[tmp1] := [x] MOD64 -1\n\t[tmp2] := [x] DIV64 2");
+    assert_eq!(main_body(&input, dce::eliminate_program), "READ [x]\n\t[tmp0] := [x] DIV64 -1\n\t[tmp1] := [x] MOD64 -1");
+}
+
+#[test]
+fn removes_unreachable_blocks() {
+    let input = program("\tsetret 0\n\tgoto lbl_0\n\tWRITE 1\n\nlbl_1:\tnop\n\tWRITE 2\n\texit\n\tWRITE 3");
+    assert_eq!(main_body(&input, dce::eliminate_program), "setret 0");
+}
+
+#[test]
+fn full_pipeline_folds_if_false() {
+    let input = program("\tIFZ false goto lbl_1\n\tWRITE 1\n\nlbl_1:\tnop\n\tWRITE 2");
+    let optimized = PassManager::preset(1).run(input).unwrap();
+    assert!(!optimized.contains("WRITE 1"));
+    assert!(optimized.contains("WRITE 2"));
+}
+
+#[test]
+fn drops_uncalled_functions() {
+    let input = "[BEGIN GLOBALS]
+[END GLOBALS]
+\t
+[BEGIN helper LOCALS]
+[END helper LOCALS]
+
+helper:\tenter helper
+lbl_1:\tleave helper
+
+[BEGIN unused LOCALS]
+[END unused LOCALS]
+
+unused:\tenter unused
+\tcall helper
+lbl_2:\tleave unused
+
+[BEGIN main LOCALS]
+[END main LOCALS]
+
+main:\tenter main
+\tcall helper
+lbl_0:\tleave main
+";
+    let optimized = run_pass(input, dce::eliminate_program);
+    assert!(optimized.contains("helper:"));
+    assert!(optimized.contains("main:"));
+    assert!(!optimized.contains("unused"));
+}
+
+#[test]
+fn reuses_common_subexpressions() {
+    let input = program("\t[tmp0] := [g] ADD64 1\n\t[tmp1] := 1 ADD64 [g]\n\t[x] := [tmp0] MULT64 [tmp1]\n\tWRITE x");
+    assert_eq!(main_body(&input, cse::eliminate_program),
+        "[tmp0] := [g] ADD64 1\n\t[tmp1] := [tmp0]\n\t[x] := [tmp0] MULT64 [tmp1]\n\tWRITE x");
+}
+
+#[test]
+fn cse_respects_redefinitions_and_calls() {
+    let redefined = program("\t[tmp0] := [x] ADD64 1\n\t[x] := 2\n\t[tmp1] := [x] ADD64 1\n\tWRITE tmp1");
+    assert!(main_body(&redefined, cse::eliminate_program).contains("[tmp1] := [x] ADD64 1"));
+
+    let called = program("\t[tmp0] := [g] ADD64 1\n\tcall main\n\t[tmp1] := [g] ADD64 1\n\tWRITE tmp1");
+    assert!(main_body(&called, cse::eliminate_program).contains("[tmp1] := [g] ADD64 1"));
+
+    let magic = program("\t[tmp2] := 24Kmagic AND64 true\n\t[b] := 24Kmagic AND64 true\n\tWRITE b");
+    assert!(main_body(&magic, cse::eliminate_program).contains("[b] := 24Kmagic AND64 true"));
+}
+
+#[test]
+fn cse_across_blocks() {
+    let input = program("\t[tmp0] := [g] MULT64 2\n\tIFZ [b] goto lbl_1\n\tWRITE 1\n\nlbl_1:\tnop\n\t[tmp1] := [g] MULT64 2\n\tWRITE tmp1");
+    assert!(main_body(&input, cse::eliminate_program).contains("[tmp1] := [tmp0]"));
+}
+
+#[test]
+fn propagates_copies() {
+    let input = program("\tREAD [tmp0]\n\t[x] := [tmp0]\n\t[tmp1] := [x] ADD64 [x]\n\tWRITE tmp1\n\tWRITE x");
+    assert_eq!(main_body(&input, copy_prop::propagate_program),
+        "READ [x]\n\t[tmp1] := [x] ADD64 [x]\n\tWRITE tmp1\n\tWRITE x");
+
+    let copied = program("\tREAD [x]\n\t[tmp0] := [x]\n\tWRITE tmp0\n\t[x] := 1\n\tWRITE tmp0\n\tWRITE x");
+    assert_eq!(main_body(&copied, copy_prop::propagate_program),
+        "READ [x]\n\t[tmp0] := [x]\n\tWRITE x\n\t[x] := 1\n\tWRITE tmp0\n\tWRITE x");
+}
+
+#[test]
+fn merges_call_results() {
+    let input = program("\tcall main\n\tgetret [tmp0]\n\t[x] := [tmp0]\n\tWRITE x");
+    assert_eq!(main_body(&input, copy_prop::propagate_program), "call main\n\tgetret [x]\n\tWRITE x");
+}
+
+#[test]
+fn shrinks_tmps() {
+    let input = program("\t[tmp0] := [g] ADD64 1\n\t[tmp1] := [g] ADD64 1\n\t[x] := [tmp0] MULT64 [tmp1]\n\tWRITE x\n\tWRITE tmp2");
+    let optimized = PassManager::preset(1).run(input).unwrap();
+    assert!(optimized.contains("[x] := [tmp0] MULT64 [tmp0]"));
+    assert!(optimized.contains("tmp0-int\ntmp1-bool\n[END main LOCALS]"));
+    assert!(optimized.contains("WRITE tmp1"));
+}
+
+#[test]
+fn hoists_loop_invariants() {
+    let input = program("\tREAD [x]\n\nlbl_1:\tnop\n\t[tmp0] := [g] MULT64 2\n\t[tmp2] := [x] LT64 [tmp0]\n\tIFZ [tmp2] goto lbl_2\n\t[tmp1] := [x] ADD64 1\n\t[x] := [tmp1]\n\tgoto lbl_1\n\nlbl_2:\tnop\n\tWRITE x");
+    assert_eq!(main_body(&input, licm::hoist_program),
+        "READ [x]\n\t[tmp0] := [g] MULT64 2\n\nlbl_1:\tnop\n\t[tmp2] := [x] LT64 [tmp0]\n\tIFZ [tmp2] goto lbl_2\n\t[tmp1] := [x] ADD64 1\n\t[x] := [tmp1]\n\tgoto lbl_1\n\nlbl_2:\tnop\n\tWRITE x");
+}
+
+#[test]
+fn licm_leaves_variant_code() {
+    // the loop calls out, so the global may change under it
+    let called = program("\tREAD [x]\n\nlbl_1:\tnop\n\t[tmp0] := [g] MULT64 2\n\tWRITE tmp0\n\tcall main\n\tgoto lbl_1");
+    assert_eq!(main_body(&called, licm::hoist_program), main_body(&called, |_| ()));
+
+    // tmp0 is still read after the loop, which may not run at all
+    let live = program("\tREAD [x]\n\nlbl_1:\tnop\n\tIFZ [b] goto lbl_2\n\t[tmp0] := [x] MULT64 2\n\tgoto lbl_1\n\nlbl_2:\tnop\n\tWRITE tmp0");
+    assert_eq!(main_body(&live, licm::hoist_program), main_body(&live, |_| ()));
+
+    let magic = program("\nlbl_1:\tnop\n\t[b] := 24Kmagic AND64 true\n\tWRITE b\n\tgoto lbl_1");
+    assert_eq!(main_body(&magic, licm::hoist_program), main_body(&magic, |_| ()));
+
+    // i64::MIN / -1 traps, and the loop may not run at all
+    for divisor in ["0", "-1"] {
+        let trap = program(&format!("\tREAD [x]\n\nlbl_1:\tnop\n\tIFZ [b] goto lbl_2\n\t[tmp0] := [x] DIV64 {divisor}\n\tWRITE tmp0\n\tgoto lbl_1\n\nlbl_2:\tnop"));
+        assert_eq!(main_body(&trap, licm::hoist_program), main_body(&trap, |_| ()));
+    }
+}
+
+const CALLS : &str = "[BEGIN GLOBALS]
+[END GLOBALS]
+\t
+[BEGIN twice LOCALS]
+n-int
+tmp0-int
+[END twice LOCALS]
+
+twice:\tenter twice
+\tgetarg 1 [n]
+\t[tmp0] := [n] ADD64 [n]
+\tsetret [tmp0]
+\tgoto lbl_0
+lbl_0:\tleave twice
+
+[BEGIN down LOCALS]
+n-int
+tmp0-int
+[END down LOCALS]
+
+down:\tenter down
+\tgetarg 1 [n]
+\t[tmp0] := [n] SUB64 1
+\tsetarg 1 [tmp0]
+\tcall down
+\tgoto lbl_1
+lbl_1:\tleave down
+
+[BEGIN main LOCALS]
+x-int
+tmp0-int
+[END main LOCALS]
+
+main:\tenter main
+\tREAD [x]
+\tsetarg 1 [x]
+\tcall twice
+\tgetret [tmp0]
+\tWRITE tmp0
+\tsetarg 1 3
+\tcall down
+\tgoto lbl_2
+lbl_2:\tleave main
+";
+
+#[test]
+fn inlines_small_functions() {
+    let inlined = run_pass(CALLS, inline::inline_program);
+    let main = &inlined[inlined.find("[BEGIN main").unwrap()..];
+    assert!(main.contains("twice.0.n-int\ntwice.0.tmp0-int\n[END main LOCALS]"));
+    assert!(main.contains("\t[twice.0.n] := [x]\n\t[twice.0.tmp0] := [twice.0.n] ADD64 [twice.0.n]\n\t[tmp0] := [twice.0.tmp0]\n\tgoto twice.0.lbl_0\n\ntwice.0.lbl_0:\tnop\n\tWRITE tmp0"));
+    assert!(!main.contains("call twice"));
+
+    // down calls itself, so it stays a call
+    assert!(main.contains("\tsetarg 1 3\n\tcall down"));
+    assert!(inlined.contains("\tsetarg 1 [tmp0]\n\tcall down"));
+}
+
+#[test]
+fn turns_self_tail_calls_into_jumps() {
+    let input = "[BEGIN GLOBALS]
+[END GLOBALS]
+\t
+[BEGIN swap LOCALS]
+a-int
+b-int
+tmp0-int
+tmp1-int
+[END swap LOCALS]
+
+swap:\tenter swap
+\tgetarg 1 [a]
+\tgetarg 2 [b]
+\tIFZ [a] goto lbl_1
+\tsetret [b]
+\tgoto lbl_0
+
+lbl_1:\tnop
+\t[tmp0] := [b] SUB64 1
+\tsetarg 1 [tmp0]
+\tsetarg 2 [a]
+\tcall swap
+\tgetret [tmp1]
+\tsetret [tmp1]
+\tgoto lbl_0
+lbl_0:\tleave swap
+";
+    let optimized = run_pass(input, tail_call::eliminate_program);
+    assert!(!optimized.contains("call swap"));
+    assert!(optimized.contains("\tgetarg 2 [b]\n\nlbl_swap.tail:\tnop\n\tIFZ [a] goto lbl_1"));
+    // a is overwritten before b gets its old value
+    assert!(optimized.contains("\t[a.tail] := [a]\n\t[a] := [tmp0]\n\t[b] := [a.tail]\n\tgoto lbl_swap.tail\n\tgoto lbl_0"));
+    assert!(optimized.contains("a.tail-int\n[END swap LOCALS]"));
+
+    // a call whose result is used first isn't a tail call
+    let multiplied = input.replace("\tsetret [tmp1]", "\t[tmp1] := [tmp1] MULT64 [a]\n\tsetret [tmp1]");
+    assert!(run_pass(&multiplied, tail_call::eliminate_program).contains("call swap"));
+}
+
+#[test]
+fn presets() {
+    assert!(PassManager::preset(0).pass_names().is_empty());
+    assert_eq!(PassManager::preset(1).pass_names(), vec!["tail-call", "const-prop", "cse", "copy-prop", "licm", "dce"]);
+    assert_eq!(PassManager::preset(2).pass_names(),
+        vec!["tail-call", "inline", "ssa", "const-prop", "cse", "copy-prop", "licm", "dce", "out-of-ssa", "copy-prop", "const-prop", "dce"]);
+}
+
+#[test]
+fn custom_pipelines() {
+    assert_eq!(PassManager::from_names(&["dce", "const-prop"]).unwrap().pass_names(), vec!["dce", "const-prop"]);
+    assert!(PassManager::from_names(&["dce", "unroll"]).is_err());
+}
+
+#[test]
+fn print_after_known_passes_only() {
+    let mut passes = PassManager::preset(1);
+    assert_eq!(passes.print_after("dce".to_string()), Ok(()));
+    assert_eq!(passes.print_after("bogus".to_string()), Err("Unknown pass bogus".to_string()));
+}
@@ -5,31 +5,37 @@ mod three_ac_node;
 use three_ac_node::ThreeAC;
 use three_ac_node::FunctionType;
 
-use crate::parser::ast::{PrimType, Program, TypeKind};
+use crate::parser::ast::builtin::builtin;
+use crate::parser::ast::{BinaryExpKind, ClassDecl, Decl, ExpKind, Id, Loc, LocKind, PrimType, Program, TypeKind, UnaryExpKind};
 
 pub fn convert_3ac(prog: Box<Program>) -> String {
     let mut vars: IRSymbolTable = IRSymbolTable {
         globals: Vec::new(),
         strings: Vec::new(),
-        functions: HashMap::new()
+        functions: HashMap::new(),
+        classes: HashMap::new(),
+        current: FunctionType::Global
     };
 
     prog.find_vars(&FunctionType::Global, &mut vars);
 
     let mut counts: Counter = Counter {
         lbl: 0,
-        tmp: 0
+        tmp: 0,
+        loops: Vec::new()
     };
 
     // println!("{}", vars);
     prog.convert_3ac(&mut vars, &mut counts)
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Variable3ACType {
     Int,
     Bool,
-    String
+    String,
+    Float,
+    Char
 }
 
 impl Display for Variable3ACType {
@@ -38,12 +44,15 @@ impl Display for Variable3ACType {
             Variable3ACType::Int => write!(fmt, "int"),
             Variable3ACType::Bool => write!(fmt, "bool"),
             Variable3ACType::String => write!(fmt, "string"),
+            Variable3ACType::Float => write!(fmt, "float"),
+            Variable3ACType::Char => write!(fmt, "char"),
         }
     }
 }
 pub struct Variable3AC {
     id: String,
-    var_type: Variable3ACType
+    var_type: Variable3ACType,
+    kind: TypeKind
 }
 
 impl Display for Variable3AC {
@@ -55,18 +64,46 @@ impl Display for Variable3AC {
 pub struct IRSymbolTable {
     globals: Vec<Variable3AC>,
     strings: Vec<String>,
-    functions: HashMap<String, FunctionValue>
+    functions: HashMap<String, FunctionValue>,
+    // every class's fields, nested classes flattened, in the order they sit in an array element
+    classes: HashMap<String, Vec<(String, TypeKind)>>,
+    current: FunctionType
 }
 
 
 pub struct Counter {
     lbl: usize,
     tmp: usize,
+    // the loops being lowered, innermost last
+    loops: Vec<LoopLabels>,
+}
+
+impl Counter {
+    fn new_lbl(&mut self) -> usize {
+        self.lbl += 1;
+        self.lbl - 1
+    }
+
+    // the innermost loop, or the one with the given label
+    fn find_loop(&self, label: &Option<Box<Id>>) -> Option<&LoopLabels> {
+        match label {
+            Some(x) => self.loops.iter().rev().find(|l| l.name.as_ref() == Some(&x.name)),
+            None => self.loops.last()
+        }
+    }
+}
+
+// continue jumps to next, break to after
+struct LoopLabels {
+    name: Option<String>,
+    next: usize,
+    after: usize,
 }
 
 struct FunctionValue {
     locals: Vec<Variable3AC>,
-    tmps: Vec<Variable3ACType>
+    tmps: Vec<Variable3ACType>,
+    ret: TypeKind
 }
 
 impl Display for FunctionValue {
@@ -78,10 +115,11 @@ impl Display for FunctionValue {
 }
 
 impl FunctionValue {
-    fn new() -> FunctionValue {
+    fn new(ret: TypeKind) -> FunctionValue {
         FunctionValue { 
             locals: Vec::new(), 
-            tmps: Vec::new()
+            tmps: Vec::new(),
+            ret
         }
     }
 }
@@ -102,37 +140,65 @@ impl Display for IRSymbolTable {
     }
 }
 
-impl IRSymbolTable {
-    fn add_var(&mut self, scope: &FunctionType, var_id: String, var_type: TypeKind) {
-        use FunctionType::*;
+impl Variable3ACType {
+    fn from_kind(kind: &TypeKind) -> Variable3ACType {
         use TypeKind::*;
         use PrimType::*;
-        let var_type = match var_type {
+        match kind {
             Prim(x) => match x {
                 Bool =>  Variable3ACType::Bool,
+                String => Variable3ACType::String,
+                Float => Variable3ACType::Float,
+                Char => Variable3ACType::Char,
                 _ => Variable3ACType::Int,
             }
             _ => Variable3ACType::Int
-        };
+        }
+    }
+}
+
+impl IRSymbolTable {
+    fn add_var(&mut self, scope: &FunctionType, var_id: String, var_type: TypeKind) {
+        use FunctionType::*;
         let var = Variable3AC {
             id: var_id,
-            var_type: var_type
+            var_type: Variable3ACType::from_kind(&var_type),
+            kind: var_type
         };
         match scope {
             Global => {self.globals.push(var);},
             Local { id } => {
                 self.functions.entry(id.to_owned())
                     .and_modify(|val| val.locals.push(var))
-                    .or_insert(FunctionValue::new());
+                    .or_insert(FunctionValue::new(TypeKind::Prim(PrimType::Int)));
             }
         }
     }
 
-    fn add_fn(&mut self, id: String) {
-        self.functions.insert(id.clone(), FunctionValue::new());
+    fn add_fn(&mut self, id: String, ret: TypeKind) {
+        self.functions.insert(id.clone(), FunctionValue::new(ret));
         // self.globals.push(id);                  // TODO: took this out to help x86, remove later
     }
 
+    fn add_class(&mut self, class: &ClassDecl) {
+        let mut fields = Vec::new();
+        for member in class.member_f.iter() {
+            let field = match **member {
+                Decl::VarDecl(ref x) => x,
+                _ => continue
+            };
+            match *field.var_type.kind {
+                TypeKind::Class(ref inner) => {
+                    for (path, kind) in self.classes.get(&inner.name).into_iter().flatten() {
+                        fields.push((format!("{}--{}", field.id, path), kind.clone()));
+                    }
+                },
+                ref kind => fields.push((field.id.to_string(), kind.clone()))
+            }
+        }
+        self.classes.insert(class.id.to_string(), fields);
+    }
+
     fn add_string(&mut self, str: String) {
         self.strings.push(str);
     }
@@ -146,12 +212,106 @@ impl IRSymbolTable {
         self.functions.entry(fn_id)
             .and_modify(|val| val.tmps.push(tmp_type))
             .or_insert_with(|| {
-                let mut val = FunctionValue::new();
+                let mut val = FunctionValue::new(TypeKind::Prim(PrimType::Int));
                 val.tmps.push(tmp_type);
                 val
             });
     }
 
+    // the latest declaration wins, locals before globals
+    fn var_kind(&self, curr_fn: &FunctionType, id: &str) -> Option<&TypeKind> {
+        let locals = match curr_fn {
+            FunctionType::Global => None,
+            FunctionType::Local { id } => self.functions.get(id).map(|val| &val.locals)
+        };
+        fn latest<'a>(vars: &'a [Variable3AC], id: &str) -> Option<&'a TypeKind> {
+            vars.iter().rev().find(|var| var.id == id).map(|var| &var.kind)
+        }
+        locals.and_then(|vars| latest(vars, id))
+            .or_else(|| latest(&self.globals, id))
+    }
+
+    /// The declared type of a location, looking through array elements and their fields.
+    fn loc_kind(&self, curr_fn: &FunctionType, loc: &Loc) -> Option<TypeKind> {
+        if let LocKind::Index { ref base, .. } = *loc.kind {
+            return match self.loc_kind(curr_fn, base)? {
+                TypeKind::Array { elem, .. } => Some(*elem.kind),
+                TypeKind::Prim(PrimType::String) => Some(TypeKind::Prim(PrimType::Char)),
+                _ => None
+            };
+        }
+        match loc.indexed_field() {
+            Some((elem, path)) => match self.loc_kind(curr_fn, elem)? {
+                TypeKind::Class(class) => self.field(&class.name, &path).map(|(_, kind)| kind.clone()),
+                _ => None
+            },
+            None => self.var_kind(curr_fn, &loc.to_string()).cloned()
+        }
+    }
+
+    fn loc_type(&self, curr_fn: &FunctionType, loc: &Loc) -> Variable3ACType {
+        self.loc_kind(curr_fn, loc).map(|kind| Variable3ACType::from_kind(&kind)).unwrap_or(Variable3ACType::Int)
+    }
+
+    /// Only locations and calls can hold an array.
+    fn exp_kind(&self, curr_fn: &FunctionType, exp: &ExpKind) -> Option<TypeKind> {
+        match exp {
+            ExpKind::Loc(loc) => self.loc_kind(curr_fn, loc),
+            ExpKind::CallExp(exp) => self.functions.get(&exp.name.to_string()).map(|val| val.ret.clone()),
+            _ => None
+        }
+    }
+
+    // where a field sits within an element of its class, and its type
+    fn field(&self, class: &str, path: &str) -> Option<(usize, &TypeKind)> {
+        let fields = self.classes.get(class)?;
+        fields.iter().position(|(id, _)| id == path).map(|i| (i, &fields[i].1))
+    }
+
+    /// How many slots an array element of this type takes up.
+    fn stride(&self, kind: &TypeKind) -> usize {
+        match kind {
+            TypeKind::Class(class) => self.classes.get(&class.name).map(|fields| fields.len()).unwrap_or(0).max(1),
+            _ => 1
+        }
+    }
+
+    /// What an expression evaluates to, from the declarations seen so far.
+    fn exp_type(&self, curr_fn: &FunctionType, exp: &ExpKind) -> Variable3ACType {
+        use ExpKind::*;
+        match exp {
+            True | False | Magic => Variable3ACType::Bool,
+            IntLit(_) => Variable3ACType::Int,
+            FloatLit(_) => Variable3ACType::Float,
+            CharLit(_) => Variable3ACType::Char,
+            StrLit(_) => Variable3ACType::String,
+            Loc(loc) => self.loc_type(curr_fn, loc),
+            UnaryExp(exp) => match *exp.kind {
+                UnaryExpKind::Neg => self.exp_type(curr_fn, &exp.exp.kind),
+                UnaryExpKind::BitNot => Variable3ACType::Int,
+                UnaryExpKind::Not => Variable3ACType::Bool,
+            },
+            BinaryExp(exp) => match *exp.kind {
+                BinaryExpKind::Plus | BinaryExpKind::Minus |
+                BinaryExpKind::Times | BinaryExpKind::Divide => self.exp_type(curr_fn, &exp.lhs.kind),
+                BinaryExpKind::Modulo | BinaryExpKind::BitAnd |
+                BinaryExpKind::BitOr | BinaryExpKind::BitXor |
+                BinaryExpKind::ShiftLeft | BinaryExpKind::ShiftRight => Variable3ACType::Int,
+                _ => Variable3ACType::Bool
+            },
+            Ternary(exp) => self.exp_type(curr_fn, &exp.true_branch.kind),
+            CallExp(exp) => self.ret_type(&exp.name.to_string()),
+        }
+    }
+
+    fn ret_type(&self, fn_id: &str) -> Variable3ACType {
+        match (self.functions.get(fn_id), builtin(fn_id)) {
+            (Some(val), _) => Variable3ACType::from_kind(&val.ret),
+            (None, Some(builtin)) => Variable3ACType::from_kind(&TypeKind::Prim(builtin.ret)),
+            (None, None) => Variable3ACType::Int
+        }
+    }
+
     fn globals(&self) -> String {
         let mut output : Vec::<String> = Vec::new();
 
@@ -1,5 +1,6 @@
 use crate::parser::ast::*;
-use super::{IRSymbolTable, Counter, Variable3ACType};
+use super::{IRSymbolTable, Counter, LoopLabels, Variable3ACType};
+use crate::assembly::ir::Operand;
 
 #[derive(Debug)]
 pub enum FunctionType {
@@ -33,10 +34,30 @@ pub trait ExpKind3AC {
     fn to_3ac(&self) -> String;
 }
 
+// global initializers and arrays have nowhere else to run, so they go at the top of main
+fn global_inits(prog: &Program) -> Vec<&VarDecl> {
+    prog.globals.iter().filter_map(|gbl| match **gbl {
+        Decl::VarDecl(ref x) if x.init_val.is_some() || x.allocates() => Some(x.as_ref()),
+        _ => None
+    }).collect()
+}
+
 impl ThreeAC for Box<Program> {
     fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
+        // calls can come before the callee, so every return type is known up front
+        for gbl in self.globals.iter() {
+            if let Decl::FnDecl(ref x) = **gbl {
+                vars.add_fn(x.id.to_string(), *x.ret.kind.clone());
+            }
+        }
+
+        let inits = global_inits(self);
         for gbl in self.globals.iter() {
-            gbl.find_vars(curr_fn, vars);
+            match **gbl {
+                Decl::VarDecl(ref x) => vars.add_var(curr_fn, x.id.to_string(), *x.var_type.clone().kind),
+                Decl::FnDecl(ref x) if x.id.to_string() == "main" => x.find_fn_vars(vars, &inits),
+                _ => gbl.find_vars(curr_fn, vars)
+            }
         }
     }
 
@@ -46,8 +67,13 @@ impl ThreeAC for Box<Program> {
         output.push(vars.globals());
         output.push("[END GLOBALS]".to_string());
 
+        let inits = global_inits(self);
         for gbl in self.globals.iter() {
-            let out = gbl.convert_3ac(vars, counts);
+            let out = match **gbl {
+                Decl::VarDecl(_) => continue,
+                Decl::FnDecl(ref x) if x.id.to_string() == "main" => x.convert_fn(vars, counts, &inits),
+                _ => gbl.convert_3ac(vars, counts)
+            };
             if out.trim() != "" {
                 output.push(format!("\t{}", out));
             }
@@ -64,7 +90,7 @@ impl ThreeAC for Decl {
         match *self {
             VarDecl(ref x) => x.find_vars(curr_fn, vars),
             FnDecl(ref x) => x.find_vars(curr_fn, vars),
-            _ => ()
+            ClassDecl(ref x) => vars.add_class(x),
         }
     }
 
@@ -82,30 +108,68 @@ impl ThreeAC for Decl {
 impl ThreeAC for VarDecl {
     fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
         vars.add_var(curr_fn, self.id.to_string(), *self.var_type.clone().kind);
+        if let Some(init) = &self.init_val {
+            init.find_vars(curr_fn, vars);
+        }
     }
 
-    fn convert_3ac(&self, _vars: &mut IRSymbolTable, _counts: &mut Counter) -> String {
-        // "".to_string() // drew davidson forgor to add so we don't need to ^-^ 
-        // TODO: fix this maybe
+    // a declaration with an initial value is just an assignment to the new variable,
+    // any other array is allocated right here, a growable one starting out empty
+    fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter) -> String {
         match &self.init_val {
-            Some(_) => "".to_string(),
-            None => "".to_string(),
+            Some(init) => {
+                let (pre_init, new_init) = 
+                    init.convert_3ac(vars, counts, Vec::new());
+                format!("{}[{}] := {}", quad_vec_to_string(pre_init), self.id, new_init)
+            },
+            None => match *self.var_type.kind {
+                TypeKind::Array { ref elem, size } => format!(
+                    "setarg 1 {}\n\tsetarg 2 {}\n\tcall array.new\n\tgetret [{}]",
+                    size.unwrap_or(0), vars.stride(&elem.kind), self.id
+                ),
+                _ => "".to_string()
+            },
         }
     }
 }
 
+impl VarDecl {
+    fn allocates(&self) -> bool {
+        self.init_val.is_none() && matches!(*self.var_type.kind, TypeKind::Array { .. })
+    }
+}
+
 impl ThreeAC for FnDecl {
     fn find_vars(&self, _curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
-        vars.add_fn(self.id.to_string());
+        self.find_fn_vars(vars, &[]);
+    }
+    
+    fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter) -> String {
+        self.convert_fn(vars, counts, &[])
+    }
+}
 
+impl FnDecl {
+    // `prologue` holds declarations whose initializers run before the body
+    fn find_fn_vars(&self, vars: &mut IRSymbolTable, prologue: &[&VarDecl]) {
+        let curr_fn = FunctionType::Local { id: self.id.to_string() };
+        for arg in self.args.iter() {
+            arg.find_vars(&curr_fn, vars);
+        }
+        for decl in prologue.iter() {
+            if let Some(init) = &decl.init_val {
+                init.find_vars(&curr_fn, vars);
+            }
+        }
         for stmt in self.body.iter() {
-            stmt.find_vars(&FunctionType::Local { id: self.id.to_string() }, vars);
+            stmt.find_vars(&curr_fn, vars);
         }
     }
-    
-    fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter) -> String {
+
+    fn convert_fn(&self, vars: &mut IRSymbolTable, counts: &mut Counter, prologue: &[&VarDecl]) -> String {
         let mut output : Vec<String> = Vec::new();
         let id = self.id.to_string();
+        vars.current = FunctionType::Local { id: id.clone() };
         output.push("".to_string());
         output.push(format!("[BEGIN {} LOCALS]", id));
         output.push(vars.fn_locals(&id));
@@ -116,6 +180,12 @@ impl ThreeAC for FnDecl {
         let leave_lbl = counts.lbl;
         counts.lbl += 1;
         counts.tmp = 0;
+        for (i, arg) in self.args.iter().enumerate() {
+            output.push(format!("\tgetarg {} [{}]", i + 1, arg.id()));
+        }
+        for decl in prologue.iter() {
+            output.push(format!("\t{}", decl.convert_3ac(vars, counts)));
+        }
         for stmt in self.body.iter() {
             let out = stmt.convert_3ac(vars, counts, leave_lbl);
             if out.trim() != "" {
@@ -128,6 +198,15 @@ impl ThreeAC for FnDecl {
     }
 }
 
+impl FormalDecl {
+    fn id(&self) -> String {
+        match self {
+            FormalDecl::VarDecl(x) => x.id.to_string(),
+            FormalDecl::FormalDecl{id, ..} => id.to_string(),
+        }
+    }
+}
+
 impl ThreeAC for FormalDecl {
     fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
         use self::FormalDecl::*;
@@ -164,7 +243,7 @@ impl Stmt3AC for Stmt {
         match self {
             Block(ref x) => x.convert_3ac(vars, counts, leave_lbl),
             Line(ref x) => x.convert_3ac(vars, counts, leave_lbl),
-            VarDecl(_) => "".to_string(),
+            VarDecl(ref x) => x.convert_3ac(vars, counts),
         }
     }
 }
@@ -180,6 +259,42 @@ impl Stmt3AC for BlockStmt {
                     stmt.find_vars(curr_fn, vars);
                 }
             },
+            DoWhile{body, cond} => {
+                for stmt in body.iter() {
+                    stmt.find_vars(curr_fn, vars);
+                }
+                cond.find_vars(curr_fn, vars);
+            },
+            Labeled{body, ..} => body.find_vars(curr_fn, vars),
+            For{init, cond, step, body} => {
+                if let Some(x) = init {
+                    x.find_vars(curr_fn, vars);
+                }
+                cond.find_vars(curr_fn, vars);
+
+                for stmt in body.iter() {
+                    stmt.find_vars(curr_fn, vars);
+                }
+                if let Some(x) = step {
+                    x.find_vars(curr_fn, vars);
+                }
+            },
+            Match{value, arms, default} => {
+                value.find_vars(curr_fn, vars);
+                vars.inc_fn_tmps(curr_fn, vars.exp_type(curr_fn, &value.kind));
+
+                for arm in arms.iter() {
+                    for _ in arm_tests(&arm.pattern) {
+                        vars.inc_fn_tmps(curr_fn, Variable3ACType::Bool);
+                    }
+                    for stmt in arm.body.iter() {
+                        stmt.find_vars(curr_fn, vars);
+                    }
+                }
+                for stmt in default.iter().flatten() {
+                    stmt.find_vars(curr_fn, vars);
+                }
+            },
             IfElse{cond, true_branch, false_branch} => {
                 cond.find_vars(curr_fn, vars);
                 
@@ -196,36 +311,52 @@ impl Stmt3AC for BlockStmt {
     fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter, leave_lbl: usize) -> String {
         use BlockStmt::*;
         let mut output: Vec<String> = Vec::new();
-        let is_while = match self {
-            While{cond: _, body: _} => true,
-            _ => false
-        };
-        let mut loop_head = 0;
-        if is_while {
-            loop_head = counts.lbl;
-            counts.lbl += 1;
-            output.push("".to_string());
-            output.push(format!("lbl_{}:\tnop", loop_head));
-        }
         match self {
-            While{cond, body} | If{cond, body} => {
+            While{..} | For{..} | DoWhile{..} => return self.convert_loop(vars, counts, leave_lbl, None),
+            Labeled{label, body} => return body.convert_loop(vars, counts, leave_lbl, Some(label.name.clone())),
+            If{cond, body} => {
                 let (pre_cond, new_cond) = 
                     cond.convert_3ac(vars, counts, Vec::new());
                 let after_lbl = counts.lbl;
                 counts.lbl += 1;
-                let pre_cond = quad_vec_to_string(pre_cond);
-                if pre_cond.trim()  != "".to_string() {
-                    output.push(format!("\t{}IFZ {} goto lbl_{}", pre_cond, new_cond, after_lbl));
-                } else {
-                    output.push(format!("IFZ {} goto lbl_{}", new_cond, after_lbl));
-                }
+                output.push(jump_unless(pre_cond, new_cond, after_lbl));
                 
                 for stmt in body {
                     output.push(format!("\t{}", stmt.convert_3ac(vars, counts, leave_lbl)));
                 }
 
-                if is_while {
-                    output.push(format!("\tgoto lbl_{}", loop_head));
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", after_lbl));
+            },
+            // a chain of tests, each arm jumping on to the next one when it doesn't fit
+            Match{value, arms, default} => {
+                let (pre_value, new_value) = 
+                    value.convert_3ac(vars, counts, Vec::new());
+                // evaluated once, 24Kmagic included
+                let held = format!("[tmp{}]", counts.tmp);
+                counts.tmp += 1;
+                output.push(format!("\t{}{} := {}", quad_vec_to_string(pre_value), held, new_value));
+                let after_lbl = counts.lbl;
+                counts.lbl += 1;
+
+                for arm in arms {
+                    let next_lbl = counts.lbl;
+                    counts.lbl += 1;
+                    for (op, operand) in arm_tests(&arm.pattern) {
+                        let test = format!("[tmp{}]", counts.tmp);
+                        counts.tmp += 1;
+                        output.push(format!("\t{} := {} {} {}", test, held, op, operand));
+                        output.push(format!("\tIFZ {} goto lbl_{}", test, next_lbl));
+                    }
+                    for stmt in arm.body.iter() {
+                        output.push(format!("\t{}", stmt.convert_3ac(vars, counts, leave_lbl)));
+                    }
+                    output.push(format!("\tgoto lbl_{}", after_lbl));
+                    output.push("".to_string());
+                    output.push(format!("lbl_{}:\tnop", next_lbl));
+                }
+                for stmt in default.iter().flatten() {
+                    output.push(format!("\t{}", stmt.convert_3ac(vars, counts, leave_lbl)));
                 }
                 output.push("".to_string());
                 output.push(format!("lbl_{}:\tnop", after_lbl));
@@ -237,13 +368,7 @@ impl Stmt3AC for BlockStmt {
                 counts.lbl += 1;
                 let false_lbl = counts.lbl;
                 counts.lbl += 1;
-                let pre_cond = quad_vec_to_string(pre_cond);
-
-                if pre_cond.trim()  != "".to_string() {
-                    output.push(format!("\t{}IFZ {} goto lbl_{}", pre_cond, new_cond, false_lbl));
-                } else {
-                    output.push(format!("IFZ {} goto lbl_{}", new_cond, false_lbl));
-                }
+                output.push(jump_unless(pre_cond, new_cond, false_lbl));
                 
                 for stmt in true_branch {
                     output.push(format!("\t{}", stmt.convert_3ac(vars, counts, leave_lbl)));
@@ -262,26 +387,118 @@ impl Stmt3AC for BlockStmt {
     }
 }
 
-impl Stmt3AC for LineStmt {
-    fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
-        self.kind.find_vars(curr_fn, vars);
+// the comparisons the matched value has to pass for an arm to run
+fn arm_tests(pattern: &Pattern) -> Vec<(&'static str, String)> {
+    match pattern {
+        Pattern::Int(x) => vec![("EQ64", x.to_string())],
+        Pattern::Range(lo, hi) => vec![("GTE64", lo.to_string()), ("LTE64", hi.to_string())],
+        Pattern::Bool(x) => vec![("EQ64", x.to_string())],
     }
+}
 
-    fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter, leave_lbl: usize) -> String {
-        self.kind.convert_3ac(vars, counts, leave_lbl)
+impl BlockStmt {
+    // every loop takes the labels continue and break jump to before its body,
+    // named after the loop's label if it has one
+    fn convert_loop(&self, vars: &mut IRSymbolTable, counts: &mut Counter, leave_lbl: usize, name: Option<String>) -> String {
+        use BlockStmt::*;
+        let mut output: Vec<String> = Vec::new();
+        match self {
+            While{cond, body} => {
+                let loop_head = counts.new_lbl();
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", loop_head));
+
+                let (pre_cond, new_cond) = 
+                    cond.convert_3ac(vars, counts, Vec::new());
+                let after_lbl = counts.new_lbl();
+                output.push(jump_unless(pre_cond, new_cond, after_lbl));
+
+                counts.loops.push(LoopLabels { name, next: loop_head, after: after_lbl });
+                for stmt in body {
+                    output.push(format!("\t{}", stmt.convert_3ac(vars, counts, leave_lbl)));
+                }
+                counts.loops.pop();
+
+                output.push(format!("\tgoto lbl_{}", loop_head));
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", after_lbl));
+            },
+            For{init, cond, step, body} => {
+                if let Some(x) = init {
+                    output.push(format!("\t{}", x.convert_3ac(vars, counts, leave_lbl)));
+                }
+                let loop_head = counts.new_lbl();
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", loop_head));
+
+                let (pre_cond, new_cond) = 
+                    cond.convert_3ac(vars, counts, Vec::new());
+                let after_lbl = counts.new_lbl();
+                let step_lbl = counts.new_lbl();
+                output.push(jump_unless(pre_cond, new_cond, after_lbl));
+
+                // continue still runs the step
+                counts.loops.push(LoopLabels { name, next: step_lbl, after: after_lbl });
+                for stmt in body {
+                    output.push(format!("\t{}", stmt.convert_3ac(vars, counts, leave_lbl)));
+                }
+                counts.loops.pop();
+
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", step_lbl));
+                if let Some(x) = step {
+                    output.push(format!("\t{}", x.convert_3ac(vars, counts, leave_lbl)));
+                }
+                output.push(format!("\tgoto lbl_{}", loop_head));
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", after_lbl));
+            },
+            // the body runs once before the condition is first checked
+            DoWhile{body, cond} => {
+                let loop_head = counts.new_lbl();
+                let cond_lbl = counts.new_lbl();
+                let after_lbl = counts.new_lbl();
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", loop_head));
+
+                counts.loops.push(LoopLabels { name, next: cond_lbl, after: after_lbl });
+                for stmt in body {
+                    output.push(format!("\t{}", stmt.convert_3ac(vars, counts, leave_lbl)));
+                }
+                counts.loops.pop();
+
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", cond_lbl));
+                let (pre_cond, new_cond) = 
+                    cond.convert_3ac(vars, counts, Vec::new());
+                output.push(format!("\t{}", jump_unless(pre_cond, new_cond, after_lbl)));
+                output.push(format!("\tgoto lbl_{}", loop_head));
+                output.push("".to_string());
+                output.push(format!("lbl_{}:\tnop", after_lbl));
+            },
+            _ => return self.convert_3ac(vars, counts, leave_lbl)
+        }
+        output.join("\n")
     }
 }
 
-fn handle_call(mut new: String, counts: &mut Counter) -> (String, String) {
-    let mut getret = "".to_string();
+fn jump_unless(pre_cond: Vec<String>, cond: String, target: usize) -> String {
+    let pre_cond = quad_vec_to_string(pre_cond);
+    if pre_cond.trim()  != "".to_string() {
+        format!("\t{}IFZ {} goto lbl_{}", pre_cond, cond, target)
+    } else {
+        format!("IFZ {} goto lbl_{}", cond, target)
+    }
+}
 
-    if new.contains("call") {
-        getret = format!("{}\n\tgetret [tmp{}]\n\t", new, counts.tmp);
-        new = format!("[tmp{}]", counts.tmp);
-        counts.tmp += 1;
+impl Stmt3AC for LineStmt {
+    fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
+        self.kind.find_vars(curr_fn, vars);
     }
 
-    (getret, new)
+    fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter, leave_lbl: usize) -> String {
+        self.kind.convert_3ac(vars, counts, leave_lbl)
+    }
 }
 
 impl Stmt3AC for LineStmtKind {
@@ -289,12 +506,38 @@ impl Stmt3AC for LineStmtKind {
         use LineStmtKind::*;
 
         match self {
-            Assign{dest: _, src} => src.find_vars(curr_fn, vars),
+            Assign{dest, src} => {
+                src.find_vars(curr_fn, vars);
+                dest.find_place_vars(curr_fn, vars);
+            },
+            CompoundAssign{dest, src, ..} => {
+                src.find_vars(curr_fn, vars);
+                dest.find_place_vars(curr_fn, vars);
+                if dest.is_indexed() {
+                    let elem_type = vars.loc_type(curr_fn, dest);
+                    vars.inc_fn_tmps(curr_fn, elem_type);
+                    vars.inc_fn_tmps(curr_fn, elem_type);
+                }
+            },
+            PostDec{loc} | PostInc{loc} => {
+                loc.find_place_vars(curr_fn, vars);
+                if loc.is_indexed() {
+                    vars.inc_fn_tmps(curr_fn, Variable3ACType::Int);
+                    vars.inc_fn_tmps(curr_fn, Variable3ACType::Int);
+                }
+            },
+            Take{recipient} => {
+                recipient.find_place_vars(curr_fn, vars);
+                if recipient.is_indexed() {
+                    let elem_type = vars.loc_type(curr_fn, recipient);
+                    vars.inc_fn_tmps(curr_fn, elem_type);
+                }
+            },
             Give{output} => output.find_vars(curr_fn, vars),
             Return{result} => {match result {
                 Some(exp) => exp.find_vars(curr_fn, vars),
                 None => ()}},
-            Call(ref exp) => exp.find_vars(curr_fn, vars),
+            Call(ref exp) => exp.find_arg_vars(curr_fn, vars),
             _ => ()
         }
     }
@@ -306,18 +549,27 @@ impl Stmt3AC for LineStmtKind {
             Assign{dest, src} => {
                 let (pre_src, new_src) = 
                     src.convert_3ac(vars, counts, Vec::new());
-                let (getret, new_src) = handle_call(new_src, counts);
-                let pre_src = quad_vec_to_string(pre_src);
-                if pre_src.trim() != "".to_string() {
-                    format!("{}{}[{}] := {}", pre_src, getret, dest, new_src)
-                } else {
-                    format!("{}[{}] := {}", getret, dest, new_src)
+                match dest.place(vars, counts, pre_src) {
+                    (pre_dest, Place::Var(var)) => format!("{}{} := {}", quad_vec_to_string(pre_dest), var, new_src),
+                    (pre_dest, Place::Elem { array, slot }) => {
+                        format!("{}STORE {} {} {}", quad_vec_to_string(pre_dest), array, slot, new_src)
+                    }
                 }
             },
 
-            PostDec{loc} => format!("[{}] := [{}] ADD64 1", loc, loc),
+            CompoundAssign{dest, op, src} => {
+                let (pre_src, new_src) = 
+                    src.convert_3ac(vars, counts, Vec::new());
+                let op = match vars.loc_type(&vars.current, dest) {
+                    Variable3ACType::Float => float_op(op.to_3ac()),
+                    _ => op.to_3ac()
+                };
+                dest.update(&op, &new_src, pre_src, vars, counts)
+            },
+
+            PostDec{loc} => loc.update("SUB64", "1", Vec::new(), vars, counts),
 
-            PostInc{loc} => format!("[{}] := [{}] SUB64 1", loc, loc),
+            PostInc{loc} => loc.update("ADD64", "1", Vec::new(), vars, counts),
 
             Give{output} => {
                 let (pre_out, mut new_output) = 
@@ -333,31 +585,37 @@ impl Stmt3AC for LineStmtKind {
                 format!("{}WRITE {}", quad_vec_to_string(pre_out), new_output)
             },
 
-            Take{recipient} => format!("READ [{}]", recipient),
+            Take{recipient} => match recipient.place(vars, counts, Vec::new()) {
+                (_, Place::Var(var)) => format!("READ {}", var),
+                (mut pre, Place::Elem { array, slot }) => {
+                    let tmp = format!("[tmp{}]", counts.tmp);
+                    counts.tmp += 1;
+                    pre.push(format!("READ {}", tmp));
+                    format!("{}STORE {} {} {}", quad_vec_to_string(pre), array, slot, tmp)
+                }
+            },
 
             Return{result} => {match result {
                 Some(x) => {
                     let (pre_out, new_ret) = 
                         x.convert_3ac(vars, counts, Vec::new());
-                    let (getret, new_ret) = handle_call(new_ret, counts);
-                    let pre_out = quad_vec_to_string(pre_out);
-                    if pre_out.trim() != "".to_string() {
-                        format!("{}{}setret {}\n\tgoto lbl_{}", pre_out, getret, new_ret, leave_lbl)
-                    } else {
-                        format!("{}setret {}\n\tgoto lbl_{}", getret, new_ret, leave_lbl)
-                    }
-                    
+                    format!("{}setret {}\n\tgoto lbl_{}", quad_vec_to_string(pre_out), new_ret, leave_lbl)
                 },
                 None => "".to_string()}},
 
             Exit => "exit".to_string(),
 
-            Call(ref exp) => {
-                let (pre_call, new_call) = 
-                    exp.convert_3ac(vars, counts, Vec::new());
-                // println!("owowowowowo{}", new_call);
-                format!("{}\n\t{}", quad_vec_to_string(pre_call), new_call)
+            Break{label} => match counts.find_loop(label) {
+                Some(x) => format!("goto lbl_{}", x.after),
+                None => "".to_string()
             },
+
+            Continue{label} => match counts.find_loop(label) {
+                Some(x) => format!("goto lbl_{}", x.next),
+                None => "".to_string()
+            },
+
+            Call(ref exp) => exp.convert_call(vars, counts, Vec::new()).join("\n\t"),
         }
     }
 }
@@ -379,9 +637,11 @@ impl Exp3AC for ExpKind {
         match self {
             UnaryExp(ref exp) => exp.find_vars(curr_fn, vars),
             BinaryExp(ref exp) => exp.find_vars(curr_fn, vars),
+            Ternary(ref exp) => exp.find_vars(curr_fn, vars),
             CallExp(ref exp) => exp.find_vars(curr_fn, vars),
             // IntLit(ref lit) => "".to_string(),
             StrLit(ref lit) => vars.add_string(lit.to_string()),
+            Loc(ref loc) => loc.find_load_vars(curr_fn, vars),
             _ => ()
         }
     }
@@ -395,10 +655,13 @@ impl Exp3AC for ExpKind {
             Magic => (curr, "24Kmagic".to_string()),
             UnaryExp(exp) => exp.convert_3ac(vars, counts, curr),
             BinaryExp(exp) => exp.convert_3ac(vars, counts, curr),
+            Ternary(exp) => exp.convert_3ac(vars, counts, curr),
             CallExp(exp) => exp.convert_3ac(vars, counts, curr),
-            IntLit(i32) => (curr, i32.to_string()),
+            IntLit(lit) => (curr, lit.to_string()),
+            FloatLit(lit) => (curr, format!("{:?}", lit)),
+            CharLit(lit) => (curr, Operand::Char(*lit).to_string()),
             StrLit(str) => (curr, vars.id_from_string(str)),
-            Loc(loc) => (curr, format!("[{}]", loc))
+            Loc(loc) => loc.load(vars, counts, curr)
         }
     }
 }
@@ -406,20 +669,28 @@ impl Exp3AC for ExpKind {
 impl Exp3AC for UnaryExp {
     fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
         let exp_type = match *self.kind.clone() {
-            UnaryExpKind::Neg => Variable3ACType::Int,
+            UnaryExpKind::Neg => vars.exp_type(curr_fn, &self.exp.kind),
+            UnaryExpKind::BitNot => Variable3ACType::Int,
             UnaryExpKind::Not => Variable3ACType::Bool
         };
         
-        vars.inc_fn_tmps(curr_fn, exp_type);
+        // temporaries are numbered in the order convert_3ac hands them out,
+        // which is after the operands'
         self.exp.find_vars(curr_fn, vars);
+        vars.inc_fn_tmps(curr_fn, exp_type);
     }
 
     fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter, mut curr: Vec<String>) -> (Vec<String>, String) {
         let exp;
         (curr, exp) = self.exp.convert_3ac(vars, counts, curr);
 
+        let op = match vars.exp_type(&vars.current, &self.exp.kind) {
+            Variable3ACType::Float => float_op(self.kind.to_3ac()),
+            _ => self.kind.to_3ac()
+        };
+
         let ret = format!("[tmp{}]", counts.tmp);
-        curr.push(format!("{} := {} {}", ret, self.kind.to_3ac(), exp));
+        curr.push(format!("{} := {} {}", ret, op, exp));
         counts.tmp += 1;
 
         (curr, ret)
@@ -431,6 +702,7 @@ impl ExpKind3AC for UnaryExpKind {
         match self {
             UnaryExpKind::Not => "NOT64".to_string(),
             UnaryExpKind::Neg => "NEG64".to_string(),
+            UnaryExpKind::BitNot => "BITNOT64".to_string(),
         }
     }
 }
@@ -438,26 +710,16 @@ impl ExpKind3AC for UnaryExpKind {
 impl Exp3AC for BinaryExp {
     fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
         use BinaryExpKind::*;
-        use TypeKind::*;
-        use PrimType::*;
         let exp_type = match *self.kind.clone() {
             And | Or => Variable3ACType::Bool,
-            Equals | NotEquals => match self.lhs.expr_type.clone() {
-                Some(x) => match *x.clone().kind {
-                    Prim(prim) => match prim {
-                        Bool => Variable3ACType::Bool,
-                        _ => Variable3ACType::Int
-                    },
-                    _ => Variable3ACType::Int
-                },
-                None => Variable3ACType::Int
-            },
+            Equals | NotEquals | Greater | Less | GreaterEq | LessEq => Variable3ACType::Bool,
+            Plus if self.joins_strings(curr_fn, vars) => Variable3ACType::String,
+            Plus | Minus | Times | Divide if self.on_floats(curr_fn, vars) => Variable3ACType::Float,
             _ => Variable3ACType::Int
         };
-        vars.inc_fn_tmps(curr_fn, exp_type);
-
         self.lhs.find_vars(curr_fn, vars);
         self.rhs.find_vars(curr_fn, vars);
+        vars.inc_fn_tmps(curr_fn, exp_type);
     }
 
     fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter, mut curr: Vec<String>) -> (Vec<String>, String) {
@@ -467,14 +729,86 @@ impl Exp3AC for BinaryExp {
         let rhs;
         (curr, rhs) = self.rhs.convert_3ac(vars, counts, curr);
 
+        let op = if self.compares_strings(&vars.current, vars) {
+            match *self.kind {
+                BinaryExpKind::NotEquals => "STRNEQ64".to_string(),
+                _ => "STREQ64".to_string()
+            }
+        } else if self.joins_strings(&vars.current, vars) {
+            "CAT64".to_string()
+        } else if self.on_floats(&vars.current, vars) {
+            float_op(self.kind.to_3ac())
+        } else {
+            self.kind.to_3ac()
+        };
+
         let ret = format!("[tmp{}]", counts.tmp);
-        curr.push(format!("{} := {} {} {}", ret, lhs, self.kind.to_3ac(), rhs));
+        curr.push(format!("{} := {} {} {}", ret, lhs, op, rhs));
         counts.tmp += 1;
         
         (curr, ret)
     }
 }
 
+impl Exp3AC for TernaryExp {
+    fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
+        let exp_type = vars.exp_type(curr_fn, &self.true_branch.kind);
+
+        // the result is handed out before either branch is lowered
+        self.cond.find_vars(curr_fn, vars);
+        vars.inc_fn_tmps(curr_fn, exp_type);
+        self.true_branch.find_vars(curr_fn, vars);
+        self.false_branch.find_vars(curr_fn, vars);
+    }
+
+    // each branch is lowered behind its own label, so only the chosen one runs
+    fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter, mut curr: Vec<String>) -> (Vec<String>, String) {
+        let cond;
+        (curr, cond) = self.cond.convert_3ac(vars, counts, curr);
+
+        let ret = format!("[tmp{}]", counts.tmp);
+        counts.tmp += 1;
+        let false_lbl = counts.new_lbl();
+        let after_lbl = counts.new_lbl();
+        curr.push(format!("IFZ {} goto lbl_{}", cond, false_lbl));
+
+        let true_val;
+        (curr, true_val) = self.true_branch.convert_3ac(vars, counts, curr);
+        curr.push(format!("{} := {}", ret, true_val));
+        curr.push(format!("goto lbl_{}", after_lbl));
+
+        curr.push(format!("lbl_{}:\tnop", false_lbl));
+        let false_val;
+        (curr, false_val) = self.false_branch.convert_3ac(vars, counts, curr);
+        curr.push(format!("{} := {}", ret, false_val));
+        curr.push(format!("lbl_{}:\tnop", after_lbl));
+
+        (curr, ret)
+    }
+}
+
+impl BinaryExp {
+    // strings are compared by their contents, not by where they live
+    fn compares_strings(&self, curr_fn: &FunctionType, vars: &IRSymbolTable) -> bool {
+        matches!(*self.kind, BinaryExpKind::Equals | BinaryExpKind::NotEquals) &&
+            vars.exp_type(curr_fn, &self.lhs.kind) == Variable3ACType::String
+    }
+
+    fn joins_strings(&self, curr_fn: &FunctionType, vars: &IRSymbolTable) -> bool {
+        *self.kind == BinaryExpKind::Plus && vars.exp_type(curr_fn, &self.lhs.kind) == Variable3ACType::String
+    }
+
+    // both sides agree on their type, so the lhs tells whether the op is done in SSE
+    fn on_floats(&self, curr_fn: &FunctionType, vars: &IRSymbolTable) -> bool {
+        vars.exp_type(curr_fn, &self.lhs.kind) == Variable3ACType::Float
+    }
+}
+
+// the float ops are named after the int ones, with an F before the width
+fn float_op(op: String) -> String {
+    op.replace("64", "F64")
+}
+
 impl ExpKind3AC for BinaryExpKind {
     fn to_3ac(&self) -> String {
         use BinaryExpKind::*;
@@ -491,34 +825,183 @@ impl ExpKind3AC for BinaryExpKind {
             Minus => "SUB64".to_string(),
             Times => "MULT64".to_string(),
             Divide => "DIV64".to_string(),
+            Modulo => "MOD64".to_string(),
+            BitAnd => "BITAND64".to_string(),
+            BitOr => "BITOR64".to_string(),
+            BitXor => "XOR64".to_string(),
+            ShiftLeft => "SHL64".to_string(),
+            ShiftRight => "SHR64".to_string(),
         }
     }
 }
 
 impl Exp3AC for CallExp {
     fn find_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
-        use PrimType::*;
-        let call_exp_type = match self.fn_type.clone() {
-            Some(x) => match *x.kind.clone() {
-                TypeKind::Prim(prim) => match prim {
-                    Bool => Variable3ACType::Bool,
-                    _ => Variable3ACType::Int
-                },
-                _ => Variable3ACType::Int
-            }
-            None => Variable3ACType::Int
-        };
+        let call_exp_type = vars.ret_type(&self.name.to_string());
+        self.find_arg_vars(curr_fn, vars);
         vars.inc_fn_tmps(curr_fn, call_exp_type);
+    }
+
+    fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter, curr: Vec<String>) -> (Vec<String>, String) {
+        let mut curr = self.convert_call(vars, counts, curr);
+        let ret = format!("[tmp{}]", counts.tmp);
+        curr.push(format!("getret {}", ret));
+        counts.tmp += 1;
+
+        (curr, ret)
+    }
+}
+
+impl CallExp {
+    // a call made for its side effects alone never needs a temporary for its result
+    fn find_arg_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
         for arg in self.args.iter() {
             arg.find_vars(curr_fn, vars);
         }
     }
 
-    fn convert_3ac(&self, vars: &mut IRSymbolTable, counts: &mut Counter, mut curr: Vec<String>) -> (Vec<String>, String) {
+    // arguments are all evaluated before the first one is passed, so the
+    // setargs sit right above the call
+    fn convert_call(&self, vars: &mut IRSymbolTable, counts: &mut Counter, mut curr: Vec<String>) -> Vec<String> {
+        let mut args : Vec<String> = Vec::new();
         for arg in self.args.iter() {
-            let _ : String;
-            (curr, _) = arg.convert_3ac(vars, counts, curr);
+            let new_arg;
+            (curr, new_arg) = arg.convert_3ac(vars, counts, curr);
+            args.push(new_arg);
+        }
+        for (i, arg) in args.iter().enumerate() {
+            curr.push(format!("setarg {} {}", i + 1, arg));
+        }
+        curr.push(format!("call {}", self.callee(vars)));
+        curr
+    }
+
+    // `len` of an array goes to the array built-in, not the string one
+    fn callee(&self, vars: &IRSymbolTable) -> String {
+        let name = self.name.to_string();
+        let measures_array = name == "len" && self.args.first()
+            .is_some_and(|arg| matches!(vars.exp_kind(&vars.current, &arg.kind), Some(TypeKind::Array { .. })));
+        if measures_array { "array.len".to_string() } else { name }
+    }
+}
+
+/// Where a location lives: a variable of its own, or a slot of an array.
+enum Place {
+    Var(String),
+    Elem { array: String, slot: String },
+}
+
+impl Loc {
+    // temporaries are counted in the order `slot` hands them out
+    fn find_place_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
+        if let LocKind::Index { ref base, ref index } = *self.kind {
+            base.find_load_vars(curr_fn, vars);
+            index.find_vars(curr_fn, vars);
+            if self.stride(curr_fn, vars) != 1 {
+                vars.inc_fn_tmps(curr_fn, Variable3ACType::Int);
+            }
+        } else if let Some((elem, path)) = self.indexed_field() {
+            elem.find_place_vars(curr_fn, vars);
+            if elem.offset(curr_fn, vars, &path) != 0 {
+                vars.inc_fn_tmps(curr_fn, Variable3ACType::Int);
+            }
+        }
+    }
+
+    fn find_load_vars(&self, curr_fn: &FunctionType, vars: &mut IRSymbolTable) {
+        self.find_place_vars(curr_fn, vars);
+        if self.is_indexed() {
+            let elem_type = vars.loc_type(curr_fn, self);
+            vars.inc_fn_tmps(curr_fn, elem_type);
+        }
+    }
+
+    fn stride(&self, curr_fn: &FunctionType, vars: &IRSymbolTable) -> usize {
+        vars.loc_kind(curr_fn, self).map(|kind| vars.stride(&kind)).unwrap_or(1)
+    }
+
+    // the slot of the field `path` within this element
+    fn offset(&self, curr_fn: &FunctionType, vars: &IRSymbolTable, path: &str) -> usize {
+        match vars.loc_kind(curr_fn, self) {
+            Some(TypeKind::Class(class)) => vars.field(&class.name, path).map(|(i, _)| i).unwrap_or(0),
+            _ => 0
+        }
+    }
+
+    /// The array an indexed location is in and the slot it takes up there.
+    /// Elements of a class take up a slot for each field, one after the other.
+    fn slot(&self, vars: &mut IRSymbolTable, counts: &mut Counter, mut curr: Vec<String>) -> (Vec<String>, String, String) {
+        let (array, mut slot, step) = match (&*self.kind, self.indexed_field()) {
+            (LocKind::Index { base, index }, _) => {
+                let (array, slot);
+                (curr, array) = base.load(vars, counts, curr);
+                (curr, slot) = index.convert_3ac(vars, counts, curr);
+                let stride = self.stride(&vars.current, vars);
+                (array, slot, (stride != 1).then(|| format!("MULT64 {}", stride)))
+            },
+            (_, Some((elem, path))) => {
+                let (array, slot);
+                (curr, array, slot) = elem.slot(vars, counts, curr);
+                let offset = elem.offset(&vars.current, vars, &path);
+                (array, slot, (offset != 0).then(|| format!("ADD64 {}", offset)))
+            },
+            _ => unreachable!("only a location inside an array has a slot")
+        };
+        if let Some(step) = step {
+            let tmp = format!("[tmp{}]", counts.tmp);
+            curr.push(format!("{} := {} {}", tmp, slot, step));
+            counts.tmp += 1;
+            slot = tmp;
+        }
+        (curr, array, slot)
+    }
+
+    fn place(&self, vars: &mut IRSymbolTable, counts: &mut Counter, curr: Vec<String>) -> (Vec<String>, Place) {
+        if !self.is_indexed() {
+            return (curr, Place::Var(format!("[{}]", self)));
+        }
+        let (curr, array, slot) = self.slot(vars, counts, curr);
+        (curr, Place::Elem { array, slot })
+    }
+
+    /// The value at this location, loaded into a temporary if it is in an array.
+    /// A character of a string is looked up by the runtime.
+    fn load(&self, vars: &mut IRSymbolTable, counts: &mut Counter, mut curr: Vec<String>) -> (Vec<String>, String) {
+        if let LocKind::Index { ref base, ref index } = *self.kind {
+            if matches!(vars.loc_kind(&vars.current, base), Some(TypeKind::Prim(PrimType::String))) {
+                let (string, at);
+                (curr, string) = base.load(vars, counts, curr);
+                (curr, at) = index.convert_3ac(vars, counts, curr);
+                let tmp = format!("[tmp{}]", counts.tmp);
+                curr.push(format!("setarg 1 {}\n\tsetarg 2 {}\n\tcall string.at\n\tgetret {}", string, at, tmp));
+                counts.tmp += 1;
+                return (curr, tmp);
+            }
+        }
+        match self.place(vars, counts, curr) {
+            (curr, Place::Var(var)) => (curr, var),
+            (mut curr, Place::Elem { array, slot }) => {
+                let tmp = format!("[tmp{}]", counts.tmp);
+                curr.push(format!("{} := LOAD {} {}", tmp, array, slot));
+                counts.tmp += 1;
+                (curr, tmp)
+            }
+        }
+    }
+
+    // ++, -- and the compound assignments read the location, apply op with
+    // the operand and write it back
+    fn update(&self, op: &str, operand: &str, pre: Vec<String>, vars: &mut IRSymbolTable, counts: &mut Counter) -> String {
+        match self.place(vars, counts, pre) {
+            (pre, Place::Var(var)) => format!("{}{} := {} {} {}", quad_vec_to_string(pre), var, var, op, operand),
+            (mut pre, Place::Elem { array, slot }) => {
+                let old = format!("[tmp{}]", counts.tmp);
+                let new = format!("[tmp{}]", counts.tmp + 1);
+                counts.tmp += 2;
+                pre.push(format!("{} := LOAD {} {}", old, array, slot));
+                pre.push(format!("{} := {} {} {}", new, old, op, operand));
+                format!("{}STORE {} {} {}", quad_vec_to_string(pre), array, slot, new)
+            }
         }
-        (curr, format!("call {}", self.name.to_string()))
     }
 }
\ No newline at end of file
@@ -1,12 +1,21 @@
 use std::{collections::{HashMap, VecDeque}, fmt::{Display, self}};
 
 use super::three_ac::Variable3ACType;
+use super::ir::{is_float_literal, parse_char, IRFunction, Quad};
+use crate::parser::ast::PrimType;
+use crate::parser::ast::builtin::{builtin, Builtin};
+use crate::scanner::tokens::unescape;
 
-pub fn convert_x86(ir: String) -> String {
+mod regalloc;
+use regalloc::Allocation;
+
+pub mod peephole;
+
+pub fn convert_x86(ir: String, bounds_checks: bool, overflow_checks: bool) -> String {
 	let mut ir = ir.as_str().split("[BEGIN ").collect::<VecDeque<&str>>();
 	ir.pop_front(); // get rid of some whitespace
 
-	let table : &mut FnSymbolTableWrapper = &mut FnSymbolTableWrapper(HashMap::new());
+	let table : &mut FnSymbolTableWrapper = &mut FnSymbolTableWrapper(HashMap::new(), bounds_checks, overflow_checks);
 	let mut output : Vec<String> = Vec::new();
 
 	output.push(match ir.pop_front() { //globals
@@ -21,6 +30,13 @@ pub fn convert_x86(ir: String) -> String {
 	}
 
 	output.push("\n\tmovq $60,\t%rax\n\tmovq $1,\t%rdi\n\t\n\tsyscall".to_string());
+	// every bounds check jumps here, from whatever depth the stack is at
+	if output.iter().any(|x| x.contains(ARRAY_INDEX_ERROR)) {
+		output.push(format!("{ARRAY_INDEX_ERROR}:\n\tandq $-16,\t%rsp\n\tcallq arrayIndexError"));
+	}
+	if output.iter().any(|x| x.contains(INTEGER_OVERFLOW_ERROR)) {
+		output.push(format!("{INTEGER_OVERFLOW_ERROR}:\n\tandq $-16,\t%rsp\n\tcallq integerOverflowError"));
+	}
 	output.push("".to_string());
     output.join("\n")
 }
@@ -41,21 +57,31 @@ impl Display for SemanticSymbol {
     }
 }
 
-struct FnSymbolTableWrapper(HashMap<String, SemanticSymbol>);
+// the flags say whether array accesses check their index first, and whether
+// arithmetic that overflows stops the program instead of wrapping around
+struct FnSymbolTableWrapper(HashMap<String, SemanticSymbol>, bool, bool);
 trait FnSymbolTable : Display {
 	// fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result;
 	fn translate_globals(&mut self, globals: &str) -> String;
-	fn populate_fn(&mut self, fn_vars: Vec<&str>) -> usize; //returns the num of locals
+	fn populate_fn(&mut self, func: &IRFunction, alloc: &Allocation);
 	fn translate_fn(&mut self, function: &str) -> String; 
-	fn translate_fn_code(&mut self, function: &str, num_locals: usize) -> String; 
+	fn translate_fn_code(&mut self, func: &IRFunction, alloc: &Allocation) -> String; 
 
-	fn translate_statement(&self, statement: &str, num_locals: usize) -> String;
+	fn translate_statement(&self, statement: &str, alloc: &Allocation) -> String;
 	fn translate_assign(&self, statement: &str) -> String;
+	fn translate_load(&self, ops: Vec<&str>) -> String;
+	fn translate_store(&self, statement: &str) -> String;
+	fn element_address(&self, array: &str, index: &str) -> String;
 	fn translate_compare(&self, ops: Vec<&str>) -> String;
+	fn translate_string_op(&self, ops: Vec<&str>) -> String;
+	fn translate_float_op(&self, ops: Vec<&str>) -> String;
+	fn translate_float_compare(&self, ops: Vec<&str>) -> String;
 	fn translate_multdiv(&self, ops: Vec<&str>) -> String;
+	fn translate_shift(&self, ops: Vec<&str>) -> String;
 	fn translate_write(&self, statement: &str) -> String;
 	fn translate_read(&self, statement: &str) -> String;
 	fn translate_if(&self, statement: &str) -> String;
+	fn translate_call(&self, statement: &str) -> String;
 
 	fn clear_fn(&mut self);
 
@@ -121,11 +147,11 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 					None => ("", "")
 				};
 				
-				output.push(format!("{lbl}: .asciz {string}"));
+				output.push(format!("{lbl}: .asciz {}", asciz(string)));
 				
 				label = lbl.to_string();
 				symbol = SemanticSymbol {
-					location: format!("({lbl})"),
+					location: format!("${lbl}"),
 					global: true,
 					sym_type: Variable3ACType::String
 				};
@@ -134,6 +160,9 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 				label = global[0].to_string();
 				let sym_type = match global[1] {
 					"bool" => Variable3ACType::Bool,
+					"string" => Variable3ACType::String,
+					"float" => Variable3ACType::Float,
+					"char" => Variable3ACType::Char,
 					_ => Variable3ACType::Int
 				};
 				output.push(format!("gbl_{label}: .quad 0"));
@@ -151,89 +180,89 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 		output.join("\n")
 	}
 
-	fn populate_fn(&mut self, fn_vars: Vec<&str>) -> usize {
-		let mut num_locals = 0;
-		for (i, var) in fn_vars.iter().enumerate() {
-			let var = var.split('-').collect::<Vec<&str>>();
-			let label = var[0];
-			let sym_type = match var[1] {
-				"bool" => Variable3ACType::Bool,
-				_ => Variable3ACType::Int
+	fn populate_fn(&mut self, func: &IRFunction, alloc: &Allocation) {
+		for (label, sym_type) in func.locals.iter() {
+			// locals that never show up in the code don't need a home
+			let location = match alloc.locations.get(label) {
+				Some(x) => x.clone(),
+				None => continue
 			};
 			let symbol = SemanticSymbol {
-				location: format!("-{}(%rbp)", i*8 + 24),
+				location,
 				global: false,
-				sym_type
+				sym_type: *sym_type
 			};
 			self.0.insert(format!("[{label}]"), symbol);
-			num_locals = i;
 		}
-		num_locals
 	}
 
 	fn translate_fn(&mut self, function: &str) -> String {
-		let mut output : Vec<String> = Vec::new();
-		let mut function = function.split("LOCALS]\n").collect::<Vec<&str>>().into_iter();
-		
-		function.next(); //extra info
-		
-		let locals = match function.next() {
-			Some(x) => x,
-			None => return "".to_string()
-		};
-		// println!("{}", &self);
-		let num_locals : usize;
-		if !locals.starts_with("\n[END ") {
-			let mut locals = locals.split("\n").collect::<Vec<&str>>();
-			locals.pop();
-			num_locals = self.populate_fn(locals);
-		} else {
-			num_locals = 0;
-		}
-
-		// output.push(function_prologue(num_locals));
-		let code = match function.next() {
-			Some(x) => x,
-			None => return "".to_string()
-		};
+		let func = IRFunction::parse(function);
+		let alloc = regalloc::allocate(&func);
 
-		// println!("{}", locals);
-		// println!("OWO!!!!!!!!!!!");
-		// println!("{}", &self);
-		// println!("{}", &code);
-		output.push(self.translate_fn_code(code, num_locals));
+		self.populate_fn(&func, &alloc);
+		let output = self.translate_fn_code(&func, &alloc);
 		self.clear_fn();
-		output.join("\n")
+		output
 	}
 
-	fn translate_fn_code(&mut self, code: &str, num_locals: usize) -> String {
+	fn translate_fn_code(&mut self, func: &IRFunction, alloc: &Allocation) -> String {
 		let mut output : Vec<String> = Vec::new();
-		let mut code = code.split("\n").collect::<Vec<&str>>().into_iter();
-		code.next();
-		let function_name = match code.next() {
-			Some(x) => x.split(":").collect::<Vec<&str>>()[0],
-			None => return "".to_string()
-		};
-		output.push(format!("{}:{}", function_name, function_prologue(num_locals)));
+		output.push(format!("{}:{}", func.name, function_prologue(alloc)));
 
-		for stmt in code {
-			output.push(self.translate_statement(stmt, num_locals));
+		// arguments are pushed at the call itself, above any saved registers
+		let mut args : Vec<String> = Vec::new();
+		for (i, line) in func.body.iter().enumerate() {
+			if let Quad::Enter(_) = line.quad {
+				continue;
+			}
+			if let Quad::Nop = line.quad {
+				output.push("".to_string());
+			}
+
+			// the statement templates only know the labels on nops and leaves,
+			// any other label (SSA names the blocks it splits off) goes on a line of its own
+			let stmt = match &line.label {
+				Some(lbl) if !matches!(line.quad, Quad::Nop | Quad::Leave(_)) => {
+					output.push(format!("{}:", lbl));
+					self.translate_statement(&format!("\t{}", line.quad), alloc)
+				},
+				_ => self.translate_statement(&line.to_string(), alloc)
+			};
+			if let Quad::SetArg(..) = line.quad {
+				args.push(stmt);
+				continue;
+			}
+			let regs = alloc.call_saves.get(&i).map(|regs| &regs[..]).unwrap_or(&[]);
+			let in_registers = match &line.quad {
+				Quad::Call(callee) => builtin(callee),
+				_ => None
+			};
+			if regs.is_empty() && args.is_empty() {
+				output.push(stmt);
+			} else {
+				output.push(save_around_call(regs, &args, stmt, in_registers));
+				args.clear();
+			}
 		}
 
 		output.join("\n")
 	}
 	
-	fn translate_statement(&self, statement: &str, num_locals: usize) -> String {
+	fn translate_statement(&self, statement: &str, alloc: &Allocation) -> String {
 		if statement.contains(":=") {
 			return self.translate_assign(statement);
 		}
+		if statement.trim().starts_with("STORE ") {
+			return self.translate_store(statement);
+		}
 		if statement.contains("nop") {
 			return statement.to_string();
 		}
 		if statement.contains("leave") {
 			return format!("{}:{}",
 				statement.split(":").collect::<Vec<&str>>()[0],
-				function_epilogue(num_locals));
+				function_epilogue(alloc));
 		}
 		if statement.contains("WRITE") {
 			return self.translate_write(statement);
@@ -245,9 +274,16 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 			return self.translate_if(statement);
 		}
 		if statement.contains("goto") {
-			let dest = statement.split(" ").collect::<Vec<&str>>()[1];
+			let dest = statement.trim().split(" ").collect::<Vec<&str>>()[1];
 			return format!("\tjmp\t\t{dest}");
 		}
+		if statement.trim() == "exit" {
+			return "\tmovq $0,\t%rdi\n\tcallq exit".to_string();
+		}
+		if statement.contains("call") || statement.contains("setret") || statement.contains("getret") ||
+			statement.contains("setarg") || statement.contains("getarg") {
+			return self.translate_call(statement);
+		}
 		"".to_string()
 	}
 
@@ -258,7 +294,11 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 		// direct assign, no math involved
 		if ops.len() == 3 {
 			let src = ops[2];
-			match src.parse::<i32>() {
+			match immediate(src).ok_or(()) {
+				// only movq into a register takes all 64 bits of an immediate
+				Ok(x) if i32::try_from(x).is_err() && !dest.starts_with('%') => {
+					return format!("\tmovq ${},\t%rax\n\tmovq %rax,\t{}", x, dest)
+				},
 				Ok(x) => return format!("\tmovq ${},\t{}", x, dest),
 				Err(_) => {
 					let (prepend, src) = self.get_loc(src);
@@ -271,13 +311,21 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 		if ops.len() == 4 {
 			let operand = get_operation_quad(ops[2]);
 			let (prepend, src) = self.parse_loc(ops[3]);
-			return format!("{}\tmovq {},\t%rax\n\t{} %rax\n\tmovq %rax,\t{}", prepend, src, operand, dest);
+			return format!("{}\tmovq {},\t%rax\n\t{}%rax\n{}\tmovq %rax,\t{}", prepend, src, operand, self.overflow_check(ops[2]), dest);
+		}
+
+		if ops[2] == "LOAD" {
+			return self.translate_load(ops);
 		}
 
 		// binary
 		match ops[3] {
 			"EQ64" | "NEQ64" | "LT64" | "GT64" | "GTE64" | "LTE64" => return self.translate_compare(ops),
-			"MULT64" | "DIV64" => return self.translate_multdiv(ops),
+			"MULT64" | "DIV64" | "MOD64" => return self.translate_multdiv(ops),
+			"SHL64" | "SHR64" => return self.translate_shift(ops),
+			"STREQ64" | "STRNEQ64" | "CAT64" => return self.translate_string_op(ops),
+			"ADDF64" | "SUBF64" | "MULTF64" | "DIVF64" => return self.translate_float_op(ops),
+			"EQF64" | "NEQF64" | "LTF64" | "GTF64" | "GTEF64" | "LTEF64" => return self.translate_float_compare(ops),
 			_ => ()
 		};
 		
@@ -286,18 +334,60 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 		let (prepend2, src2) = self.parse_loc(ops[4]);
 		let (prepend3, dest) = self.parse_loc(ops[0].trim());
 		return format!(
-			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n\t{} %rbx,\t%rax\n{}\tmovq %rax,\t{}", 
-			prepend1, prepend2, src1, src2, quad, prepend3, dest
+			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n\t{} %rbx,\t%rax\n{}{}\tmovq %rax,\t{}", 
+			prepend1, prepend2, src1, src2, quad, self.overflow_check(ops[3]), prepend3, dest
+		)
+	}
+
+	fn translate_load(&self, ops: Vec<&str>) -> String {
+		let (prepend, dest) = self.parse_loc(ops[0].trim());
+		format!("{}\tmovq (%rbx),\t%rax\n{}\tmovq %rax,\t{}", self.element_address(ops[3], ops[4]), prepend, dest)
+	}
+
+	fn translate_store(&self, statement: &str) -> String {
+		let ops : Vec<&str> = statement.trim().split(' ').collect();
+		let (prepend, src) = self.parse_loc(ops[3]);
+		format!("{}\tmovq {},\t%rdx\n{}\tmovq %rdx,\t(%rbx)", prepend, src, self.element_address(ops[1], ops[2]))
+	}
+
+	// leaves the address of the slot in %rbx; the array points at its slot count,
+	// with the data pointer two words in. The index is compared unsigned, so
+	// a negative one fails the check too.
+	fn element_address(&self, array: &str, index: &str) -> String {
+		let (prepend1, array) = self.parse_loc(array);
+		let (prepend2, index) = self.parse_loc(index);
+		let check = match self.1 {
+			true => format!("\tcmpq (%rax),\t%rbx\n\tjae\t\t{ARRAY_INDEX_ERROR}\n"),
+			false => "".to_string()
+		};
+		format!(
+			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n{}\tshlq $3,\t%rbx\n\taddq 16(%rax),\t%rbx\n",
+			prepend1, prepend2, array, index, check
 		)
 	}
 
 	fn translate_multdiv(&self, ops: Vec<&str>) -> String {
 		let quad = get_operation_quad(ops[3]);
+		// the remainder is left in %rdx
+		let remainder = if ops[3] == "MOD64" { "\tmovq %rdx,\t%rax\n" } else { "" };
 		let (prepend1, src1) = self.parse_loc(ops[2].trim());
 		let (prepend2, src2) = self.parse_loc(ops[4].trim());
 		let (prepend3, dest) = self.parse_loc(ops[0].trim());
 		return format!(
-			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n\t{}\t\t%rbx\n{}\tmovq %rax,\t{}", 
+			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n{}\t{}\t\t%rbx\n{}{}{}\tmovq %rax,\t{}", 
+			prepend1, prepend2, src1, src2, sign_extend(ops[3]), quad, self.overflow_check(ops[3]), remainder, prepend3, dest
+		)
+	}
+
+	// the count has to be in %cl, and %rcx may hold an allocated variable, so it
+	// is saved around the shift
+	fn translate_shift(&self, ops: Vec<&str>) -> String {
+		let quad = if ops[3] == "SHL64" { "salq" } else { "sarq" };
+		let (prepend1, src1) = self.parse_loc(ops[2].trim());
+		let (prepend2, src2) = self.parse_loc(ops[4].trim());
+		let (prepend3, dest) = self.parse_loc(ops[0].trim());
+		return format!(
+			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n\tpushq %rcx\n\tmovq %rbx,\t%rcx\n\t{} %cl,\t%rax\n\tpopq %rcx\n{}\tmovq %rax,\t{}", 
 			prepend1, prepend2, src1, src2, quad, prepend3, dest
 		)
 	}
@@ -316,18 +406,77 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 		let (prepend2, src2) = self.parse_loc(ops[4]);
 		let (prepend3, dest) = self.parse_loc(ops[0].trim());
 		return format!(
-			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n\tset{}\t\t%al\n{}\tmovb %al,\t{}", 
+			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n\tcmpq %rbx,\t%rax\n\tset{}\t\t%al\n\tmovzbq %al,\t%rax\n{}\tmovq %rax,\t{}", 
 			prepend1, prepend2, src1, src2, set_suffix, prepend3, dest
 		)
 	}
 
+	// the operands travel as bits through %rax and %rbx, the math itself is done in SSE
+	fn translate_float_op(&self, ops: Vec<&str>) -> String {
+		let quad = match ops[3] {
+			"SUBF64" => "subsd",
+			"MULTF64" => "mulsd",
+			"DIVF64" => "divsd",
+			_ => "addsd"
+		};
+		let (prepend1, src1) = self.parse_loc(ops[2]);
+		let (prepend2, src2) = self.parse_loc(ops[4]);
+		let (prepend3, dest) = self.parse_loc(ops[0].trim());
+		return format!(
+			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n\tmovq %rax,\t%xmm0\n\tmovq %rbx,\t%xmm1\n\t{} %xmm1,\t%xmm0\n\tmovq %xmm0,\t%rax\n{}\tmovq %rax,\t{}", 
+			prepend1, prepend2, src1, src2, quad, prepend3, dest
+		)
+	}
+
+	// ucomisd flags an unordered (NaN) compare as below and equal, so < and <= swap
+	// their operands to use the above conditions, and == and != check the parity flag
+	fn translate_float_compare(&self, ops: Vec<&str>) -> String {
+		let (compare, set) = match ops[3] {
+			"EQF64" => ("%xmm1,\t%xmm0", "sete\t\t%al\n\tsetnp\t\t%bl\n\tandb %bl,\t%al"),
+			"NEQF64" => ("%xmm1,\t%xmm0", "setne\t\t%al\n\tsetp\t\t%bl\n\torb %bl,\t%al"),
+			"LTF64" => ("%xmm0,\t%xmm1", "seta\t\t%al"),
+			"LTEF64" => ("%xmm0,\t%xmm1", "setae\t\t%al"),
+			"GTEF64" => ("%xmm1,\t%xmm0", "setae\t\t%al"),
+			_ => ("%xmm1,\t%xmm0", "seta\t\t%al"),
+		};
+		let (prepend1, src1) = self.parse_loc(ops[2]);
+		let (prepend2, src2) = self.parse_loc(ops[4]);
+		let (prepend3, dest) = self.parse_loc(ops[0].trim());
+		return format!(
+			"{}{}\tmovq {},\t%rax\n\tmovq {},\t%rbx\n\tmovq %rax,\t%xmm0\n\tmovq %rbx,\t%xmm1\n\tucomisd {}\n\t{}\n\tmovzbq %al,\t%rax\n{}\tmovq %rax,\t{}", 
+			prepend1, prepend2, src1, src2, compare, set, prepend3, dest
+		)
+	}
+
+	// the runtime does the work on the characters, %rsi is only clobbered once both are loaded
+	fn translate_string_op(&self, ops: Vec<&str>) -> String {
+		let (runtime, negate) = match ops[3] {
+			"STRNEQ64" => ("stringEquals", "\txorq $1,\t%rax\n"),
+			"CAT64" => ("stringConcat", ""),
+			_ => ("stringEquals", "")
+		};
+		let (prepend1, src1) = self.parse_loc(ops[2]);
+		let (prepend2, src2) = self.parse_loc(ops[4]);
+		let (prepend3, dest) = self.parse_loc(ops[0].trim());
+		return format!(
+			"{}{}\tmovq {},\t%rdi\n\tmovq {},\t%rsi\n\tcallq {}\n{}{}\tmovq %rax,\t{}", 
+			prepend1, prepend2, src1, src2, runtime, negate, prepend3, dest
+		)
+	}
+
 	fn translate_write(&self, statement: &str) -> String {
 		let statement : Vec<&str> = statement.trim().split(" ").collect();
-		let (prepend, loc) = self.parse_loc(statement[1]);
-		let print_call = match self.get_type(statement[1]) {
+		// WRITE drops the brackets around variables
+		let bracketed = format!("[{}]", statement[1]);
+		let label = if self.0.contains_key(&bracketed) { bracketed.as_str() } else { statement[1] };
+		let (prepend, loc) = self.parse_loc(label);
+		let print_call = match self.get_type(label) {
 			Variable3ACType::Bool => "printBool",
 			Variable3ACType::Int => "printInt",
 			Variable3ACType::String => "printString",
+			Variable3ACType::Char => "printChar",
+			// a double is passed in %xmm0
+			Variable3ACType::Float => return format!("{}\tmovq {},\t%rdi\n\tmovq %rdi,\t%xmm0\n\tcallq printFloat", prepend, loc),
 		};
 		format!("{}\tmovq {},\t%rdi\n\tcallq {}", prepend, loc, print_call)
 	}
@@ -339,19 +488,61 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 			Variable3ACType::Bool => "getBool",
 			Variable3ACType::Int => "getInt",
 			Variable3ACType::String => "getString",
+			Variable3ACType::Char => "getChar",
+			Variable3ACType::Float => "getFloat",
 		};
-		format!("{}\tcallq {}\n\tmovq %rax,\t{}\n\t", prepend, loc, print_call)
+		if print_call == "getFloat" {
+			return format!("{}\tcallq {}\n{}\n\tmovq %rax,\t{}", prepend, print_call, FLOAT_RETURN, loc)
+		}
+		format!("{}\tcallq {}\n\tmovq %rax,\t{}", prepend, print_call, loc)
 	}
 
 	fn translate_if(&self, statement: &str) -> String {
 		let statement : Vec<&str> = statement.trim().split(" ").collect();
 		let (prepend, predicate) = self.parse_loc(statement[1]);
 		let dest = statement[3];		
-		format!("{}\tmovb {},\t%al\n\tcmpb $0,\t%al\n\tje\t\t{}", prepend, predicate, dest)
+		format!("{}\tmovq {},\t%rax\n\tcmpq $0,\t%rax\n\tje\t\t{}", prepend, predicate, dest)
+	}
+
+	fn translate_call(&self, statement: &str) -> String {
+		let statement : Vec<&str> = statement.trim().split(" ").collect();
+		match statement[0] {
+			"call" => match builtin(statement[1]) {
+				Some(builtin) if builtin.ret == PrimType::Float => format!("\tcallq {}\n{}", builtin.runtime, FLOAT_RETURN),
+				Some(builtin) => format!("\tcallq {}", builtin.runtime),
+				None => format!("\tcallq {}", statement[1])
+			},
+			"setret" => {
+				let (prepend, src) = self.parse_loc(statement[1]);
+				format!("{}\tmovq {},\t%rax", prepend, src)
+			},
+			"getret" => {
+				let (_, dest) = self.get_loc(statement[1]);
+				format!("\tmovq %rax,\t{}", dest)
+			},
+			"setarg" => {
+				// string labels come without brackets
+				let bracketed = format!("[{}]", statement[2]);
+				let label = if self.0.contains_key(&bracketed) { bracketed.as_str() } else { statement[2] };
+				let (prepend, src) = self.parse_loc(label);
+				// pushq only takes a 32 bit immediate
+				match src.strip_prefix('$').and_then(|x| x.parse::<i64>().ok()) {
+					Some(x) if i32::try_from(x).is_err() => format!("\tmovq {},\t%rax\n\tpushq %rax", src),
+					_ => format!("{}\tpushq {}", prepend, src)
+				}
+			},
+			"getarg" => {
+				// the caller pushes the first argument last, right above the return address
+				let offset = (statement[1].parse::<usize>().unwrap_or(1) - 1) * 8;
+				let (_, dest) = self.get_loc(statement[2]);
+				format!("\tmovq {}(%rbp),\t%rax\n\tmovq %rax,\t{}", offset, dest)
+			},
+			_ => "".to_string()
+		}
 	}
 
 	fn clear_fn(&mut self) {
-		self.0.retain(|_, sym| sym.global == false);
+		self.0.retain(|_, sym| sym.global);
 	}
 
 	fn get_loc(&self, label: &str) -> (&str, &str) {
@@ -359,22 +550,26 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 			Some(x) => ("", x.location.as_str()),
 			None => {
 				match label {
-					"24Kmagic" => ("\n\tcallq magic\n", "%rdi"),
+					"24Kmagic" => ("\tcallq magic\n\tmovq %rax,\t%rdi\n", "%rdi"),
 					"true" => ("", "$1"),
 					"false" => ("", "$0"),
-					_ => ("", "")
+					// string labels come without brackets
+					_ => match self.0.get(&format!("[{label}]")) {
+						Some(x) if x.sym_type == Variable3ACType::String => ("", x.location.as_str()),
+						_ => ("", "")
+					}
 				}
 			}
 		}
 	}
 	
 	fn parse_loc(&self, label: &str) -> (&str, String) {
-		match label.parse::<i32>() {
-			Ok(_) => {
-				let label = format!("${label}").to_owned();
+		match immediate(label) {
+			Some(x) => {
+				let label = format!("${x}").to_owned();
 				("", label)
 			},
-			Err(_) => {
+			None => {
 				let (a, b) = self.get_loc(label);
 				(a, b.to_string())
 			}
@@ -383,7 +578,9 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 	fn get_type(&self, label: &str) -> Variable3ACType {
 		match label {
 			"true" | "false" | "24Kmagic" => Variable3ACType::Bool,
-			_ => match label.parse::<i32>() {
+			_ if float_bits(label).is_some() => Variable3ACType::Float,
+			_ if parse_char(label).is_some() => Variable3ACType::Char,
+			_ => match label.parse::<i64>() {
 				Ok(_) => Variable3ACType::Int,
 				Err(_) => match self.0.get(label) {
 					Some(x) => x.sym_type,
@@ -394,24 +591,136 @@ impl FnSymbolTable for FnSymbolTableWrapper {
 	}
 }
 
-fn function_prologue(num_locs: usize) -> String {
-	format!("\tpushq %rbp\n\tmovq %rsp,\t%rbp\n\taddq $16,\t%rbp\n\tsubq ${},\t%rsp", num_locs*8)
+const ARRAY_INDEX_ERROR: &str = "array_index_error";
+
+const INTEGER_OVERFLOW_ERROR: &str = "integer_overflow_error";
+
+const ARGUMENT_REGS: [&str; 3] = ["%rdi", "%rsi", "%rdx"];
+
+const FLOAT_ARGUMENT_REGS: [&str; 2] = ["%xmm0", "%xmm1"];
+
+// float literals are moved around as the bits of the double
+fn float_bits(label: &str) -> Option<i64> {
+	match is_float_literal(label) {
+		true => label.parse::<f64>().ok().map(|x| x.to_bits() as i64),
+		false => None
+	}
+}
+
+// ints, and floats and chars as their bits and code
+fn immediate(label: &str) -> Option<i64> {
+	label.parse::<i64>().ok()
+		.or_else(|| float_bits(label))
+		.or_else(|| parse_char(label).map(|x| x as i64))
+}
+
+// the literal's escapes decoded to UTF-8, then every byte the assembler might
+// read differently written in octal
+fn asciz(literal: &str) -> String {
+	let body = unescape(&literal[1..literal.len() - 1]).unwrap_or_default();
+	let bytes: String = body.bytes().map(|b| match b {
+		b' ' | b'!' | b'#'..=b'[' | b']'..=b'~' => (b as char).to_string(),
+		_ => format!("\\{:03o}", b)
+	}).collect();
+	format!("\"{bytes}\"")
+}
+
+// the runtime hands a double back in %xmm0, the rest of the code expects it in %rax
+const FLOAT_RETURN: &str = "\tmovq %xmm0,\t%rax";
+
+fn function_prologue(alloc: &Allocation) -> String {
+	let mut output = format!("\tpushq %rbp\n\tmovq %rsp,\t%rbp\n\taddq $16,\t%rbp\n\tsubq ${},\t%rsp", alloc.frame_size);
+	for (reg, slot) in alloc.callee_saved.iter() {
+		output.push_str(&format!("\n\tmovq {},\t{}", reg, slot));
+	}
+	output
+}
+
+fn function_epilogue(alloc: &Allocation) -> String {
+	let mut output = "".to_string();
+	for (reg, slot) in alloc.callee_saved.iter() {
+		output.push_str(&format!("\tmovq {},\t{}\n", slot, reg));
+	}
+	output.push_str(&format!("\taddq ${},\t%rsp\n\tpopq %rbp\n\tretq", alloc.frame_size));
+	output
+}
+
+// caller-saved registers holding live values are pushed around the call, then the
+// arguments last to first, padded so %rsp stays 16-byte aligned at the callq.
+// The runtime takes its arguments in registers instead, so they are popped
+// straight back off into them, after every argument has been read. As System V
+// has it, ints and floats fill their own registers in order.
+fn save_around_call(regs: &[&str], args: &[String], stmt: String, in_registers: Option<&Builtin>) -> String {
+	let mut output : Vec<String> = Vec::new();
+	let on_stack = if in_registers.is_some() { 0 } else { args.len() };
+	let padded = (regs.len() + on_stack) % 2 == 1;
+	if padded {
+		output.push("\tsubq $8,\t%rsp".to_string());
+	}
+	for reg in regs.iter() {
+		output.push(format!("\tpushq {}", reg));
+	}
+	for arg in args.iter().rev() {
+		output.push(arg.clone());
+	}
+	if let Some(builtin) = in_registers {
+		let mut int_regs = ARGUMENT_REGS.iter();
+		let mut float_regs = FLOAT_ARGUMENT_REGS.iter();
+		for (_, arg_type) in builtin.args.iter().take(args.len()) {
+			let arg = match arg_type {
+				PrimType::Float => float_regs.next().map(|reg| format!("\tpopq %rax\n\tmovq %rax,\t{}", reg)),
+				_ => int_regs.next().map(|reg| format!("\tpopq {}", reg))
+			};
+			output.extend(arg);
+		}
+	}
+	output.push(stmt);
+	if on_stack > 0 {
+		output.push(format!("\taddq ${},\t%rsp", on_stack * 8));
+	}
+	for reg in regs.iter().rev() {
+		output.push(format!("\tpopq {}", reg));
+	}
+	if padded {
+		output.push("\taddq $8,\t%rsp".to_string());
+	}
+	output.join("\n")
+}
+
+impl FnSymbolTableWrapper {
+	// the overflow flag is still set from the instruction right before
+	fn overflow_check(&self, operation: &str) -> String {
+		match (self.2, operation) {
+			(true, "ADD64" | "SUB64" | "MULT64" | "NEG64") => format!("\tjo\t\t{INTEGER_OVERFLOW_ERROR}\n"),
+			_ => "".to_string()
+		}
+	}
 }
 
-fn function_epilogue(num_locs: usize) -> String {
-	format!("\taddq ${},\t%rsp\n\tpopq %rbp\n\tretq", num_locs*8)
+// idivq divides %rdx:%rax, so %rax has to be sign extended first
+fn sign_extend(operation: &str) -> &str {
+	match operation {
+		"DIV64" | "MOD64" => "\tcqto\n",
+		_ => ""
+	}
 }
 
 fn get_operation_quad(operation: &str) -> &str {
 	match operation {
-		"NEG64" => "notq",
+		"NEG64" => "negq ",
 		"ADD64" => "addq",
 		"SUB64" => "subq",
 		"MULT64" => "imulq",
-		"DIV64" => "idivq",
+		"DIV64" | "MOD64" => "idivq",
+		"BITAND64" => "andq",
+		"BITOR64" => "orq",
+		"XOR64" => "xorq",
+		"BITNOT64" => "notq ",
 		"AND64" => "andq",
 		"OR64" => "orq",
-		"NOT64" => "notq",
+		"NOT64" => "xorq $1,\t",
+		// flipping the sign bit negates a double
+		"NEGF64" => "btcq $63,\t",
 		&_ => ""
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,370 @@
+use std::fmt::{Display, Formatter, Error};
+
+#[cfg(test)]
+mod tests;
+
+/// One line of emitted assembly. Everything outside `.text` is kept verbatim.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    Label(String),
+    Op { name: String, args: Vec<String> },
+    Other(String),
+}
+
+/// A rewrite rule inspects the window starting at `i` and, when it fires, returns
+/// how many instructions it consumed and what replaces them.
+type Rule = fn(&[Instr], usize) -> Option<(usize, Vec<Instr>)>;
+
+/// The catalogue of rewrites, tried in order at every position until none fire.
+const RULES: [(&str, Rule); 9] = [
+    // movq X, X
+    ("self-move", remove_self_move),
+    // addq $0, %rsp / subq $0, %rsp
+    ("zero-stack-adjust", remove_zero_stack_adjust),
+    // movq A, B; movq B, C  =>  movq A, B; movq A, C  (dropped when C is A)
+    ("store-reload", forward_stored_value),
+    // movq X, %r; movq %r, Y  =>  movq X, Y  when %r is dead afterwards
+    ("move-chain", collapse_move_chain),
+    // movq X, %r  when %r is dead afterwards
+    ("dead-move", remove_dead_move),
+    // movq X, %r; addq %r, Y  =>  addq X, Y  when %r is dead afterwards
+    ("fold-operand", fold_moved_operand),
+    // jmp L; L:
+    ("jump-to-next", remove_jump_to_next),
+    // nop
+    ("nop", remove_nop),
+    // lbl_N: that nothing jumps to
+    ("unused-label", remove_unused_label),
+];
+
+pub fn peephole(asm: String) -> String {
+    let mut instrs = parse(&asm);
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let mut i = 0;
+        while i < instrs.len() {
+            for (_, rule) in RULES.iter() {
+                if let Some((consumed, replacement)) = rule(&instrs, i) {
+                    instrs.splice(i..i + consumed, replacement);
+                    changed = true;
+                    break;
+                }
+            }
+            i += 1;
+        }
+    }
+
+    instrs_to_string(&instrs)
+}
+
+pub fn parse(asm: &str) -> Vec<Instr> {
+    let mut instrs : Vec<Instr> = Vec::new();
+    let mut in_text = false;
+
+    for line in asm.split('\n') {
+        if !in_text {
+            in_text = line.trim() == ".text";
+            instrs.push(Instr::Other(line.to_string()));
+            continue;
+        }
+
+        let mut code = line;
+        if !line.starts_with('\t') {
+            if let Some((lbl, rest)) = line.split_once(':') {
+                instrs.push(Instr::Label(lbl.to_string()));
+                code = rest;
+            }
+        }
+
+        let code = code.trim();
+        if code.is_empty() {
+            if line.trim().is_empty() {
+                instrs.push(Instr::Other(line.to_string()));
+            }
+            continue;
+        }
+
+        let (name, args) = match code.split_once(char::is_whitespace) {
+            Some((name, args)) => (name, args.split(',').map(|arg| arg.trim().to_string()).collect()),
+            None => (code, Vec::new()),
+        };
+        instrs.push(Instr::Op { name: name.to_string(), args });
+    }
+    instrs
+}
+
+pub fn instrs_to_string(instrs: &[Instr]) -> String {
+    let mut output : Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < instrs.len() {
+        // a label shares its line with the instruction right after it
+        match (&instrs[i], instrs.get(i + 1)) {
+            (Instr::Label(lbl), Some(op @ Instr::Op { .. })) => {
+                output.push(format!("{}:{}", lbl, op));
+                i += 2;
+            },
+            (instr, _) => {
+                output.push(instr.to_string());
+                i += 1;
+            }
+        }
+    }
+    output.join("\n")
+}
+
+impl Display for Instr {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Instr::Label(lbl) => write!(fmt, "{}:", lbl),
+            Instr::Op { name, args } if args.is_empty() => write!(fmt, "\t{}", name),
+            Instr::Op { name, args } => write!(fmt, "\t{} {}", name, args.join(",\t")),
+            Instr::Other(line) => write!(fmt, "{}", line),
+        }
+    }
+}
+
+fn op(instrs: &[Instr], i: usize) -> Option<(&str, &[String])> {
+    match instrs.get(i)? {
+        Instr::Op { name, args } => Some((name.as_str(), args.as_slice())),
+        _ => None
+    }
+}
+
+fn movq(src: &str, dest: &str) -> Instr {
+    Instr::Op { name: "movq".to_string(), args: vec![src.to_string(), dest.to_string()] }
+}
+
+fn is_mem(arg: &str) -> bool {
+    arg.contains('(')
+}
+
+fn is_reg(arg: &str) -> bool {
+    arg.starts_with('%')
+}
+
+// only movq into a register takes an immediate that needs all 64 bits
+fn is_wide_imm(arg: &str) -> bool {
+    arg.strip_prefix('$').and_then(|x| x.parse::<i64>().ok()).is_some_and(|x| i32::try_from(x).is_err())
+}
+
+// whether one movq can do it, the SSE registers take no immediates at all
+fn can_move(src: &str, dest: &str) -> bool {
+    let too_wide = is_wide_imm(src) && !is_reg(dest);
+    let into_sse = src.starts_with('$') && dest.starts_with("%xmm");
+    !(is_mem(src) && is_mem(dest) || too_wide || into_sse)
+}
+
+fn remove_self_move(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    match op(instrs, i)? {
+        ("movq", [src, dest]) if src == dest => Some((1, Vec::new())),
+        _ => None
+    }
+}
+
+fn remove_zero_stack_adjust(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    match op(instrs, i)? {
+        ("addq" | "subq", [amount, dest]) if amount == "$0" && dest == "%rsp" => Some((1, Vec::new())),
+        _ => None
+    }
+}
+
+fn forward_stored_value(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    let (a, b) = match op(instrs, i)? {
+        ("movq", [a, b]) => (a, b),
+        _ => return None
+    };
+    let c = match op(instrs, i + 1)? {
+        ("movq", [src, c]) if src == b => c,
+        _ => return None
+    };
+
+    // A must still hold the value after being copied to B
+    if is_mem(a) && a.contains(b.as_str()) {
+        return None;
+    }
+    if a == c {
+        return Some((2, vec![instrs[i].clone()]));
+    }
+    if !can_move(a, c) || a == b {
+        return None;
+    }
+    Some((2, vec![instrs[i].clone(), movq(a, c)]))
+}
+
+fn collapse_move_chain(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    let (x, r) = match op(instrs, i)? {
+        ("movq", [x, r]) if is_reg(r) => (x, r),
+        _ => return None
+    };
+    let y = match op(instrs, i + 1)? {
+        ("movq", [src, y]) if src == r && y != r => y,
+        _ => return None
+    };
+
+    if !can_move(x, y) || mentions(y, r) || !reg_dead_after(instrs, i + 2, r) {
+        return None;
+    }
+    if x == y {
+        return Some((2, Vec::new()));
+    }
+    Some((2, vec![movq(x, y)]))
+}
+
+fn remove_dead_move(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    match op(instrs, i)? {
+        ("movq", [_, r]) if is_reg(r) && r != "%rsp" && r != "%rbp" && reg_dead_after(instrs, i + 1, r) => {
+            Some((1, Vec::new()))
+        },
+        _ => None
+    }
+}
+
+fn fold_moved_operand(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    let (x, r) = match op(instrs, i)? {
+        ("movq", [x, r]) if is_reg(r) && !is_wide_imm(x) => (x, r),
+        _ => return None
+    };
+    let (name, args) = op(instrs, i + 1)?;
+
+    let folded = match (name, args) {
+        ("addq" | "subq" | "andq" | "orq" | "xorq" | "cmpq", [src, dest]) if src == r && !mentions(dest, r) => {
+            if is_mem(x) && is_mem(dest) {
+                return None;
+            }
+            vec![x.clone(), dest.clone()]
+        },
+        // the one operand forms can't take an immediate
+        ("imulq" | "idivq", [src]) if src == r && !x.starts_with('$') => vec![x.clone()],
+        _ => return None
+    };
+
+    if !reg_dead_after(instrs, i + 2, r) {
+        return None;
+    }
+    Some((2, vec![Instr::Op { name: name.to_string(), args: folded }]))
+}
+
+fn remove_jump_to_next(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    let target = match op(instrs, i)? {
+        ("jmp", [target]) => target,
+        _ => return None
+    };
+
+    for instr in instrs[i + 1..].iter() {
+        match instr {
+            Instr::Label(lbl) if lbl == target => return Some((1, Vec::new())),
+            Instr::Label(_) => (),
+            Instr::Other(line) if line.trim().is_empty() => (),
+            Instr::Op { name, .. } if name == "nop" => (),
+            _ => return None
+        }
+    }
+    None
+}
+
+fn remove_nop(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    match op(instrs, i)? {
+        ("nop", []) => Some((1, Vec::new())),
+        _ => None
+    }
+}
+
+fn remove_unused_label(instrs: &[Instr], i: usize) -> Option<(usize, Vec<Instr>)> {
+    let lbl = match &instrs[i] {
+        Instr::Label(lbl) if lbl.starts_with("lbl_") => lbl,
+        _ => return None
+    };
+
+    let used = instrs.iter().any(|instr| match instr {
+        Instr::Op { args, .. } => args.contains(lbl),
+        _ => false
+    });
+    if used { None } else { Some((1, Vec::new())) }
+}
+
+const CALLER_SAVED: [&str; 8] = ["%rax", "%rcx", "%rdx", "%rsi", "%r8", "%r9", "%r10", "%r11"];
+// the runtime library takes at most three arguments, in these, and a double in %xmm0
+const ARGUMENT_REGS: [&str; 4] = ["%rdi", "%rsi", "%rdx", "%xmm0"];
+
+// %al and friends alias the full register
+fn full_reg(arg: &str) -> &str {
+    match arg {
+        "%al" | "%eax" => "%rax",
+        "%bl" | "%ebx" => "%rbx",
+        "%dl" | "%edx" => "%rdx",
+        "%dil" | "%edi" => "%rdi",
+        "%cl" | "%ecx" => "%rcx",
+        _ => arg
+    }
+}
+
+fn mentions(arg: &str, reg: &str) -> bool {
+    full_reg(arg) == reg || (is_mem(arg) && arg.contains(reg))
+}
+
+/// Whether `reg` is overwritten before anything reads it, starting at `start`.
+/// Anything that leaves the straight-line window counts as a read.
+fn reg_dead_after(instrs: &[Instr], start: usize, reg: &str) -> bool {
+    for instr in instrs[start..].iter() {
+        let (name, args) = match instr {
+            Instr::Op { name, args } => (name.as_str(), args.as_slice()),
+            Instr::Other(line) if line.trim().is_empty() => continue,
+            _ => return false
+        };
+
+        match (name, args) {
+            ("nop", _) => continue,
+            // callees only take arguments in registers from the runtime, and clobber the other caller-saved ones
+            ("callq", _) => {
+                if ARGUMENT_REGS.contains(&reg) {
+                    return false;
+                }
+                if CALLER_SAVED.contains(&reg) {
+                    return true;
+                }
+            },
+            (set, [dest]) if set.starts_with("set") => {
+                if full_reg(dest) == reg {
+                    return false;
+                }
+            },
+            ("movq" | "movzbq" | "popq", _) => {
+                if args.iter().take(args.len() - 1).any(|arg| mentions(arg, reg)) {
+                    return false;
+                }
+                let dest = &args[args.len() - 1];
+                if full_reg(dest) == reg {
+                    return true;
+                }
+                if mentions(dest, reg) {
+                    return false;
+                }
+            },
+            ("cqto", _) => {
+                if reg == "%rax" {
+                    return false;
+                }
+                if reg == "%rdx" {
+                    return true;
+                }
+            },
+            ("addq" | "subq" | "andq" | "orq" | "xorq" | "cmpq" | "negq" | "notq" | "pushq", _) => {
+                if args.iter().any(|arg| mentions(arg, reg)) {
+                    return false;
+                }
+            },
+            ("imulq" | "idivq", [src]) => {
+                if mentions(src, reg) || reg == "%rax" || (name == "idivq" && reg == "%rdx") {
+                    return false;
+                }
+                if reg == "%rdx" {
+                    return true;
+                }
+            },
+            _ => return false
+        }
+    }
+    false
+}
@@ -0,0 +1,115 @@
+use super::{peephole, parse, instrs_to_string};
+
+fn check(before: &str, after: &str) {
+    let wrap = |body: &str| format!("\t.globl main\n\t.text\n{}\n", body);
+    assert_eq!(peephole(wrap(before)), wrap(after));
+}
+
+#[test]
+fn round_trip() {
+    let asm = "\t.data\nstr0:\t.asciz \"hi\"\n\t.text\nmain:\tpushq %rbp\n\tmovq %rsp,\t%rbp\nlbl_0:\tleave\n";
+    assert_eq!(instrs_to_string(&parse(asm)), asm);
+}
+
+#[test]
+fn self_move() {
+    check("\tmovq %r12,\t%r12\n\tretq", "\tretq");
+}
+
+#[test]
+fn zero_stack_adjust() {
+    check("\tsubq $0,\t%rsp\n\taddq $0,\t%rsp\n\tretq", "\tretq");
+}
+
+#[test]
+fn store_reload() {
+    check(
+        "\tmovq %rax,\t-24(%rbp)\n\tmovq -24(%rbp),\t%r12\n\taddq %r12,\t%r13",
+        "\tmovq %rax,\t-24(%rbp)\n\tmovq %rax,\t%r12\n\taddq %r12,\t%r13",
+    );
+}
+
+#[test]
+fn move_chain() {
+    check(
+        "\tmovq -24(%rbp),\t%rax\n\tmovq %rax,\t%r12\n\tmovq $0,\t%rax",
+        "\tmovq -24(%rbp),\t%r12\n\tmovq $0,\t%rax",
+    );
+}
+
+#[test]
+fn move_chain_keeps_live_register() {
+    check(
+        "\tmovq $3,\t%rax\n\tmovq %rax,\t-24(%rbp)\n\tmovq %rax,\t%rdi\n\tcallq printInt",
+        "\tmovq $3,\t%rax\n\tmovq $3,\t-24(%rbp)\n\tmovq %rax,\t%rdi\n\tcallq printInt",
+    );
+}
+
+#[test]
+fn dead_move() {
+    check("\tmovq $1,\t%rax\n\tmovq $2,\t%rax\n\tmovq %rax,\t%rdi\n\tcallq printInt", "\tmovq $2,\t%rdi\n\tcallq printInt");
+}
+
+#[test]
+fn keeps_second_argument() {
+    let body = "\tmovq %rcx,\t%rsi\n\tmovq $str0,\t%rdi\n\tcallq stringEquals";
+    check(body, body);
+}
+
+#[test]
+fn fold_operand() {
+    check(
+        "\tmovq %r13,\t%rbx\n\taddq %rbx,\t%r12\n\tmovq $0,\t%rbx",
+        "\taddq %r13,\t%r12\n\tmovq $0,\t%rbx",
+    );
+}
+
+#[test]
+fn fold_operand_skips_immediate_divisor() {
+    let body = "\tmovq $2,\t%rbx\n\tidivq %rbx\n\tmovq %rax,\t%rdi\n\tcallq printInt";
+    check(body, body);
+}
+
+#[test]
+fn keeps_wide_immediates_in_registers() {
+    check(
+        "\tmovq $5000000000,\t%rax\n\tmovq %rax,\t-24(%rbp)\n\tmovq $0,\t%rax",
+        "\tmovq $5000000000,\t%rax\n\tmovq %rax,\t-24(%rbp)\n\tmovq $0,\t%rax",
+    );
+    check(
+        "\tmovq $5000000000,\t%rbx\n\taddq %rbx,\t%r12\n\tmovq $0,\t%rbx",
+        "\tmovq $5000000000,\t%rbx\n\taddq %rbx,\t%r12\n\tmovq $0,\t%rbx",
+    );
+}
+
+#[test]
+fn keeps_immediates_out_of_sse_registers() {
+    check(
+        "\tmovq $4609434218613702656,\t%rdi\n\tmovq %rdi,\t%xmm0\n\tcallq printFloat",
+        "\tmovq $4609434218613702656,\t%rdi\n\tmovq %rdi,\t%xmm0\n\tcallq printFloat",
+    );
+    check(
+        "\tmovq -24(%rbp),\t%rax\n\tmovq %rax,\t%xmm0\n\tcallq printFloat",
+        "\tmovq -24(%rbp),\t%xmm0\n\tcallq printFloat",
+    );
+}
+
+#[test]
+fn jump_to_next() {
+    check("\tjmp lbl_1\n\nlbl_1:\tretq", "\n\tretq");
+}
+
+#[test]
+fn keeps_jump_elsewhere() {
+    check("\tjmp lbl_1\n\tretq\nlbl_1:\tretq", "\tjmp lbl_1\n\tretq\nlbl_1:\tretq");
+}
+
+#[test]
+fn unused_label_and_nop() {
+    check("lbl_4:\tnop\n\tretq", "\tretq");
+}
+
+#[test]
+fn keeps_function_labels() {
+    check("main:\tretq", "main:\tretq");
+}
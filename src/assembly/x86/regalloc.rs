@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use crate::assembly::ir::IRFunction;
+use crate::assembly::ir::liveness::Liveness;
+
+#[cfg(test)]
+mod tests;
+
+// %rax, %rbx, %rdx and %rdi stay free as scratch for the statement templates
+const CALLEE_SAVED: [&str; 4] = ["%r12", "%r13", "%r14", "%r15"];
+const CALLER_SAVED: [&str; 6] = ["%rcx", "%rsi", "%r8", "%r9", "%r10", "%r11"];
+
+/// Where every local and temporary of one function lives, plus the bookkeeping
+/// the prologue, epilogue and call sites need to keep registers intact.
+pub struct Allocation {
+    pub locations: HashMap<String, String>,
+    pub frame_size: usize,
+    pub callee_saved: Vec<(&'static str, String)>,
+    pub call_saves: HashMap<usize, Vec<&'static str>>,
+}
+
+struct Interval {
+    var: String,
+    start: usize,
+    end: usize,
+    crosses_call: bool,
+    // read and written by a line calling magic, so it can't be saved around it
+    callee_only: bool,
+}
+
+pub fn allocate(func: &IRFunction) -> Allocation {
+    let liveness = Liveness::analyze(func);
+    let mut intervals = build_intervals(func, &liveness);
+    intervals.sort_by(|a, b| (a.start, a.end, &a.var).cmp(&(b.start, b.end, &b.var)));
+
+    let mut assigned: HashMap<String, &'static str> = HashMap::new();
+    let mut spilled: Vec<String> = Vec::new();
+    let mut active: Vec<usize> = Vec::new(); // indices into intervals, sorted by end
+    let mut free: Vec<&'static str> = CALLER_SAVED.iter().chain(CALLEE_SAVED.iter()).copied().collect();
+
+    for i in 0..intervals.len() {
+        // expire intervals that ended before this one starts
+        active.retain(|&a| {
+            if intervals[a].end <= intervals[i].start {
+                free.push(assigned[&intervals[a].var]);
+                false
+            } else {
+                true
+            }
+        });
+
+        match pick_register(&free, &intervals[i]) {
+            Some(reg) => {
+                free.retain(|r| *r != reg);
+                assigned.insert(intervals[i].var.clone(), reg);
+                insert_active(&mut active, &intervals, i);
+            },
+            None => {
+                // spill whichever of the active intervals or this one ends last
+                let last = *active.last().unwrap();
+                let usable = !intervals[i].callee_only || CALLEE_SAVED.contains(&assigned[&intervals[last].var]);
+                if intervals[last].end > intervals[i].end && usable {
+                    let reg = assigned.remove(&intervals[last].var).unwrap();
+                    spilled.push(intervals[last].var.clone());
+                    assigned.insert(intervals[i].var.clone(), reg);
+                    active.pop();
+                    insert_active(&mut active, &intervals, i);
+                } else {
+                    spilled.push(intervals[i].var.clone());
+                }
+            }
+        }
+    }
+
+    let mut locations: HashMap<String, String> = HashMap::new();
+    for (var, reg) in assigned.iter() {
+        locations.insert(var.clone(), reg.to_string());
+    }
+    for (slot, var) in spilled.iter().enumerate() {
+        locations.insert(var.clone(), stack_slot(slot));
+    }
+
+    let mut callee_saved = Vec::new();
+    for reg in CALLEE_SAVED.iter() {
+        if assigned.values().any(|r| r == reg) {
+            callee_saved.push((*reg, stack_slot(spilled.len() + callee_saved.len())));
+        }
+    }
+
+    let mut call_saves = HashMap::new();
+    for (i, line) in func.body.iter().enumerate() {
+        if !line.quad.is_call() {
+            continue;
+        }
+        let mut saves = liveness.live_across(i, func).iter()
+            .filter_map(|var| assigned.get(var).copied())
+            .filter(|reg| CALLER_SAVED.contains(reg))
+            .collect::<Vec<&'static str>>();
+        saves.sort();
+        if !saves.is_empty() {
+            call_saves.insert(i, saves);
+        }
+    }
+
+    let slots = spilled.len() + callee_saved.len();
+    Allocation {
+        locations,
+        frame_size: (slots * 8).div_ceil(16) * 16,
+        callee_saved,
+        call_saves,
+    }
+}
+
+fn build_intervals(func: &IRFunction, liveness: &Liveness) -> Vec<Interval> {
+    let mut ranges: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for (i, line) in func.body.iter().enumerate() {
+        let mut seen = liveness.live_in[i].iter().chain(liveness.live_out[i].iter())
+            .collect::<Vec<&String>>();
+        seen.extend(line.quad.def());
+        seen.extend(line.quad.uses());
+        for var in seen {
+            if !func.is_local(var) {
+                continue;
+            }
+            let range = ranges.entry(var.clone()).or_insert((i, i));
+            range.0 = range.0.min(i);
+            range.1 = range.1.max(i);
+        }
+    }
+
+    let calls = (0..func.body.len())
+        .filter(|i| func.body[*i].quad.is_call())
+        .collect::<Vec<usize>>();
+
+    ranges.into_iter().map(|(var, (start, end))| Interval {
+        crosses_call: calls.iter().any(|c| liveness.live_across(*c, func).contains(&var)),
+        callee_only: func.body.iter().any(|line| {
+            line.quad.calls_magic() && line.quad.def() == Some(&var) && line.quad.uses().contains(&&var)
+        }),
+        var,
+        start,
+        end,
+    }).collect()
+}
+
+// values living across a call prefer registers the callee has to preserve
+fn pick_register(free: &[&'static str], interval: &Interval) -> Option<&'static str> {
+    let (first, second) = if interval.callee_only {
+        (&CALLEE_SAVED[..], &[][..])
+    } else if interval.crosses_call {
+        (&CALLEE_SAVED[..], &CALLER_SAVED[..])
+    } else {
+        (&CALLER_SAVED[..], &CALLEE_SAVED[..])
+    };
+    first.iter().chain(second.iter()).find(|reg| free.contains(reg)).copied()
+}
+
+fn insert_active(active: &mut Vec<usize>, intervals: &[Interval], i: usize) {
+    let pos = active.iter().position(|&a| intervals[a].end > intervals[i].end).unwrap_or(active.len());
+    active.insert(pos, i);
+}
+
+fn stack_slot(slot: usize) -> String {
+    format!("-{}(%rbp)", slot * 8 + 24)
+}
@@ -0,0 +1,154 @@
+use std::collections::HashSet;
+
+use super::{allocate, build_intervals, CALLEE_SAVED, CALLER_SAVED};
+use crate::assembly::ir::IRFunction;
+use crate::assembly::ir::liveness::Liveness;
+
+// a = 1; b = a + 2; g(); c = a + b; give c;
+const SMALL : &str = "f LOCALS]
+a-int
+b-int
+c-int
+[END f LOCALS]
+
+f:\tenter f
+\t[a] := 1
+\t[b] := [a] ADD64 2
+\tcall g
+\t[c] := [a] ADD64 [b]
+\tWRITE [c]
+lbl_0:\tleave f
+";
+
+// n ints all defined before any is printed, with an optional call in between
+fn all_live(n: usize, call: bool) -> IRFunction {
+    let vars = (0..n).map(|i| format!("v{i}")).collect::<Vec<String>>();
+    let locals = vars.iter().map(|v| format!("{v}-int\n")).collect::<String>();
+    let defs = vars.iter().enumerate().map(|(i, v)| format!("\t[{v}] := {i}\n")).collect::<String>();
+    let uses = vars.iter().map(|v| format!("\tWRITE [{v}]\n")).collect::<String>();
+    let call = if call { "\tcall g\n" } else { "" };
+    IRFunction::parse(&format!("f LOCALS]\n{locals}[END f LOCALS]\n\nf:\tenter f\n{defs}{call}{uses}lbl_0:\tleave f\n"))
+}
+
+// a dies at the magic quad, but magic is called before it is read
+const MAGIC : &str = "f LOCALS]
+a-bool
+b-bool
+[END f LOCALS]
+
+f:\tenter f
+\t[a] := false
+\t[b] := 24Kmagic AND64 [a]
+\tWRITE [b]
+lbl_0:\tleave f
+";
+
+// n ints live through a line calling magic that also reads and writes a
+fn magic_updates(n: usize) -> IRFunction {
+    let vars = (0..n).map(|i| format!("v{i}")).collect::<Vec<String>>();
+    let locals = vars.iter().map(|v| format!("{v}-int\n")).collect::<String>();
+    let defs = vars.iter().enumerate().map(|(i, v)| format!("\t[{v}] := {i}\n")).collect::<String>();
+    let uses = vars.iter().map(|v| format!("\tWRITE [{v}]\n")).collect::<String>();
+    IRFunction::parse(&format!("f LOCALS]\n{locals}a-bool\n[END f LOCALS]\n\nf:\tenter f\n{defs}\t[a] := true\n\t[a] := [a] OR64 24Kmagic\n\tWRITE [a]\n{uses}lbl_0:\tleave f\n"))
+}
+
+fn is_stack(location: &str) -> bool {
+    location.ends_with("(%rbp)")
+}
+
+#[test]
+fn live_intervals() {
+    let func = IRFunction::parse(SMALL);
+    let mut intervals = build_intervals(&func, &Liveness::analyze(&func)).into_iter()
+        .map(|x| (x.var, x.start, x.end, x.crosses_call))
+        .collect::<Vec<(String, usize, usize, bool)>>();
+    intervals.sort();
+
+    // a and b are still needed after the call on line 3, c only lives after it
+    assert_eq!(intervals, vec![
+        ("a".to_string(), 1, 4, true),
+        ("b".to_string(), 2, 4, true),
+        ("c".to_string(), 4, 5, false),
+    ]);
+}
+
+#[test]
+fn values_across_calls_take_callee_saved_registers() {
+    let alloc = allocate(&IRFunction::parse(SMALL));
+    assert!(CALLEE_SAVED.contains(&alloc.locations["a"].as_str()));
+    assert!(CALLEE_SAVED.contains(&alloc.locations["b"].as_str()));
+    assert!(CALLER_SAVED.contains(&alloc.locations["c"].as_str()));
+
+    // the prologue saves the two callee-saved registers, nothing is saved at the call
+    assert_eq!(alloc.callee_saved.len(), 2);
+    assert!(alloc.call_saves.is_empty());
+    assert_eq!(alloc.frame_size, 16);
+}
+
+#[test]
+fn caller_saved_registers_are_saved_around_calls() {
+    let func = all_live(CALLEE_SAVED.len() + 2, true);
+    let alloc = allocate(&func);
+    let call = func.body.iter().position(|line| line.quad.is_call()).unwrap();
+
+    // once the callee-saved registers are used up, the rest are saved at the call
+    let saves = &alloc.call_saves[&call];
+    assert_eq!(saves.len(), 2);
+    assert!(saves.iter().all(|reg| CALLER_SAVED.contains(reg)));
+    assert_eq!(alloc.callee_saved.len(), CALLEE_SAVED.len());
+}
+
+#[test]
+fn spills_when_registers_run_out() {
+    let registers = CALLER_SAVED.len() + CALLEE_SAVED.len();
+    let func = all_live(registers + 2, false);
+    let alloc = allocate(&func);
+
+    // every value is live at once, so the two that end last go to the stack
+    let spilled = alloc.locations.iter()
+        .filter(|(_, location)| is_stack(location))
+        .map(|(var, _)| var.as_str())
+        .collect::<HashSet<&str>>();
+    let last = format!("v{}", registers + 1);
+    let second_last = format!("v{}", registers);
+    assert_eq!(spilled, HashSet::from([last.as_str(), second_last.as_str()]));
+
+    // and no two of the others share a register
+    let registers_used = alloc.locations.values()
+        .filter(|location| !is_stack(location))
+        .collect::<HashSet<&String>>();
+    assert_eq!(registers_used.len(), registers);
+
+    // two spill slots and four saved callee-saved registers, rounded up to 16 bytes
+    let slots = alloc.locations.values().chain(alloc.callee_saved.iter().map(|(_, slot)| slot))
+        .filter(|location| is_stack(location))
+        .collect::<HashSet<&String>>();
+    assert_eq!(slots.len(), 2 + CALLEE_SAVED.len());
+    assert_eq!(alloc.frame_size, 48);
+}
+
+#[test]
+fn magic_operands_survive_the_call() {
+    let func = IRFunction::parse(MAGIC);
+    let intervals = build_intervals(&func, &Liveness::analyze(&func));
+    let a = intervals.iter().find(|x| x.var == "a").unwrap();
+    assert!(a.crosses_call);
+
+    // a ends on the magic line, yet its register has to outlive the call
+    let alloc = allocate(&func);
+    let location = alloc.locations["a"].as_str();
+    let saved = alloc.call_saves.get(&2).is_some_and(|regs| regs.contains(&location));
+    assert!(CALLEE_SAVED.contains(&location) || saved);
+}
+
+#[test]
+fn magic_updates_never_take_caller_saved_registers() {
+    // a can't be saved around the line that writes it, so it takes a callee-saved register
+    let alloc = allocate(&magic_updates(0));
+    assert!(CALLEE_SAVED.contains(&alloc.locations["a"].as_str()));
+
+    // and once those are all taken, the stack
+    let alloc = allocate(&magic_updates(CALLEE_SAVED.len() + CALLER_SAVED.len()));
+    let location = alloc.locations["a"].as_str();
+    assert!(CALLEE_SAVED.contains(&location) || is_stack(location));
+}
@@ -3,11 +3,11 @@ use std::fs::File;
 use std::io::{self, Write};
 
 mod scanner;
-use scanner::{tokenizer, lexer::Lexer};
+use scanner::{tokenizer, lexing_errors, lexer::Lexer, tokens::{LexingError, TokenType}};
 
 mod parser;
 use parser::{unparse, grammar::*};
-use parser::ast::span::{line_bytes, node::SpanNode};
+use parser::ast::span::{line_lengths, node::SpanNode};
 
 mod format;
 
@@ -18,13 +18,23 @@ use analysis::_type::type_error_check;
 mod assembly;
 use assembly::three_ac::convert_3ac;
 use assembly::x86::convert_x86;
+use assembly::x86::peephole::peephole;
+pub use assembly::opt::PassManager;
+use assembly::opt::pass_list;
+pub use assembly::ir::{IRProgram, IRFunction};
+pub use assembly::ir::cfg::{Cfg, BasicBlock, Loop, program_dot};
 
-use indoc::indoc;
+use indoc::formatdoc;
+use lalrpop_util::ParseError;
 
 pub struct Config {
     input: String,
     output: Box<dyn Write>,
-    mode: ProcessMode
+    mode: ProcessMode,
+    peephole: bool,
+    bounds_checks: bool,
+    overflow_checks: bool,
+    passes: PassManager,
 }
 
 enum ProcessMode {
@@ -35,16 +45,23 @@ enum ProcessMode {
     TypeCheck,
     Generate3AC,
     X86,
+    CfgDot,
 }
 
 
 impl Config {
     pub fn build<'a>(
         mut args: impl Iterator<Item = String>,
-    ) -> Result<Config, &'static str> {
+    ) -> Result<Config, String> {
         let mut mode = None;
         let mut input = None;
         let mut output_file = None;
+        let mut peephole = true;
+        let mut bounds_checks = true;
+        let mut overflow_checks = false;
+        let mut opt_level = 0;
+        let mut pass_names : Option<String> = None;
+        let mut print_after : Vec<String> = Vec::new();
         
         args.next();
 
@@ -53,7 +70,28 @@ impl Config {
                 Some(x) => x,
                 None => break,
             };
-            if arg.starts_with("-") {
+            if arg == "--no-peephole" {
+                peephole = false;
+            } else if arg == "--no-bounds-checks" {
+                bounds_checks = false;
+            } else if arg == "--trap-overflow" {
+                overflow_checks = true;
+            } else if let Some(level) = arg.strip_prefix("-O") {
+                opt_level = match level.parse::<u8>() {
+                    Ok(x) => x,
+                    Err(_) => return Err("Optimization levels are given as -O0, -O1, ...".to_string())
+                };
+            } else if let Some(names) = arg.strip_prefix("--passes=") {
+                pass_names = Some(names.to_string());
+            } else if let Some(name) = arg.strip_prefix("--print-after=") {
+                print_after.push(name.to_string());
+            } else if let Some(emit) = arg.strip_prefix("--emit=") {
+                mode = match emit {
+                    "cfg-dot" => Some(ProcessMode::CfgDot),
+                    _ => return Err("The only supported --emit target is cfg-dot.".to_string())
+                };
+                output_file = args.next();
+            } else if arg.starts_with("-") {
                 let arg_str = arg.as_str();
                 mode = match arg_str {
                     "-t" => Some(ProcessMode::Tokenize),
@@ -63,7 +101,7 @@ impl Config {
                     "-c" => Some(ProcessMode::TypeCheck),
                     "-a" => Some(ProcessMode::Generate3AC),
                     "-o" => Some(ProcessMode::X86),
-                    _ => return Err(indoc!{"
+                    _ => return Err(formatdoc!{"
                         The only supported options right now are:
                             [<inputFile.dm> -t <outputFile> ]: Tokenizes inputFile. Outputs result into <outputFile>.
                             [<inputFile.dm> -p]: Checks if inputFile has syntactically correct Drewno Mars code.
@@ -72,10 +110,17 @@ impl Config {
                             [<inputFile.dm> -c]: Checks if the Drewno Mars code in inputFile passes Type Analysis.
                             [<inputFile.dm> -a <outputFile>]: Converts Drewno Mars code into an 3AC intermediate representation. Outputs result into <outputFile>.
                             [<inputFile.dm> -o <outputFile>]: Converts Drewno Mars code into x86 assembly. Outputs result into <outputFile>.
+                            [<inputFile.dm> --emit=cfg-dot <outputFile>]: Draws the control-flow graph of every function as Graphviz dot. Outputs result into <outputFile>.
+                            [--no-peephole]: Skips the peephole pass over the generated x86 assembly.
+                            [--no-bounds-checks]: Leaves out the check that every array index is in bounds.
+                            [--trap-overflow]: Stops the program with an error when int arithmetic overflows, instead of wrapping around.
+                            [-O<n>]: Optimizes the 3AC before it is printed or translated. -O0 (the default) leaves it as is, -O1 turns self tail calls into jumps, folds and propagates constants, reuses common subexpressions, propagates copies, hoists loop-invariant code and removes dead code, -O2 also inlines small functions and runs them over SSA form first.
+                            [--passes=<pass>,<pass>,...]: Runs exactly these passes, in this order, instead of an -O preset. Passes: {passes}.
+                            [--print-after=<pass>]: Prints the 3AC to stderr every time <pass> finishes.
                         Try again with a supported option.
 
                         Note: all <outputFile> arguments are optional. If no <outputFile> is given, output will be printed to console.
-                    "})
+                    ", passes = pass_list()})
                 };
 
                 output_file = match arg_str {
@@ -91,20 +136,36 @@ impl Config {
             } else {
                 input = match input {
                     None => Some(arg),
-                    _ => return Err("Only one input file allowed!")
+                    _ => return Err("Only one input file allowed!".to_string())
                 };
             }
         }
 
-        if mode.is_none()  { return Err("No option given!");     }
-        if input.is_none() { return Err("No input file given!"); }
+        if mode.is_none()  { return Err("No option given!".to_string());     }
+        if input.is_none() { return Err("No input file given!".to_string()); }
+
+        let mut passes = match pass_names {
+            Some(names) => {
+                let names = names.split(',').filter(|name| !name.is_empty()).collect::<Vec<&str>>();
+                match PassManager::from_names(&names) {
+                    Ok(x) => x,
+                    Err(_) => return Err(format!("Unknown pass given to --passes. Passes: {}.", pass_list()))
+                }
+            },
+            None => PassManager::preset(opt_level)
+        };
+        for name in print_after {
+            if passes.print_after(name).is_err() {
+                return Err(format!("Unknown pass given to --print-after. Passes: {}.", pass_list()));
+            }
+        }
 
         let output = match &output_file {
             Some(x) => {
                 let path = Path::new(&x);
                 let out_file = match File::create(&path) {
                     Ok(x) => x,
-                    Err(_) => return Err("Unable to write to output file!"),
+                    Err(_) => return Err("Unable to write to output file!".to_string()),
                 };
                 Box::new(out_file) as Box<dyn Write>
             }
@@ -114,11 +175,34 @@ impl Config {
         Ok(Config {
             input: input.unwrap(), 
             output,
-            mode: mode.unwrap()
+            mode: mode.unwrap(),
+            peephole,
+            bounds_checks,
+            overflow_checks,
+            passes,
         })
     }
 }
 
+/// Lowers Drewno Mars source to 3AC and reads it back as structured functions,
+/// ready for `Cfg::build`, after running the given pipeline of passes.
+/// Gives back the parser's complaint if the source doesn't parse.
+pub fn lower_to_ir(input: &str, passes: &PassManager) -> Result<IRProgram, String> {
+    let lexer = Lexer::new(input);
+    match ProgramParser::new().parse(lexer) {
+        Ok(x) => Ok(IRProgram::parse(&passes.run(convert_3ac(x))?)),
+        Err(x) => Err(parse_failure(input, x)),
+    }
+}
+
+// a lexing error comes without a position, so the scanner finds it again
+fn parse_failure(input: &str, error: ParseError<usize, TokenType, LexingError>) -> String {
+    match error {
+        ParseError::User { .. } => format!("Parse failed:\n{}", lexing_errors(input).trim_end()),
+        _ => format!("Parse failed: {:?}", error)
+    }
+}
+
 pub fn run(config: Config) {
     // read config
     let input = match std::fs::read_to_string(config.input) {
@@ -128,15 +212,14 @@ pub fn run(config: Config) {
 
     match config.mode {
         ProcessMode::Tokenize => {
-            let lines: Vec<&str> = input.split('\n').collect();
-            tokenizer(lines, config.output)
+            tokenizer(&input, config.output)
         },
         ProcessMode::ParseCheck => {
             let lexer = Lexer::new(&input[..]);
             
             match ProgramParser::new().parse(lexer) {
                 Ok(mut x) => {
-                    x.correct_span_rec(&line_bytes(input));
+                    x.correct_span_rec(&line_lengths(input));
                     // println!("{:?}", x);
                 },
                 Err(_) => { eprintln!("syntax error\nParse failed"); },
@@ -163,15 +246,15 @@ pub fn run(config: Config) {
                 .write_all(named_unparse(x, input).as_bytes())
                 .expect("Error writing to output file."),
                 
-                Err(x) => { eprintln!("Parse failed: {:?}", x); },
+                Err(x) => { eprintln!("{}", parse_failure(&input, x)); },
             };
         },
 
         ProcessMode::TypeCheck => {
             let lexer = Lexer::new(&input[..]);
             match ProgramParser::new().parse(lexer) {
-                Ok(mut x) => { x.correct_span_rec(&line_bytes(input)); type_error_check(x); },
-                Err(x) => { eprintln!("Parse failed: {:?}", x); },
+                Ok(mut x) => { x.correct_span_rec(&line_lengths(input)); type_error_check(x); },
+                Err(x) => { eprintln!("{}", parse_failure(&input, x)); },
             };
         },
 
@@ -180,10 +263,13 @@ pub fn run(config: Config) {
 
             let lexer = Lexer::new(&input[..]);
             match ProgramParser::new().parse(lexer) {
-                Ok(x) => output
-                    .write_all(convert_3ac(x).as_bytes())
-                    .expect("Error writing to output file."),
-                Err(x) => { eprintln!("Parse failed: {:?}", x); },
+                Ok(x) => match config.passes.run(convert_3ac(x)) {
+                    Ok(three_ac) => output
+                        .write_all(three_ac.as_bytes())
+                        .expect("Error writing to output file."),
+                    Err(x) => { eprintln!("{}", x); },
+                },
+                Err(x) => { eprintln!("{}", parse_failure(&input, x)); },
             };
         },
 
@@ -192,10 +278,31 @@ pub fn run(config: Config) {
 
             let lexer = Lexer::new(&input[..]);
             match ProgramParser::new().parse(lexer) {
+                Ok(x) => {
+                    let three_ac = match config.passes.run(convert_3ac(x)) {
+                        Ok(three_ac) => three_ac,
+                        Err(x) => { eprintln!("{}", x); return; },
+                    };
+                    let mut asm = convert_x86(three_ac, config.bounds_checks, config.overflow_checks);
+                    if config.peephole {
+                        asm = peephole(asm);
+                    }
+                    output
+                        .write_all(asm.as_bytes())
+                        .expect("Error writing to output file.")
+                },
+                Err(x) => { eprintln!("{}", parse_failure(&input, x)); },
+            };
+        },
+
+        ProcessMode::CfgDot => {
+            let mut output = config.output;
+
+            match lower_to_ir(&input, &config.passes) {
                 Ok(x) => output
-                    .write_all(convert_x86(convert_3ac(x)).as_bytes())
+                    .write_all(program_dot(&x.functions).as_bytes())
                     .expect("Error writing to output file."),
-                Err(x) => { eprintln!("Parse failed: {:?}", x); },
+                Err(x) => { eprintln!("{}", x); },
             };
         },
     };
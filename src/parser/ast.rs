@@ -1,5 +1,6 @@
 pub mod display;
 pub mod span;
+pub mod builtin;
 use span::Span;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -20,6 +21,8 @@ pub struct VarDecl {
     pub var_type: Box<Type>, 
     pub id: Box<Id>, 
     pub init_val: Option<Box<Exp>>,
+    /// The `///` lines before the declaration, without their slashes.
+    pub doc: Option<String>,
     // pub position: Position
 }
 
@@ -41,6 +44,9 @@ impl Type {
 impl PartialEq for Type {
     fn eq(&self, other: &Self) -> bool {
         use TypeKind::*;
+        if matches!(*self.kind, Array { .. }) || matches!(*other.kind, Array { .. }) {
+            return self.kind == other.kind;
+        }
         let self_type = match *self.kind {
             Prim(x) => x,
             _ => PrimType::Void
@@ -55,16 +61,41 @@ impl PartialEq for Type {
 }
 
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum TypeKind {
     Prim(PrimType),
     Class(Box<Id>),
+    /// `int[10]` has a size, `int[]` grows as it is pushed onto.
+    Array { elem: Box<Type>, size: Option<i64> },
+}
+
+impl TypeKind {
+    // the elements of a perfect array can't be assigned either
+    pub fn array(elem: TypeKind, size: Option<i64>, perfect: bool) -> TypeKind {
+        TypeKind::Array { elem: Type::new(elem, perfect), size }
+    }
+}
+
+// the size of an array is only known where it is declared, so it doesn't
+// take part in its type
+impl PartialEq for TypeKind {
+    fn eq(&self, other: &Self) -> bool {
+        use TypeKind::*;
+        match (self, other) {
+            (Prim(x), Prim(y)) => x == y,
+            (Class(x), Class(y)) => x.name == y.name,
+            (Array { elem: x, .. }, Array { elem: y, .. }) => x.kind == y.kind,
+            _ => false
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum PrimType {
     Bool,
     Int,
+    Float,
+    Char,
     Void,
     String,
 }
@@ -73,6 +104,7 @@ pub enum PrimType {
 pub struct ClassDecl {
     pub id: Box<Id>, 
     pub member_f: Box<Vec<Box<Decl>>>,
+    pub doc: Option<String>,
     // pub position: Position
 }
 
@@ -82,6 +114,7 @@ pub struct FnDecl {
     pub args: Vec<Box<FormalDecl>>, 
     pub ret: Box<Type>, 
     pub body: Vec<Box<Stmt>>,
+    pub doc: Option<String>,
     // pub position: Position
 }
 
@@ -105,8 +138,30 @@ pub enum Stmt {
 #[derive(Debug, Clone, PartialEq)]
 pub enum BlockStmt {
     While  {cond: Box<Exp>, body: Vec<Box<Stmt>> },
+    // init and step are optional, like in C, and step runs again after a continue
+    For    {init: Option<Box<LineStmt>>, cond: Box<Exp>, step: Option<Box<LineStmt>>, body: Vec<Box<Stmt>> },
     If     {cond: Box<Exp>, body: Vec<Box<Stmt>> } ,
     IfElse {cond: Box<Exp>, true_branch: Vec<Box<Stmt>>, false_branch: Vec<Box<Stmt>> },
+    DoWhile{body: Vec<Box<Stmt>>, cond: Box<Exp> },
+    // only loops take a label, for break and continue to name them by
+    Labeled{label: Box<Id>, body: Box<BlockStmt> },
+    // the first arm that fits runs, then the whole match is done
+    Match  {value: Box<Exp>, arms: Vec<MatchArm>, default: Option<Vec<Box<Stmt>>> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Vec<Box<Stmt>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    Int(i64),
+    // both ends included
+    Range(i64, i64),
+    Bool(bool),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -127,6 +182,8 @@ impl LineStmt {
 #[derive(Debug, Clone, PartialEq)]
 pub enum LineStmtKind {
     Assign { dest: Box<Loc>, src: Box<Exp> },
+    // dest op= src, op is one of the arithmetic kinds
+    CompoundAssign { dest: Box<Loc>, op: BinaryExpKind, src: Box<Exp> },
     PostDec{ loc: Box<Loc>},
     PostInc{ loc: Box<Loc>},
     Give   { output: Box<Exp>},
@@ -134,6 +191,9 @@ pub enum LineStmtKind {
     Return { result: Option<Box<Exp>>},
     Exit,
     Call(Box<CallExp>),
+    // without a label they go to the innermost loop
+    Break   { label: Option<Box<Id>>},
+    Continue{ label: Option<Box<Id>>},
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -173,8 +233,11 @@ pub enum ExpKind {
     Magic,
     UnaryExp(Box<UnaryExp>),
     BinaryExp(Box<BinaryExp>),
+    Ternary(Box<TernaryExp>),
     CallExp(Box<CallExp>),
-    IntLit(i32),
+    IntLit(i64),
+    FloatLit(f64),
+    CharLit(char),
     StrLit(String),
     Loc(Box<Loc>),
 }
@@ -208,6 +271,7 @@ impl UnaryExp {
 pub enum UnaryExpKind {
     Neg,
     Not,
+    BitNot,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -231,6 +295,28 @@ impl BinaryExp {
     }
 }
 
+// cond ? true_branch : false_branch, only the chosen branch is evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub struct TernaryExp {
+    pub cond: Box<Exp>,
+    pub true_branch: Box<Exp>,
+    pub false_branch: Box<Exp>,
+    pub expr_type: Option<Box<Type>>,
+    pub span: Span,
+}
+
+impl TernaryExp {
+    pub fn new(cond: Box<Exp>, true_branch: Box<Exp>, false_branch: Box<Exp>, left_span: usize, right_span: usize) -> Box<TernaryExp> {
+        Box::new(TernaryExp {
+            cond,
+            true_branch,
+            false_branch,
+            expr_type: None,
+            span: Span::new(left_span, right_span)
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum BinaryExpKind {
     And,
@@ -245,6 +331,12 @@ pub enum BinaryExpKind {
     Minus,
     Times,
     Divide,
+    Modulo,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -265,7 +357,34 @@ pub struct Loc {
 #[derive(Debug, Clone, PartialEq)]
 pub enum LocKind {
     Id(Box<Id>),
-    Loc { base_class: Box<Loc>, field_name: Box<Id> }
+    Loc { base_class: Box<Loc>, field_name: Box<Id> },
+    Index { base: Box<Loc>, index: Box<Exp> },
+}
+
+impl Loc {
+    /// For a field of an array element, `a[i]--inner--x`, the element and the
+    /// path to the field within it.
+    pub fn indexed_field(&self) -> Option<(&Loc, String)> {
+        match *self.kind {
+            LocKind::Loc { ref base_class, ref field_name } => {
+                if let LocKind::Index { .. } = *base_class.kind {
+                    return Some((base_class, field_name.to_string()));
+                }
+                let (elem, path) = base_class.indexed_field()?;
+                Some((elem, format!("{}--{}", path, field_name)))
+            },
+            _ => None
+        }
+    }
+
+    /// Whether reaching this location goes through an array.
+    pub fn is_indexed(&self) -> bool {
+        match *self.kind {
+            LocKind::Id(_) => false,
+            LocKind::Loc { ref base_class, .. } => base_class.is_indexed(),
+            LocKind::Index { .. } => true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -0,0 +1,39 @@
+use super::PrimType;
+use super::PrimType::*;
+
+/// A function every program can call without declaring it. The runtime
+/// library implements it under `runtime`, taking the arguments in registers.
+pub struct Builtin {
+    pub name: &'static str,
+    pub args: &'static [(&'static str, PrimType)],
+    pub ret: PrimType,
+    pub runtime: &'static str,
+}
+
+pub const BUILTINS: [Builtin; 10] = [
+    Builtin { name: "len", args: &[("s", String)], ret: Int, runtime: "stringLength" },
+    Builtin { name: "charAt", args: &[("s", String), ("i", Int)], ret: String, runtime: "stringCharAt" },
+    Builtin { name: "substr", args: &[("s", String), ("i", Int), ("n", Int)], ret: String, runtime: "stringSubstr" },
+    Builtin { name: "toString", args: &[("i", Int)], ret: String, runtime: "intToString" },
+    Builtin { name: "toInt", args: &[("s", String)], ret: Int, runtime: "stringToInt" },
+    Builtin { name: "toFloat", args: &[("i", Int)], ret: Float, runtime: "intToFloat" },
+    Builtin { name: "truncate", args: &[("f", Float)], ret: Int, runtime: "floatToInt" },
+    Builtin { name: "toChar", args: &[("i", Int)], ret: Char, runtime: "intToChar" },
+    Builtin { name: "charCode", args: &[("c", Char)], ret: Int, runtime: "charToInt" },
+    // the 3AC lowers `s[i]` to this, it can't be called by name
+    Builtin { name: "string.at", args: &[("s", String), ("i", Int)], ret: Char, runtime: "stringIndex" },
+];
+
+/// Built-ins over arrays. What they take depends on the element type, so type
+/// analysis checks their arguments itself instead of reading `args`. Only `push`
+/// can be called by name, the 3AC lowers `len` of an array and every array
+/// declaration to the other two.
+pub const ARRAY_BUILTINS: [Builtin; 3] = [
+    Builtin { name: "push", args: &[("a", Void), ("x", Void)], ret: Void, runtime: "arrayPush" },
+    Builtin { name: "array.len", args: &[("a", Void)], ret: Int, runtime: "arrayLength" },
+    Builtin { name: "array.new", args: &[("n", Int), ("stride", Int)], ret: Int, runtime: "arrayNew" },
+];
+
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().chain(ARRAY_BUILTINS.iter()).find(|builtin| builtin.name == name)
+}
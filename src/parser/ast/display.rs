@@ -1,4 +1,5 @@
 use crate::parser::ast::*;
+use crate::scanner::tokens::escape_char;
 
 use std::fmt::{Display, Formatter, Error};
 
@@ -23,8 +24,8 @@ impl Display for Decl {
 impl Display for VarDecl {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         match &self.init_val {
-            Some(v) => write!(fmt, "{} : {} = {};\n", &self.id, &self.var_type, v),
-            None => write!(fmt, "{} : {};\n", &self.id, &self.var_type),
+            Some(v) => write!(fmt, "{}{} : {} = {};\n", fmt_doc(&self.doc), &self.id, &self.var_type, v),
+            None => write!(fmt, "{}{} : {};\n", fmt_doc(&self.doc), &self.id, &self.var_type),
         }
     }
 }
@@ -46,6 +47,8 @@ impl Display for TypeKind {
         match self {
             Prim(x) => write!(fmt, "{}", x),
             Class(x) => write!(fmt, "{}", x),
+            Array { elem, size: Some(size) } => write!(fmt, "{}[{}]", elem.kind, size),
+            Array { elem, size: None } => write!(fmt, "{}[]", elem.kind),
         }
     }
 }
@@ -55,22 +58,24 @@ impl Display for PrimType {
         use PrimType::*;
         match *self {
             Int => write!(fmt, "int"),
+            Float => write!(fmt, "float"),
+            Char => write!(fmt, "char"),
             Bool => write!(fmt, "bool"),
             Void => write!(fmt, "void"),
-            _ => Ok(())
+            String => write!(fmt, "string"),
         }   
     }
 }
 
 impl Display for ClassDecl {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
-        write!(fmt, "{} : class {{\n{}}};\n", &self.id, fmt_vec(&self.member_f))
+        write!(fmt, "{}{} : class {{\n{}}};\n", fmt_doc(&self.doc), &self.id, fmt_vec(&self.member_f))
     }
 }
 
 impl Display for FnDecl {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
-        write!(fmt, "{} : ({}) {}  {{\n{}}}\n", &self.id, fmt_vec_commas(&self.args), &self.ret, fmt_vec(&self.body))
+        write!(fmt, "{}{} : ({}) {}  {{\n{}}}\n", fmt_doc(&self.doc), &self.id, fmt_vec_commas(&self.args), &self.ret, fmt_vec(&self.body))
     }
 }
 
@@ -100,8 +105,36 @@ impl Display for BlockStmt {
         use BlockStmt::*;
         match self {
             While{cond, body} => write!(fmt, "while ({}){{\n{}}}\n", cond, fmt_vec(body)),
+            For{init, cond, step, body} => write!(fmt, "for ({}; {}; {}){{\n{}}}\n",
+                fmt_opt(init), cond, fmt_opt(step), fmt_vec(body)),
             If{cond, body} => write!(fmt, "if ({}){{\n{}}}\n", cond, fmt_vec(body)),
             IfElse{cond, true_branch, false_branch} => write!(fmt, "if ({}){{\n{}}}\nelse{{\n{}}}\n", cond, fmt_vec(true_branch), fmt_vec(false_branch)),
+            DoWhile{body, cond} => write!(fmt, "do{{\n{}}}\nwhile ({});\n", fmt_vec(body), cond),
+            Labeled{label, body} => write!(fmt, "{}: {}", label, body),
+            Match{value, arms, default} => {
+                let default = match default {
+                    Some(x) => format!("else => {{\n{}}}\n", fmt_vec(x)),
+                    None => "".to_string()
+                };
+                write!(fmt, "match ({}){{\n{}{}}}\n", value, fmt_vec(arms), default)
+            },
+        }
+    }
+}
+
+impl Display for MatchArm {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(fmt, "{} => {{\n{}}}\n", self.pattern, fmt_vec(&self.body))
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        use Pattern::*;
+        match self {
+            Int(x) => write!(fmt, "{}", x),
+            Range(lo, hi) => write!(fmt, "{}..{}", lo, hi),
+            Bool(x) => write!(fmt, "{}", x),
         }
     }
 }
@@ -117,6 +150,7 @@ impl Display for LineStmtKind {
         use LineStmtKind::*;
         match self {
             Assign{dest, src} => write!(fmt, "{} = {}", dest, src),
+            CompoundAssign{dest, op, src} => write!(fmt, "{} {}= {}", dest, op, src),
             PostDec{loc} => write!(fmt, "{}--", loc),
             PostInc{loc} => write!(fmt, "{}++", loc),
             Give{output} => write!(fmt, "give {}", output),
@@ -126,6 +160,8 @@ impl Display for LineStmtKind {
                 None => write!(fmt, "return"),}},
             Exit => write!(fmt, "today I don't feel like doing any work"),
             Call(ref exp) => write!(fmt, "{}", exp),
+            Break{label} => write!(fmt, "break{}", fmt_label(label)),
+            Continue{label} => write!(fmt, "continue{}", fmt_label(label)),
         }
     }
 }
@@ -145,8 +181,11 @@ impl Display for ExpKind {
             Magic => write!(fmt, "magic"),
             UnaryExp(ref exp) => write!(fmt, "{}", exp),
             BinaryExp(ref exp) => write!(fmt, "{}", exp),
+            Ternary(ref exp) => write!(fmt, "{}", exp),
             CallExp(ref exp) => write!(fmt, "{}", exp),
             IntLit(ref lit) => write!(fmt, "{}", lit),
+            FloatLit(ref lit) => write!(fmt, "{:?}", lit),
+            CharLit(lit) => write!(fmt, "{}", escape_char(*lit)),
             StrLit(ref lit) => write!(fmt, "{}", lit),
             Loc(ref loc) => write!(fmt, "{}", loc),
         }
@@ -165,6 +204,7 @@ impl Display for UnaryExpKind {
         match self {
             Neg => write!(fmt, "-"),
             Not => write!(fmt, "!"),
+            BitNot => write!(fmt, "~"),
         }
     }
 }
@@ -175,6 +215,14 @@ impl Display for BinaryExp {
     }
 }
 
+// it binds looser than everything else, so the parentheses keep it whole
+// wherever it ends up
+impl Display for TernaryExp {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(fmt, "({} ? {} : {})", self.cond, self.true_branch, self.false_branch)
+    }
+}
+
 impl Display for BinaryExpKind {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         use BinaryExpKind::*;
@@ -191,6 +239,12 @@ impl Display for BinaryExpKind {
             Minus => write!(fmt, "-"),
             Times => write!(fmt, "*"),
             Divide => write!(fmt, "/"),
+            Modulo => write!(fmt, "%"),
+            BitAnd => write!(fmt, "&"),
+            BitOr => write!(fmt, "|"),
+            BitXor => write!(fmt, "^"),
+            ShiftLeft => write!(fmt, "<<"),
+            ShiftRight => write!(fmt, ">>"),
         }
     }
 }
@@ -213,6 +267,7 @@ impl Display for LocKind {
         match self {
             Id(ref x) => write!(fmt, "{}", x),
             Loc { base_class , field_name} => write!(fmt, "{}--{}", base_class, field_name),
+            Index { base, index } => write!(fmt, "{}[{}]", base, index),
         }
     }
 }
@@ -229,4 +284,25 @@ fn fmt_vec_commas<T: std::fmt::Display>(vec: &Vec<T>) -> String {
 
 fn fmt_vec<T: std::fmt::Display>(vec: &Vec<T>) -> String {
     return vec.iter().map(|arg| format!("{}", arg)).collect::<Vec<String>>().join("");
-}
\ No newline at end of file
+}
+// one /// line for every line of the doc comment
+fn fmt_doc(doc: &Option<String>) -> String {
+    match doc {
+        Some(x) => x.split('\n').map(|line| format!("///{}\n", line)).collect(),
+        None => "".to_string()
+    }
+}
+
+fn fmt_label(label: &Option<Box<Id>>) -> String {
+    match label {
+        Some(x) => format!(" {}", x),
+        None => "".to_string()
+    }
+}
+
+fn fmt_opt<T: std::fmt::Display>(opt: &Option<T>) -> String {
+    match opt {
+        Some(x) => format!("{}", x),
+        None => "".to_string()
+    }
+}
@@ -83,18 +83,18 @@ impl Span {
 	let mut line_bytes = line_bytes.into_iter();
 	loop {
 		let line = match line_bytes.next() {
-			Some(x) => x + 2,
+			Some(x) => *x,
 			None => break,
 		};
 
-		if self.start.col >= line {
+		if self.start.col > line {
 			self.start.col -= line;
 			self.start.row += 1;
 		} else {
 			()
 		}
 
-		if self.end.col >= line {
+		if self.end.col > line {
 			self.end.col -= line;
 			self.end.row += 1;
 		} else {
@@ -104,13 +104,15 @@ impl Span {
 }
 }
 
-pub fn line_bytes(input: String) -> Vec<usize> {
-	let lines = input.lines();
-	lines.map(|line| line.len()).collect::<Vec<usize>>()
+/// The length of every line in characters, the unit the lexer's spans count in,
+/// along with whichever line terminator ends it.
+pub fn line_lengths(input: String) -> Vec<usize> {
+	let lines = input.split_inclusive('\n');
+	lines.map(|line| line.chars().count()).collect::<Vec<usize>>()
 }
 
 mod tests {
-	use super::Position;
+	use super::{line_lengths, Position, Span};
 	use std::cmp::Ordering;
 
 	impl Position {
@@ -174,4 +176,14 @@ mod tests {
 		);
 		}
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn correct_counts_line_terminators() {
+		// `x` starts at char 9 with \n endings and at char 11 with \r\n ones
+		for (input, start) in [("a;\nbb;\n  x;\n", 9), ("a;\r\nbb;\r\n  x;\r\n", 11)] {
+			let mut span = Span::new(start, start + 1);
+			span.correct(&line_lengths(input.to_string()));
+			assert_eq!(span.to_string(), "[3,3]-[3,4]", "for {:?}", input);
+		}
+	}
+}
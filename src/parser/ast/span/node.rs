@@ -89,12 +89,46 @@ impl SpanNode for BlockStmt {
 					stmt.correct_span_rec(line_bytes);
 				}
 			},
+            For{init, cond, step, body} => {
+				if let Some(x) = init {
+					x.correct_span_rec(line_bytes);
+				}
+				cond.correct_span_rec(line_bytes);
+				if let Some(x) = step {
+					x.correct_span_rec(line_bytes);
+				}
+				for stmt in body.iter_mut() {
+					stmt.correct_span_rec(line_bytes);
+				}
+			},
             If{cond, body} => {
 				cond.correct_span_rec(line_bytes);
 				for stmt in body.iter_mut() {
 					stmt.correct_span_rec(line_bytes);
 				}
 			},
+            DoWhile{body, cond} => {
+				for stmt in body.iter_mut() {
+					stmt.correct_span_rec(line_bytes);
+				}
+				cond.correct_span_rec(line_bytes);
+			},
+            Labeled{label, body} => {
+				label.correct_span_rec(line_bytes);
+				body.correct_span_rec(line_bytes);
+			},
+            Match{value, arms, default} => {
+				value.correct_span_rec(line_bytes);
+				for arm in arms.iter_mut() {
+					arm.span.correct(line_bytes);
+					for stmt in arm.body.iter_mut() {
+						stmt.correct_span_rec(line_bytes);
+					}
+				}
+				for stmt in default.iter_mut().flatten() {
+					stmt.correct_span_rec(line_bytes);
+				}
+			},
             IfElse{cond, true_branch, false_branch} => {
 				cond.correct_span_rec(line_bytes);
 				for stmt in true_branch.iter_mut() {
@@ -120,7 +154,7 @@ impl SpanNode for LineStmtKind {
 		use LineStmtKind::*;
 
 		match self {
-            Assign{dest, src} => {
+            Assign{dest, src} | CompoundAssign{dest, src, ..} => {
 				dest.correct_span_rec(line_bytes);
 				src.correct_span_rec(line_bytes);
 			},
@@ -131,6 +165,9 @@ impl SpanNode for LineStmtKind {
             Return{result} => {match result {
                 Some(x) => x.correct_span_rec(line_bytes),
                 None => (),}},
+            Break{label} | Continue{label} => {match label {
+                Some(x) => x.correct_span_rec(line_bytes),
+                None => (),}},
             Exit => (),
             Call(exp) => exp.correct_span_rec(line_bytes),
         }
@@ -153,6 +190,7 @@ impl SpanNode for ExpKind {
             Magic => (),
             UnaryExp(exp) => exp.correct_span_rec(line_bytes),
             BinaryExp(exp) => exp.correct_span_rec(line_bytes),
+            Ternary(exp) => exp.correct_span_rec(line_bytes),
             CallExp(exp) => exp.correct_span_rec(line_bytes),
             // IntLit(lit) => lit.correct_span_rec(line_bytes),
             // StrLit(lit) => lit.correct_span_rec(line_bytes),
@@ -177,6 +215,15 @@ impl SpanNode for BinaryExp {
     }
 }
 
+impl SpanNode for TernaryExp {
+    fn correct_span_rec(&mut self, line_bytes: &Vec<usize>) {
+		self.span.correct(line_bytes);
+        self.cond.correct_span_rec(line_bytes);
+		self.true_branch.correct_span_rec(line_bytes);
+		self.false_branch.correct_span_rec(line_bytes);
+    }
+}
+
 impl SpanNode for CallExp {
     fn correct_span_rec(&mut self, line_bytes: &Vec<usize>) {
 		self.span.correct(line_bytes);
@@ -203,6 +250,10 @@ impl SpanNode for LocKind {
 				base_class.correct_span_rec(line_bytes);
 				field_name.correct_span_rec(line_bytes);
 			},
+            Index { base, index } => {
+				base.correct_span_rec(line_bytes);
+				index.correct_span_rec(line_bytes);
+			},
         }
     }
 }
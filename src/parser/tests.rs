@@ -97,10 +97,23 @@ fn parse_program() {
             c:perfect bool;
         };",
         "myClass:class {};",
+        "/// documented
+        /// twice
+        a : int;
+        /* a /* nested */ comment */
+        /// a class
+        myClass:class {
+            /// a field
+            a:int;
+            /// a method
+            b:() void {}
+        };",
+        "a : int; /// nothing after it",
     ];
 
     let program_bad = vec![
         "main();",
+        "/* /* only one closed */ a : int;",
         "myClass:class {
             a:int = 12
             b:int
@@ -112,6 +125,62 @@ fn parse_program() {
 }
 
 
+#[test]
+fn doc_comments_attach_to_decls() {
+    use super::ast::{Decl, Stmt};
+    let program = ProgramParser::new().parse(Lexer::new("
+        /// Adds.
+        ///   Twice.
+        add : () void {
+            /// a local
+            x : int;
+            y : int;
+        }
+        //// a divider
+        z : int;
+        p : class { /// a field
+            f : int; };
+    ")).unwrap();
+
+    let Decl::FnDecl(add) = &*program.globals[0] else { panic!("add is not a function") };
+    assert_eq!(add.doc.as_deref(), Some(" Adds.\n   Twice."));
+    let docs: Vec<_> = add.body.iter().map(|stmt| match &**stmt {
+        Stmt::VarDecl(x) => x.doc.clone(),
+        _ => panic!("not a declaration")
+    }).collect();
+    assert_eq!(docs, vec![Some(" a local".to_string()), None]);
+
+    let Decl::VarDecl(z) = &*program.globals[1] else { panic!("z is not a variable") };
+    assert_eq!(z.doc, None);
+
+    let Decl::ClassDecl(p) = &*program.globals[2] else { panic!("p is not a class") };
+    let Decl::VarDecl(f) = &*p.member_f[0] else { panic!("f is not a field") };
+    assert_eq!((p.doc.as_deref(), f.doc.as_deref()), (None, Some(" a field")));
+}
+
+#[test]
+fn stray_doc_comments_are_comments() {
+    use super::ast::{Decl, Stmt};
+    let program = ProgramParser::new().parse(Lexer::new("
+        main : () void {
+            x : int;
+            x = 1; /// note
+            /// before a label
+            outer: while (true) { break outer; }
+            /// before a give
+            give x;
+            /// before the end
+        }
+        /// at the end of the file
+    ")).unwrap();
+
+    let Decl::FnDecl(main) = &*program.globals[0] else { panic!("main is not a function") };
+    assert_eq!(main.doc, None);
+    assert_eq!(main.body.len(), 4);
+    let Stmt::VarDecl(x) = &*main.body[0] else { panic!("x is not a declaration") };
+    assert_eq!(x.doc, None);
+}
+
 #[test]
 fn parse_decl() {
     let d_good = vec![
@@ -132,6 +201,7 @@ fn parse_var_decl() {
     let vd_good = vec![
         "a : int;",
         "a : int = 123;",
+        "s : string = \"meow\";",
     ];
 
     let vd_bad = vec![
@@ -147,6 +217,7 @@ fn parse_fn_decl() {
         "main : () void {}",
         "a : (b: int, c: void) bool {}",
         "a : (b: int, c: void) void { return; }",
+        "a : (b: string) string { return b; }",
     ];
 
     let fd_bad = vec![
@@ -163,14 +234,21 @@ fn parse_type() {
         "int",
         "bool",
         "void",
+        "string",
         "perfect int",
+        "perfect string",
         "perfect owo",
         "perfect _nya",
+        "int[10]",
+        "string[]",
+        "perfect owo[3]",
     ];
 
     let type_bad = vec![
         "perfect",
         "perfect 123",
+        "int[n]",
+        "int[10",
     ];
     
     test_inputs(type_good, Some(type_bad), &ParserType::Type);
@@ -180,8 +258,11 @@ fn parse_type() {
 fn parse_prim_type() {
     let prim_type = vec![
         "int",
+        "float",
+        "char",
         "bool",
         "void",
+        "string",
     ];
     
     test_inputs(prim_type, None, &ParserType::PrimType);
@@ -225,10 +306,31 @@ fn parse_stmt_list() {
         "return;",
         "return; return;",
         "return; if (true) {}",
+        "for (i = 0; i < 3; i++) { break; }",
+        "for (; true; ) { continue; }",
+        "if (a) {} else if (b) {} else {}",
+        "if (a) {} else if (b) {}",
+        "match (n) { 1 => {} -2..2 => { give n; } else => {} }",
+        "match (b) { true => {} false => {} }",
+        "match (n) {}",
+        "do { give i; } while (i < 3);",
+        "outer: while (true) { inner: for (;true;) { break outer; continue inner; } }",
+        "a: do {} while (true);",
+        "/// a local
+        x : int; x = 2;",
+        "/// not a declaration
+        return;",
+        "x = 1; /// trailing",
     ];
 
     let stmt_list_bad = vec![
-        "return"
+        "return",
+        "for (i = 0, i < 3) {}",
+        "if (a) {} else if {}",
+        "match (n) { else => {} 1 => {} }",
+        "match (n) { x => {} }",
+        "do { } while (true)",
+        "a: if (true) {}",
     ];
     //println!("{:?}", StmtListParser::new().parse(Lexer::new("return; return;")));
     test_inputs(stmt_list_good, Some(stmt_list_bad), &ParserType::StmtList);
@@ -249,11 +351,24 @@ fn parse_line_stmt() {
         "today I don't feel like doing any work",
         "abc()",
         "owo(uwu)",
+        "break",
+        "continue",
+        "break outer",
+        "continue outer",
+        "a += 1",
+        "a[i] -= b * 2",
+        "a--b *= 3",
+        "a /= b",
+        "a %= 7",
     ];
 
     let stmt_bad = vec![
         "take a-b",
         "abc--abc a--c",
+        "break a--b",
+        "a += ",
+        "a + = 1",
+        "a() += 1",
     ];
 
 
@@ -281,10 +396,26 @@ fn parse_exp() {
         "abc <= abc",
         "abc",
         "a-b-c-d-e-f",
+        "abc % abc",
+        "abc & abc | abc ^ abc",
+        "~abc",
+        "abc << 2 >> 1",
+        "~a & -b << c % 3 == d",
+        "a ? b : c",
+        "a < b ? a : b + 1",
+        "a ? b ? c : d : e ? f : g",
+        "(a ? \"x\" : \"y\") + s",
+        "1.5 * -x + 2e10 / y",
+        "s[i] == 'a' and c != '\\n'",
     ];
 
     let exp_bad = vec![
         "abc abc",
+        "a ? b",
+        "a ? : c",
+        "a ? b : c : d",
+        "abc ~ abc",
+        "abc & & abc",
     ];
     test_inputs(exp_good, Some(exp_bad), &ParserType::Exp);
 }
@@ -338,10 +469,23 @@ fn parse_term() {
         "a (a-b)",
         "(!!too hot)",
         "(a-b)",
+        "2147483648",
+        "9223372036854775807",
+        "0x1F",
+        "0b1010",
+        "0o17",
+        "1_000_000",
+        "3.25",
+        "1e-3",
+        "'x'",
+        "'\\''",
     ];
 
     let terms_bad = vec![
-        "2147483648",
+        "9223372036854775808",
+        "0x8000_0000_0000_0000",
+        "1e400",
+        "1.",
         r#""meow meow meow"#,
         r#""heewoo!??! \g""#,
         r#""\g"#,
@@ -356,11 +500,16 @@ fn parse_loc() {
         "a--b--c--d",
         "owo--owo",
         "_nya_",
+        "a[1]",
+        "a[i + 1]--x",
+        "a[b[0]]",
     ];
 
     let locs_bad = vec![
         "a--b--c--d--",
         "--",
+        "a[]",
+        "a[1",
     ];
     test_inputs(locs_good, Some(locs_bad),&ParserType::Loc);
 }
@@ -6,17 +6,18 @@ use tokens::TokenType;
 
 pub mod lexer;
 
-pub fn tokenizer(input: Vec<&str>, mut tokens: Box<dyn Write>) {
+#[cfg(test)]
+mod tests;
+
+pub fn tokenizer(input: &str, mut tokens: Box<dyn Write>) {
     let mut scanner = Scanner::new();
     
     // processing 
-    for line in input.iter() {
-        let (output, error) = scanner.tokenize_line(&line);
-        if output != "" {       tokens.write_all(output.as_bytes()).expect("Error writing to file.");      }
-        if error  != "" { io::stderr().write_all(error.as_bytes()).expect("Error writing to error file."); }
-    }
+    let (output, error) = scanner.tokenize(input);
+    if output != "" {       tokens.write_all(output.as_bytes()).expect("Error writing to file.");      }
+    if error  != "" { io::stderr().write_all(error.as_bytes()).expect("Error writing to error file."); }
     
-    let eof = if *input.last().unwrap() == "" {
+    let eof = if input.is_empty() || input.ends_with('\n') {
         format!("EOF [{},1]", scanner.row + 1)
     } else {
         format!("EOF [{},{}]", scanner.row, scanner.last_col)
@@ -25,6 +26,12 @@ pub fn tokenizer(input: Vec<&str>, mut tokens: Box<dyn Write>) {
     tokens.write_all(eof.as_bytes()).expect("Error writing to file.");
 }
 
+/// The lexing errors in the input, one positioned line each, for when the
+/// parser has given up on a token it can't say where it was.
+pub fn lexing_errors(input: &str) -> String {
+    Scanner::new().tokenize(input).1
+}
+
 pub struct Scanner {
     pub row: u32,
     pub last_col: usize,
@@ -38,8 +45,20 @@ impl Scanner {
         }
     }
 
-    pub fn tokenize_line(&mut self, stream: &str) -> (String, String) {
-        self.row += 1;
+    /// Lexes the whole input at once, so a block comment can run over several
+    /// lines, and places every token at the row and column it starts on.
+    pub fn tokenize(&mut self, stream: &str) -> (String, String) {
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(stream.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        self.row = line_starts.len() as u32;
+
+        // columns count characters, not bytes
+        let position = |offset: usize| {
+            let row = line_starts.partition_point(|&start| start <= offset);
+            (row, stream[line_starts[row - 1]..offset].chars().count() + 1)
+        };
+
         let lex: logos::SpannedIter<'_, TokenType>  = TokenType::lexer(stream).spanned();
         
         // initialize return texts
@@ -47,20 +66,20 @@ impl Scanner {
         let mut errors: String = "".to_owned();
 
         // iterate through tokens that logos lexer found 
-        for (result, mut range) in lex {
-            range.start += 1;
-            range.end += 1;
+        for (result, range) in lex {
+            let (row, col) = position(range.start);
+            let (end_row, end_col) = position(range.end);
             
             match result {
                 Ok(token) => {
-                    text = format!("{}{:15}\t[{},{}]\n", text, token, self.row, range.start);
+                    text = format!("{}{:15}\t[{},{}]\n", text, token, row, col);
                 }
                 Err(e) => {
-                    errors = format!("{}FATAL [{},{}]-[{},{}]: {}\n", errors, self.row, range.start, self.row, range.end, e);
+                    errors = format!("{}FATAL [{},{}]-[{},{}]: {}\n", errors, row, col, end_row, end_col, e);
                 }
             }
-            self.last_col = range.end;
+            self.last_col = end_col;
         }
         (text, errors)
     }
-}
\ No newline at end of file
+}
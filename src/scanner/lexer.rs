@@ -1,30 +1,82 @@
+use std::collections::VecDeque;
 use logos::{Logos, SpannedIter};
 use super::tokens::{TokenType, LexingError};
 
 pub struct Lexer<'input> {
+    input: &'input str,
     token_stream: SpannedIter<'input, TokenType>,
+    // the last byte offset handed out and how many chars come before it
+    seen: (usize, usize),
+    // tokens read ahead to see what follows a doc comment
+    lookahead: VecDeque<Spanned<TokenType, usize, LexingError>>,
 }
 
 impl<'input> Lexer<'input> {
     pub fn new(input: &'input str) -> Self {
-        Self { token_stream: TokenType::lexer(input).spanned() }
+        Self { input, token_stream: TokenType::lexer(input).spanned(), seen: (0, 0), lookahead: VecDeque::new() }
     }
-}
-
-pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
 
-impl<'input> Iterator for Lexer<'input> {
-    type Item = Spanned<TokenType, usize, LexingError>;
+    // spans count characters, not bytes, tokens only ever move forward
+    fn char_offset(&mut self, byte: usize) -> usize {
+        let (last_byte, last_char) = self.seen;
+        let offset = last_char + self.input[last_byte..byte].chars().count();
+        self.seen = (byte, offset);
+        offset
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn pull(&mut self) -> Option<Spanned<TokenType, usize, LexingError>> {
         self.token_stream.next().map(|(token, span)| {
             //let token = token.unwrap_or_else(|_| LexicalError::InvalidToken );
             match token {
-                Ok(x) => Ok((span.start, x, span.end)),
+                Ok(x) => {
+                    let start = self.char_offset(span.start);
+                    Ok((start, x, self.char_offset(span.end)))
+                }
                 Err(x) => {
                     Err(x)
                 }
             }
         })
     }
-}
\ No newline at end of file
+
+    // a declaration starts with `name :` and then a type, `(` or `class`,
+    // where a loop label has the loop instead
+    fn declaration_follows(&mut self) -> bool {
+        let is_doc = |item: &Spanned<TokenType, usize, LexingError>| matches!(item, Ok((_, TokenType::DOCCOMMENT(_), _)));
+        while self.lookahead.iter().filter(|item| !is_doc(item)).count() < 3 {
+            match self.pull() {
+                Some(item) => self.lookahead.push_back(item),
+                None => break
+            }
+        }
+        let next: Vec<&TokenType> = self.lookahead.iter()
+            .filter(|item| !is_doc(item))
+            .take(3)
+            .map_while(|item| item.as_ref().ok().map(|(_, token, _)| token))
+            .collect();
+        matches!(next[..], [
+            TokenType::ID(_),
+            TokenType::COLON,
+            TokenType::INT | TokenType::FLOAT | TokenType::CHAR | TokenType::BOOL | TokenType::VOID |
+            TokenType::STRING | TokenType::ID(_) | TokenType::PERFECT | TokenType::LPAREN | TokenType::CLASS
+        ])
+    }
+}
+
+pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Spanned<TokenType, usize, LexingError>;
+
+    // `///` lines only reach the parser when a declaration follows them,
+    // anywhere else they are ordinary comments
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.lookahead.pop_front().or_else(|| self.pull())?;
+            if matches!(item, Ok((_, TokenType::DOCCOMMENT(_), _))) && !self.declaration_follows() {
+                continue;
+            }
+            return Some(item);
+        }
+    }
+}
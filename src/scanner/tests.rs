@@ -12,7 +12,7 @@ fn check_valid (mut lex: Lexer<'_, TokenType>, expected_vec: Vec<TokenType>) {
         let text = lex.slice();
         let token = match result {
             Ok(x) => x,
-            Err(_) => panic!("{text} returned an error!"),
+            Err(e) => panic!("{text} returned an error! {e}"),
         };
         let expected = &expected_vec[i];
 
@@ -51,7 +51,12 @@ fn lex_valid_tokens() {
         +         -          ==       >       >= 
         {         <          <=       (       ! 
         !=        --         ++       }       ) 
-        ;         /          * 
+        ;         /          *        string  [
+        ]         for        break    continue  match
+        =>        ..         do      %       &
+        |         ^          ~       <<      >>
+        +=        -=         *=      /=      %=
+        ?         float      char
         today I don't feel like doing any work"#
     );
 
@@ -63,7 +68,14 @@ fn lex_valid_tokens() {
         TokenType::CROSS,     TokenType::DASH,    TokenType::EQUALS,  TokenType::GREATER, TokenType::GREATEREQ,
         TokenType::LCURLY,    TokenType::LESS,    TokenType::LESSEQ,  TokenType::LPAREN,  TokenType::NOT,
         TokenType::NOTEQUALS, TokenType::POSTDEC, TokenType::POSTINC, TokenType::RCURLY,  TokenType::RPAREN,
-        TokenType::SEMICOL,   TokenType::SLASH,   TokenType::STAR,    TokenType::EXIT
+        TokenType::SEMICOL,   TokenType::SLASH,   TokenType::STAR,    TokenType::STRING,  TokenType::LBRACKET,
+        TokenType::RBRACKET,  TokenType::FOR,     TokenType::BREAK,   TokenType::CONTINUE,
+        TokenType::MATCH,     TokenType::ARROW,   TokenType::DOTDOT,  TokenType::DO,
+        TokenType::PERCENT,   TokenType::AMPERSAND, TokenType::PIPE,  TokenType::CARET,   TokenType::TILDE,
+        TokenType::LSHIFT,    TokenType::RSHIFT,
+        TokenType::CROSSASSIGN, TokenType::DASHASSIGN, TokenType::STARASSIGN, TokenType::SLASHASSIGN,
+        TokenType::PERCENTASSIGN, TokenType::QUESTION, TokenType::FLOAT, TokenType::CHAR,
+        TokenType::EXIT
     ];
 
     check_valid(lex, correct_results);
@@ -72,38 +84,191 @@ fn lex_valid_tokens() {
 #[test]
 fn lex_lits() {
     let valid_lex = TokenType::lexer(r#"
-        0 500 2147483647 
+        0 500 2147483647 2147483648 9223372036854775807
+        1.5 0.25 2e10 6.02E+23 1e-3 1..5
         _nice __ _ a a123 
         "meow!"
         " this is a valid escape: \n"
+        'a' ' ' '\n' '\'' '"' '\\'
         "#
     );
 
     let invalid_lex = TokenType::lexer(r#"
-        2147483648
+        9223372036854775808
+        1e400
         @ $
         " this is an invalid escape: \g "
         " what .... 
         " what .... \g
+        '\g'
+        ''
+        'ab
         "#
     );
 
     let valid_results = vec![
         TokenType::INTLITERAL(0), TokenType::INTLITERAL(500), TokenType::INTLITERAL(2147483647), 
+        TokenType::INTLITERAL(2147483648), TokenType::INTLITERAL(i64::MAX),
+        TokenType::FLOATLITERAL(1.5), TokenType::FLOATLITERAL(0.25), TokenType::FLOATLITERAL(2e10),
+        TokenType::FLOATLITERAL(6.02e23), TokenType::FLOATLITERAL(1e-3),
+        TokenType::INTLITERAL(1), TokenType::DOTDOT, TokenType::INTLITERAL(5),
         TokenType::ID("_nice".to_string()), TokenType::ID("__".to_string()), TokenType::ID("_".to_string()), 
         TokenType::ID("a".to_string()), TokenType::ID("a123".to_string()), 
         TokenType::STRINGLITERAL(r#""meow!""#.to_string()), 
-        TokenType::STRINGLITERAL(r#"" this is a valid escape: \n""#.to_string()) 
+        TokenType::STRINGLITERAL(r#"" this is a valid escape: \n""#.to_string()),
+        TokenType::CHARLITERAL('a'), TokenType::CHARLITERAL(' '), TokenType::CHARLITERAL('\n'),
+        TokenType::CHARLITERAL('\''), TokenType::CHARLITERAL('"'), TokenType::CHARLITERAL('\\'),
     ];
 
     let invalid_results = vec![
         LexingError::IntliteralOverflow,
+        LexingError::FloatliteralOverflow,
         LexingError::Illegal("@".to_string()), LexingError::Illegal("$".to_string()),
         LexingError::StringliteralBadEscape, 
         LexingError::StringliteralUnterminated, 
         LexingError::StringliteralUnterminatedBadEscape, 
+        LexingError::CharliteralBadEscape,
+        LexingError::CharliteralEmpty,
+        LexingError::CharliteralUnterminated,
     ];
 
     check_valid(valid_lex, valid_results);
     check_invalid(invalid_lex, invalid_results);
-}
\ No newline at end of file
+}
+#[test]
+fn lex_int_radixes() {
+    let valid_lex = TokenType::lexer(r#"
+        0x1F 0xff_FF 0b1010 0b1_1 0o17 0o777 1_000_000 0_1 0o1_0
+        0x7fff_ffff_ffff_ffff 0b111 0o0
+        "#
+    );
+
+    let invalid_lex = TokenType::lexer(r#"
+        0x8000_0000_0000_0000
+        0b1111111111111111111111111111111111111111111111111111111111111111
+        0o1000000000000000000000
+        9_223_372_036_854_775_808
+        0x 0b_ 0o_
+        1_ 0b_1 0x1__F 0o7_ 1__0
+        "#
+    );
+
+    let valid_results = vec![
+        TokenType::INTLITERAL(31), TokenType::INTLITERAL(0xffff), TokenType::INTLITERAL(10), 
+        TokenType::INTLITERAL(3), TokenType::INTLITERAL(15), TokenType::INTLITERAL(511), 
+        TokenType::INTLITERAL(1_000_000), TokenType::INTLITERAL(1), TokenType::INTLITERAL(8),
+        TokenType::INTLITERAL(i64::MAX), TokenType::INTLITERAL(7), TokenType::INTLITERAL(0),
+    ];
+
+    let invalid_results = vec![
+        LexingError::IntliteralOverflow, LexingError::IntliteralOverflow,
+        LexingError::IntliteralOverflow, LexingError::IntliteralOverflow,
+        LexingError::IntliteralEmpty, LexingError::IntliteralEmpty, LexingError::IntliteralEmpty,
+        LexingError::IntliteralBadSeparator, LexingError::IntliteralBadSeparator,
+        LexingError::IntliteralBadSeparator, LexingError::IntliteralBadSeparator,
+        LexingError::IntliteralBadSeparator,
+    ];
+
+    check_valid(valid_lex, valid_results);
+    check_invalid(invalid_lex, invalid_results);
+}
+
+#[test]
+fn lex_unicode() {
+    let valid_lex = TokenType::lexer(r#"
+        größe π _ą zähler2
+        "zażółć gęślą jaźń" // komentarz → ✓
+        "\r\x41\x7f\u{1F600}\u{a}"
+        '\r' '\0' '\x41' '\u{3c0}' 'ł'
+        "#
+    );
+
+    let invalid_lex = TokenType::lexer(r#"
+        "\x80"
+        "\x4"
+        "\u{110000}"
+        "\u{d800}"
+        "\u{1234567}"
+        "\u{}" 
+        "\u41"
+        '\xg1'
+        '\u{41'
+        →
+        "a\0b" "\x00" "\u{0}"
+        "#
+    );
+
+    let valid_results = vec![
+        TokenType::ID("größe".to_string()), TokenType::ID("π".to_string()), 
+        TokenType::ID("_ą".to_string()), TokenType::ID("zähler2".to_string()),
+        TokenType::STRINGLITERAL(r#""zażółć gęślą jaźń""#.to_string()),
+        TokenType::STRINGLITERAL(r#""\r\x41\x7f\u{1F600}\u{a}""#.to_string()),
+        TokenType::CHARLITERAL('\r'), TokenType::CHARLITERAL('\0'), TokenType::CHARLITERAL('A'),
+        TokenType::CHARLITERAL('π'), TokenType::CHARLITERAL('ł'),
+    ];
+
+    let invalid_results = vec![
+        LexingError::StringliteralBadEscape, LexingError::StringliteralBadEscape,
+        LexingError::StringliteralBadEscape, LexingError::StringliteralBadEscape,
+        LexingError::StringliteralBadEscape, LexingError::StringliteralBadEscape,
+        LexingError::StringliteralBadEscape,
+        LexingError::CharliteralBadEscape, LexingError::CharliteralBadEscape,
+        LexingError::Illegal("→".to_string()),
+        LexingError::StringliteralNul, LexingError::StringliteralNul, LexingError::StringliteralNul,
+    ];
+
+    check_valid(valid_lex, valid_results);
+    check_invalid(invalid_lex, invalid_results);
+}
+
+#[test]
+fn columns_count_characters() {
+    let mut scanner = super::Scanner::new();
+    let (text, errors) = scanner.tokenize("ł = \"żółw\"; ¬");
+    assert_eq!(text, "ID:ł\t[1,1]\nASSIGN\t[1,3]\nSTRINGLITERAL:\"żółw\"\t[1,5]\nSEMICOL\t[1,11]\n");
+    assert_eq!(errors, "FATAL [1,13]-[1,14]: Illegal character ¬\n");
+}
+
+#[test]
+fn lex_comments() {
+    let valid_lex = TokenType::lexer(r#"
+        a // b
+        /* c */ d /* e /* f */ g */ h
+        /* i
+           /* j */ */ k /**/ l /***/ m
+        //// n
+        /// o
+        ///p
+        ///
+        ///// q
+        r //
+        "#
+    );
+
+    let invalid_lex = TokenType::lexer(r#"
+        /* a /* b */
+        "#
+    );
+
+    let valid_results = vec![
+        TokenType::ID("a".to_string()), TokenType::ID("d".to_string()), TokenType::ID("h".to_string()), 
+        TokenType::ID("k".to_string()), TokenType::ID("l".to_string()), TokenType::ID("m".to_string()), 
+        TokenType::DOCCOMMENT(" o".to_string()), TokenType::DOCCOMMENT("p".to_string()),
+        TokenType::DOCCOMMENT("".to_string()), TokenType::ID("r".to_string()),
+    ];
+
+    let invalid_results = vec![
+        LexingError::CommentUnterminated,
+    ];
+
+    check_valid(valid_lex, valid_results);
+    check_invalid(invalid_lex, invalid_results);
+}
+
+#[test]
+fn block_comments_span_lines() {
+    let mut scanner = super::Scanner::new();
+    let (text, errors) = scanner.tokenize("a /* b\n c */ d\n/* /* e */\n");
+    assert_eq!(text, "ID:a\t[1,1]\nID:d\t[2,7]\n");
+    assert_eq!(errors, "FATAL [3,1]-[4,1]: Unterminated comment detected\n");
+}
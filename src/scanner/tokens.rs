@@ -1,20 +1,28 @@
-use logos::Logos;
+use logos::{FilterResult, Logos};
 use std::fmt;
 use std::num::ParseIntError;
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub enum LexingError {
     IntliteralOverflow,    
+    IntliteralEmpty,
+    IntliteralBadSeparator,
+    FloatliteralOverflow,
     StringliteralBadEscape,
+    StringliteralNul,
     StringliteralUnterminated,
     StringliteralUnterminatedBadEscape,
+    CharliteralBadEscape,
+    CharliteralUnterminated,
+    CharliteralEmpty,
+    CommentUnterminated,
     Illegal(String), // needed to print which char flagged this
 
     #[default]
     NonAsciiCharacter,
 }
 
-/// Error type returned by calling `lex.slice().parse()` to i32.
+/// Error type returned by calling `lex.slice().parse()` to i64.
 impl From<ParseIntError> for LexingError {
     fn from(_err: ParseIntError) -> Self {
         LexingError::IntliteralOverflow
@@ -25,19 +33,166 @@ impl fmt::Display for LexingError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             LexingError::IntliteralOverflow => write!(f, "Integer literal overflow"),
+            LexingError::IntliteralEmpty => write!(f, "Integer literal without digits detected"),
+            LexingError::IntliteralBadSeparator => write!(f, "Integer literal with misplaced underscore detected"),
+            LexingError::FloatliteralOverflow => write!(f, "Float literal overflow"),
             LexingError::StringliteralBadEscape => write!(f, "String literal with bad escape sequence detected"),
+            LexingError::StringliteralNul => write!(f, "String literal with a NUL character detected"),
             LexingError::StringliteralUnterminated => write!(f, "Unterminated string literal detected"),
             LexingError::StringliteralUnterminatedBadEscape => write!(f, "Unterminated string literal with bad escape sequence detected"),
+            LexingError::CharliteralBadEscape => write!(f, "Character literal with bad escape sequence detected"),
+            LexingError::CharliteralUnterminated => write!(f, "Unterminated character literal detected"),
+            LexingError::CharliteralEmpty => write!(f, "Empty character literal detected"),
+            LexingError::CommentUnterminated => write!(f, "Unterminated comment detected"),
             LexingError::Illegal(v) => write!(f, "Illegal character {}", v),
             LexingError::NonAsciiCharacter => write!(f, "Non ASCII character detected"),
         }
     }
 }
 
+// 0x, 0o and 0b pick the radix, the regexes only let underscores through
+// between two digits
+fn int_literal(lex: &mut logos::Lexer<TokenType>) -> Result<i64, LexingError> {
+    let digits = lex.slice().replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, &digits[..])
+    };
+    Ok(i64::from_str_radix(digits, radix)?)
+}
+
+// whatever int_literal's regexes turned down: a prefix with no digits after
+// it, or a leading, trailing or doubled underscore
+fn bad_int_literal(lex: &mut logos::Lexer<TokenType>) -> Result<i64, LexingError> {
+    let slice = lex.slice();
+    let body = match slice.get(..2) {
+        Some("0x" | "0o" | "0b") => &slice[2..],
+        _ => slice
+    };
+    if body.chars().all(|c| c == '_') {
+        Err(LexingError::IntliteralEmpty)
+    } else {
+        Err(LexingError::IntliteralBadSeparator)
+    }
+}
+
+// literals too big for a double would come out as infinity
+fn float_literal(lex: &mut logos::Lexer<TokenType>) -> Result<f64, LexingError> {
+    match lex.slice().parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(x),
+        _ => Err(LexingError::FloatliteralOverflow)
+    }
+}
+
+/// Decodes the escapes in the body of a string or character literal:
+/// `\n \t \r \0 \' \" \\`, `\xNN` up to 7F and `\u{...}` with one to
+/// six hex digits naming a Unicode scalar value. `None` on a bad escape.
+pub fn unescape(body: &str) -> Option<String> {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            x @ ('\'' | '"' | '\\') => x,
+            'x' => {
+                let code = chars.as_str().get(..2)?;
+                chars.nth(1);
+                match u8::from_str_radix(code, 16) {
+                    Ok(x) if x.is_ascii() && code.chars().all(|c| c.is_ascii_hexdigit()) => x as char,
+                    _ => return None
+                }
+            }
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let code = &rest[..rest.find('}')?];
+                if code.is_empty() || code.len() > 6 || !code.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return None;
+                }
+                chars.nth(code.len() + 1);
+                char::from_u32(u32::from_str_radix(code, 16).ok()?)?
+            }
+            _ => return None
+        });
+    }
+    Some(out)
+}
+
+// the quotes are kept, only the escapes are checked. Strings end at their
+// first NUL at runtime, so one inside would cut the string short
+fn string_literal(lex: &mut logos::Lexer<TokenType>) -> Result<String, LexingError> {
+    let slice = lex.slice();
+    match unescape(&slice[1..slice.len() - 1]) {
+        Some(body) if body.contains('\0') => Err(LexingError::StringliteralNul),
+        Some(_) => Ok(slice.to_string()),
+        None => Err(LexingError::StringliteralBadEscape)
+    }
+}
+
+fn unterminated_string_literal(lex: &mut logos::Lexer<TokenType>) -> LexingError {
+    match unescape(&lex.slice()[1..]) {
+        Some(_) => LexingError::StringliteralUnterminated,
+        None => LexingError::StringliteralUnterminatedBadEscape
+    }
+}
+
+// the quotes are dropped, the escapes have to make up exactly one character
+fn char_literal(lex: &mut logos::Lexer<TokenType>) -> Result<char, LexingError> {
+    let slice = lex.slice();
+    let body = unescape(&slice[1..slice.len() - 1]).ok_or(LexingError::CharliteralBadEscape)?;
+    let mut chars = body.chars();
+    match (chars.next(), chars.next()) {
+        (Some(x), None) => Ok(x),
+        _ => Err(LexingError::CharliteralBadEscape)
+    }
+}
+
+// block comments nest, so the comment runs until every /* has its */
+fn block_comment(lex: &mut logos::Lexer<TokenType>) -> FilterResult<(), LexingError> {
+    let rest = lex.remainder().as_bytes();
+    let mut depth = 1;
+    let mut i = 0;
+    while i < rest.len() {
+        match &rest[i..rest.len().min(i + 2)] {
+            b"/*" => { depth += 1; i += 2; }
+            b"*/" => { depth -= 1; i += 2; }
+            _ => i += 1
+        }
+        if depth == 0 {
+            lex.bump(i);
+            return FilterResult::Skip;
+        }
+    }
+    lex.bump(rest.len());
+    FilterResult::Error(LexingError::CommentUnterminated)
+}
+
+/// A character the way it is written in a literal, quotes included. Control
+/// characters without a short escape are written by their code.
+pub fn escape_char(c: char) -> String {
+    match c {
+        '\n' => "'\\n'".to_string(),
+        '\t' => "'\\t'".to_string(),
+        '\r' => "'\\r'".to_string(),
+        '\0' => "'\\0'".to_string(),
+        '\'' | '\\' => format!("'\\{}'", c),
+        _ if c.is_control() => format!("'\\u{{{:x}}}'", c as u32),
+        _ => format!("'{}'", c)
+    }
+}
+
 #[derive(Debug, Logos, PartialEq, Clone)]
 #[logos(error = LexingError)]
 #[logos(skip r"[ \s]+")]
-#[logos(skip r#"//.*[\n\r]?"#)]
+#[logos(skip r#"//([^/\n][^\n]*)?|////[^\n]*"#)]
 pub enum TokenType {
     //Keywords
     #[token("and", priority = 3)]
@@ -61,6 +216,12 @@ pub enum TokenType {
     #[token("int", priority = 3)]
     INT,
 
+    #[token("float", priority = 3)]
+    FLOAT,
+
+    #[token("char", priority = 3)]
+    CHAR,
+
     #[token("give", priority = 3)]
     GIVE,
 
@@ -76,6 +237,9 @@ pub enum TokenType {
     #[token("return", priority = 3)]
     RETURN,
 
+    #[token("string", priority = 3)]
+    STRING,
+
     #[token("class", priority = 3)]
     CLASS,
 
@@ -91,20 +255,60 @@ pub enum TokenType {
     #[token("while", priority = 3)]
     WHILE,
 
+    #[token("for", priority = 3)]
+    FOR,
+
+    #[token("do", priority = 3)]
+    DO,
+
+    #[token("break", priority = 3)]
+    BREAK,
+
+    #[token("continue", priority = 3)]
+    CONTINUE,
+
+    #[token("match", priority = 3)]
+    MATCH,
+
+    //a /// comment documents the declaration after it, the slashes are dropped
+    #[regex(r"///([^/\n][^\n]*)?", priority = 2, callback = |lex| lex.slice()[3..].trim_end_matches('\r').to_string())]
+    DOCCOMMENT(String),
+
     //Identifiers and Literals
-    #[regex(r"[a-zA-Z_][0-9a-zA-Z_]*", priority = 2, callback = |lex| lex.slice().parse().ok())]
+    #[regex(r"[\p{XID_Start}_]\p{XID_Continue}*", priority = 2, callback = |lex| lex.slice().parse().ok())]
     ID(String),
 
-    #[regex(r"[0-9]+", priority = 2, callback = |lex| lex.slice().parse())]
-    INTLITERAL(i32),
+    #[regex(r"[0-9](_?[0-9])*", priority = 3, callback = int_literal)]
+    #[regex(r"0x[0-9a-fA-F](_?[0-9a-fA-F])*", priority = 3, callback = int_literal)]
+    #[regex(r"0o[0-7](_?[0-7])*", priority = 3, callback = int_literal)]
+    #[regex(r"0b[01](_?[01])*", priority = 3, callback = int_literal)]
+    #[regex(r"[0-9][0-9_]*", priority = 2, callback = bad_int_literal)]
+    #[regex(r"0x[0-9a-fA-F_]*", priority = 2, callback = bad_int_literal)]
+    #[regex(r"0o[0-7_]*", priority = 2, callback = bad_int_literal)]
+    #[regex(r"0b[01_]*", priority = 2, callback = bad_int_literal)]
+    INTLITERAL(i64),
+
+    #[regex(r"[0-9]+\.[0-9]+([eE][+-]?[0-9]+)?|[0-9]+[eE][+-]?[0-9]+", priority = 2, callback = float_literal)]
+    FLOATLITERAL(f64),
 
-    #[regex(r#""(\\[nt"\\]|[^\n"\\])*""#, priority = 2, callback = |lex| lex.slice().parse().ok())]
+    #[regex(r#""(\\[^\n]|[^\n"\\])*""#, priority = 2, callback = string_literal)]
     STRINGLITERAL(String),
 
+    // a class mixing ASCII and non-ASCII ranges only matches the first byte of a
+    // multi-byte character, so the two halves are spelled out separately
+    #[regex(r#"'([^\n'\\\u{80}-\u{10FFFF}]|[\u{80}-\u{10FFFF}]|\\'|\\[^\n']+)'"#, priority = 2, callback = char_literal)]
+    CHARLITERAL(char),
+
     //Symbol Operators
     #[regex("=", priority = 3)]
     ASSIGN,
 
+    #[token("=>", priority = 3)]
+    ARROW,
+
+    #[token("..", priority = 3)]
+    DOTDOT,
+
     #[regex(":", priority = 3)]
     COLON,
 
@@ -126,6 +330,9 @@ pub enum TokenType {
     #[regex(">=", priority = 3)]
     GREATEREQ,
 
+    #[regex(r#"\["#, priority = 3)]
+    LBRACKET,
+
     #[regex(r#"\{"#, priority = 3)]
     LCURLY,
 
@@ -150,6 +357,9 @@ pub enum TokenType {
     #[regex(r#"\+\+"#, priority = 3)]
     POSTINC,
 
+    #[regex(r#"\]"#, priority = 3)]
+    RBRACKET,
+
     #[regex("}", priority = 3)]
     RCURLY,
 
@@ -165,26 +375,56 @@ pub enum TokenType {
     #[regex(r#"\*"#, priority = 3)]
     STAR,
 
-    //string literal with bad escape sequence ignored
-    #[regex(
-        r#""((\\[nt"\\]|[^\n"\\])*(\\[^nt"\\])(\\[nt"\\]|[^\n"\\])*)+""#, 
-        priority = 3, 
-        callback = |_| Err(LexingError::StringliteralBadEscape)
-    )]
+    #[token("%", priority = 3)]
+    PERCENT,
 
-    //unterminated string literal ignored \n \t \" \\
-    #[regex(
-        r#""(\\[nt"\\]|[^\n"\\])*"#, 
-        priority = 3, 
-        callback = |_| Err(LexingError::StringliteralUnterminated)
-    )]
-    
-    //untermintated string literal with bad escape sequence ignored
-    #[regex(
-        r#""((\\[nt"\\]|[^\n"\\])*(\\[^nt"\\])(\\[nt"\\]|[^\n"\\])*)+"#, 
-        priority = 3, 
-        callback = |_| Err(LexingError::StringliteralUnterminatedBadEscape)
-    )]
+    #[token("&", priority = 3)]
+    AMPERSAND,
+
+    #[token("|", priority = 3)]
+    PIPE,
+
+    #[token("^", priority = 3)]
+    CARET,
+
+    #[token("~", priority = 3)]
+    TILDE,
+
+    #[token("<<", priority = 3)]
+    LSHIFT,
+
+    #[token(">>", priority = 3)]
+    RSHIFT,
+
+    #[token("+=", priority = 3)]
+    CROSSASSIGN,
+
+    #[token("-=", priority = 3)]
+    DASHASSIGN,
+
+    #[token("*=", priority = 3)]
+    STARASSIGN,
+
+    #[token("/=", priority = 3)]
+    SLASHASSIGN,
+
+    #[token("%=", priority = 3)]
+    PERCENTASSIGN,
+
+    #[token("?", priority = 3)]
+    QUESTION,
+
+    //unterminated string literal ignored, with or without a bad escape sequence
+    #[regex(r#""(\\[^\n]|[^\n"\\])*"#, priority = 3, callback = |lex| Err(unterminated_string_literal(lex)))]
+
+    //unterminated character literal ignored
+    #[regex(r#"'([^\n'\\]|\\[^\n])*"#, priority = 3, callback = |_| Err(LexingError::CharliteralUnterminated))]
+
+    //empty character literal ignored
+    #[token("''", priority = 3, callback = |_| Err(LexingError::CharliteralEmpty))]
+
+    //block comment skipped, or unterminated and ignored
+    #[token("/*", priority = 3, callback = block_comment)]
 
     //illegal
     #[regex(
@@ -201,6 +441,9 @@ impl fmt::Display for TokenType {
             TokenType::ID(v) =>            write!(f, "ID:{}", v),
             TokenType::STRINGLITERAL(v) => write!(f, "STRINGLITERAL:{}", v),
             TokenType::INTLITERAL(v) =>       write!(f, "INTLITERAL:{}", v),
+            TokenType::FLOATLITERAL(v) =>     write!(f, "FLOATLITERAL:{:?}", v),
+            TokenType::CHARLITERAL(v) =>      write!(f, "CHARLITERAL:{}", escape_char(*v)),
+            TokenType::DOCCOMMENT(v) =>       write!(f, "DOCCOMMENT:{}", v),
             _ =>                                    write!(f, "{:?}", self)
         }
     }
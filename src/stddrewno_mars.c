@@ -1,5 +1,6 @@
 #include "stdio.h"
 #include "stdlib.h"
+#include "string.h"
 #include <inttypes.h>
 #include <time.h>
 
@@ -32,11 +33,87 @@ void printInt(long int num) {
 	fflush(stdout);
 }
 
+void printFloat(double num) {
+	printf("%g", num);
+	fflush(stdout);
+}
+
+// A char is a code point, printed as UTF-8
+void printChar(int64_t c) {
+	if (c < 0x80) {
+		putchar(c);
+	} else if (c < 0x800) {
+		putchar(0xC0 | (c >> 6));
+		putchar(0x80 | (c & 0x3F));
+	} else if (c < 0x10000) {
+		putchar(0xE0 | (c >> 12));
+		putchar(0x80 | ((c >> 6) & 0x3F));
+		putchar(0x80 | (c & 0x3F));
+	} else {
+		putchar(0xF0 | (c >> 18));
+		putchar(0x80 | ((c >> 12) & 0x3F));
+		putchar(0x80 | ((c >> 6) & 0x3F));
+		putchar(0x80 | (c & 0x3F));
+	}
+	fflush(stdout);
+}
+
+// How many bytes the character starting with byte c takes up. Bytes that
+// can't start one count as a character of their own.
+static int charWidth(unsigned char c) {
+	if (c >= 0xF0 && c <= 0xF7){
+		return 4;
+	} else if (c >= 0xE0){
+		return c <= 0xEF ? 3 : 1;
+	} else if (c >= 0xC0){
+		return 2;
+	}
+	return 1;
+}
+
+// The code point of the character at s, whose bytes it stores in *width.
+// A sequence cut short counts up to where it stops.
+static int64_t decodeChar(const char * s, int * width) {
+	const unsigned char * u = (const unsigned char *)s;
+	int n = charWidth(u[0]);
+	if (n == 1){
+		*width = 1;
+		return u[0];
+	}
+	int64_t c = u[0] & (0x7F >> n);
+	int i = 1;
+	for (; i < n && (u[i] & 0xC0) == 0x80; i++){
+		c = (c << 6) | (u[i] & 0x3F);
+	}
+	*width = i;
+	return i == n ? c : u[0];
+}
+
+// Skips n characters of s, stopping at its end
+static const char * skipChars(const char * s, int64_t n) {
+	int width;
+	while (n > 0 && *s != 0){
+		decodeChar(s, &width);
+		s += width;
+		n--;
+	}
+	return s;
+}
+
+// string variables start out as null, which reads as ""
+static const char * orEmpty(const char * s) {
+	return s == NULL ? "" : s;
+}
+
 void printString(const char * str) {
-	fprintf(stdout, "%s", str);
+	fprintf(stdout, "%s", orEmpty(str));
 	fflush(stdout);
 }
 
+int64_t stringEquals(const char * a, const char * b) {
+	return strcmp(orEmpty(a), orEmpty(b)) == 0;
+}
+
 int64_t getBool(){
 	char c;
 	fscanf(stdin, "%c", &c);
@@ -57,3 +134,187 @@ int64_t getInt(){
 	long int res = atol(buffer);
 	return res;
 }
+
+// Reads one character, all of its UTF-8 bytes, and the newline after it
+int64_t getChar(){
+	int c = getchar();
+	if (c == EOF){
+		return 0;
+	}
+	if (c == '\n'){
+		return c;
+	}
+	char bytes[5] = {c, 0, 0, 0, 0};
+	int next = 0;
+	for (int i = 1; i < charWidth(c); i++){
+		next = getchar();
+		if (next == EOF || next == '\n'){
+			break;
+		}
+		bytes[i] = next;
+	}
+	if (next != EOF && next != '\n'){
+		getchar();
+	}
+	int width;
+	return decodeChar(bytes, &width);
+}
+
+double getFloat(){
+	char buffer[64];
+	for (int i = 0 ; i < 64; i++){
+		buffer[i] = 0;
+	}
+	fgets(buffer, 64, stdin);
+	return atof(buffer);
+}
+
+// Strings are never freed; every operation that makes one allocates it fresh.
+// Lengths and indices count characters, not UTF-8 bytes, and indices out of
+// range clamp to the ends of the string instead of failing.
+
+static char * copyOf(const char * s, int64_t n) {
+	char * result = malloc(n + 1);
+	memcpy(result, s, n);
+	result[n] = 0;
+	return result;
+}
+
+char * stringConcat(const char * a, const char * b) {
+	a = orEmpty(a);
+	b = orEmpty(b);
+	size_t lenA = strlen(a);
+	size_t lenB = strlen(b);
+	char * result = malloc(lenA + lenB + 1);
+	memcpy(result, a, lenA);
+	memcpy(result + lenA, b, lenB + 1);
+	return result;
+}
+
+int64_t stringLength(const char * s) {
+	s = orEmpty(s);
+	int64_t len = 0;
+	for (; *s != 0; s = skipChars(s, 1)){
+		len++;
+	}
+	return len;
+}
+
+char * stringSubstr(const char * s, int64_t i, int64_t n) {
+	s = orEmpty(s);
+	if (i < 0){
+		i = 0;
+	}
+	if (n < 0){
+		n = 0;
+	}
+	const char * start = skipChars(s, i);
+	return copyOf(start, skipChars(start, n) - start);
+}
+
+char * stringCharAt(const char * s, int64_t i) {
+	return stringSubstr(s, i, 1);
+}
+
+char * intToString(int64_t i) {
+	char buffer[32];
+	int n = snprintf(buffer, 32, "%" PRId64, i);
+	return copyOf(buffer, n);
+}
+
+int64_t stringToInt(const char * s) {
+	return atol(orEmpty(s));
+}
+
+double intToFloat(int64_t i) {
+	return (double)i;
+}
+
+// the fraction is dropped, toward zero
+int64_t floatToInt(double f) {
+	return (int64_t)f;
+}
+
+int64_t intToChar(int64_t i) {
+	return i;
+}
+
+int64_t charToInt(int64_t c) {
+	return c;
+}
+
+// Unlike the other string operations, reading a single character has
+// nothing to clamp to, so an index out of range stops the program.
+int64_t stringIndex(const char * s, int64_t i) {
+	s = orEmpty(s);
+	const char * at = skipChars(s, i);
+	if (i < 0 || *at == 0){
+		fprintf(stderr, "String index out of bounds\n");
+		exit(1);
+	}
+	int width;
+	return decodeChar(at, &width);
+}
+
+// An array is handed around as a pointer to this header. The generated code
+// reads slots and data directly, so those two have to stay where they are.
+// Every element takes up stride slots, one per field for arrays of classes.
+typedef struct {
+	int64_t slots;
+	int64_t len;
+	int64_t * data;
+	int64_t stride;
+	int64_t cap;
+} Array;
+
+Array * arrayNew(int64_t len, int64_t stride) {
+	Array * a = malloc(sizeof(Array));
+	a->slots = len * stride;
+	a->len = len;
+	a->stride = stride;
+	a->cap = a->slots > 0 ? a->slots : stride;
+	a->data = calloc(a->cap, sizeof(int64_t));
+	return a;
+}
+
+int64_t arrayLength(const Array * a) {
+	return a->len;
+}
+
+void arrayPush(Array * a, int64_t x) {
+	if (a->slots + a->stride > a->cap){
+		a->cap *= 2;
+		a->data = realloc(a->data, a->cap * sizeof(int64_t));
+	}
+	memset(a->data + a->slots, 0, a->stride * sizeof(int64_t));
+	a->data[a->slots] = x;
+	a->slots += a->stride;
+	a->len++;
+}
+
+void arrayIndexError(){
+	fprintf(stderr, "Array index out of bounds\n");
+	exit(1);
+}
+
+void integerOverflowError(){
+	fprintf(stderr, "Integer overflow\n");
+	exit(1);
+}
+
+// Reads one line, without its newline, into a fresh buffer that is never freed
+char * getString(){
+	size_t cap = 32;
+	size_t len = 0;
+	char * buffer = malloc(cap);
+	int c;
+	while ((c = getchar()) != EOF && c != '\n'){
+		if (len + 1 == cap){
+			cap *= 2;
+			buffer = realloc(buffer, cap);
+		}
+		buffer[len++] = c;
+	}
+	buffer[len] = 0;
+	return buffer;
+}
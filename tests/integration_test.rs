@@ -77,6 +77,134 @@ fn test(project: u8) {
     }
 }
 
+/// Compiles every program in `tests/run` with and without the peephole pass, runs
+/// both executables and checks that they print the same, expected output.
+#[test]
+fn peephole_preserves_output() {
+    let tests : &Vec::<Test> = &chunk_by_test(fs::read_dir("./tests/run").unwrap());
+
+    for t in tests {
+        let directory = t.directory.to_str().unwrap();
+        let correct_output = match std::fs::read_to_string(format!("{}/{}.expected", directory, *t.name)) {
+            Ok(v) => v,
+            Err(_) => panic!("Unable to read given input file.")
+        };
+
+        let optimized = run_program(t, &[]);
+        let unoptimized = run_program(t, &["--no-peephole"]);
+        assert_eq!(optimized, unoptimized, "{} changed behaviour under the peephole pass", *t.name);
+        assert_eq!(correct_output, optimized);
+    }
+}
+
+/// Runs every program in `tests/run` with the IR optimizations turned on and
+/// checks the output against the unoptimized build's.
+#[test]
+fn optimizations_preserve_output() {
+    let tests : &Vec::<Test> = &chunk_by_test(fs::read_dir("./tests/run").unwrap());
+
+    for t in tests {
+        let unoptimized = run_program(t, &["-O0"]);
+        for flag in ["-O1", "-O2", "--passes=dce,const-prop,dce", "--passes=ssa", "--passes=cse,copy-prop,dce", "--passes=licm", "--passes=inline", "--passes=tail-call"] {
+            assert_eq!(unoptimized, run_program(t, &[flag]), "{} changed behaviour under {}", *t.name, flag);
+        }
+    }
+}
+
+/// Programs in `tests/tail` recurse a million calls deep, far past what the
+/// stack holds unless self tail calls reuse the frame.
+#[test]
+fn tail_calls_run_in_one_frame() {
+    let tests : &Vec::<Test> = &chunk_by_test(fs::read_dir("./tests/tail").unwrap());
+
+    for t in tests {
+        let directory = t.directory.to_str().unwrap();
+        let correct_output = std::fs::read_to_string(format!("{}/{}.expected", directory, *t.name)).unwrap();
+        for flag in ["-O1", "-O2", "--passes=tail-call"] {
+            assert_eq!(correct_output, run_program(t, &[flag]), "{} under {}", *t.name, flag);
+        }
+    }
+}
+
+#[test]
+fn emit_cfg_dot() {
+    let mut command = Command::cargo_bin("drewno_mars").unwrap();
+    command.args(["./tests/run/loops.dm", "--emit=cfg-dot"]);
+    let output = command.assert().success();
+    let output = String::from_utf8(output.get_output().stdout.to_vec()).unwrap();
+
+    assert!(output.starts_with("digraph program {"));
+    assert!(output.contains("subgraph \"cluster_main\""));
+    // the back edge of the outer while loop
+    assert!(output.contains("\"main_B8\" -> \"main_B1\";"));
+}
+
+/// Programs in `tests/bounds` index past the end of an array or a string. The check stops
+/// them with the message in `.err` before anything in `.expected` is skipped.
+#[test]
+fn out_of_bounds_index_exits() {
+    let tests : &Vec::<Test> = &chunk_by_test(fs::read_dir("./tests/bounds").unwrap());
+
+    for t in tests {
+        let directory = t.directory.to_str().unwrap();
+        let correct_output = std::fs::read_to_string(format!("{}/{}.expected", directory, *t.name)).unwrap();
+        let correct_error = std::fs::read_to_string(format!("{}/{}.err", directory, *t.name)).unwrap();
+        for flags in [&[][..], &["-O2"], &["--no-peephole"]] {
+            let output = Command::new(build_program(t, flags)).assert().code(1);
+            let output = output.get_output();
+            assert_eq!(correct_output, String::from_utf8(output.stdout.to_vec()).unwrap());
+            assert_eq!(correct_error, String::from_utf8(output.stderr.to_vec()).unwrap());
+        }
+    }
+}
+
+/// Programs in `tests/overflow` push an int past the 64 bit range. Built with
+/// `--trap-overflow` they stop there with the message in `.err`.
+#[test]
+fn overflow_traps_when_asked() {
+    let tests : &Vec::<Test> = &chunk_by_test(fs::read_dir("./tests/overflow").unwrap());
+
+    for t in tests {
+        let directory = t.directory.to_str().unwrap();
+        let correct_output = std::fs::read_to_string(format!("{}/{}.expected", directory, *t.name)).unwrap();
+        let correct_error = std::fs::read_to_string(format!("{}/{}.err", directory, *t.name)).unwrap();
+        for flags in [&["--trap-overflow"][..], &["--trap-overflow", "-O2"], &["--trap-overflow", "--no-peephole"]] {
+            let output = Command::new(build_program(t, flags)).assert().code(1);
+            let output = output.get_output();
+            assert_eq!(correct_output, String::from_utf8(output.stdout.to_vec()).unwrap());
+            assert_eq!(correct_error, String::from_utf8(output.stderr.to_vec()).unwrap());
+        }
+    }
+}
+
+/// Compiles one test program to x86, assembles it against the runtime library
+/// and returns what it prints.
+fn run_program(t: &Test, flags: &[&str]) -> String {
+    let output = Command::new(build_program(t, flags)).assert().success();
+    String::from_utf8(output.get_output().stdout.to_vec()).unwrap()
+}
+
+/// Compiles one test program to x86 and returns the path of the executable.
+fn build_program(t: &Test, flags: &[&str]) -> String {
+    let directory = t.directory.to_str().unwrap();
+    let input_file = format!("{}/{}.dm", directory, *t.name);
+    let stem = format!("{}/{}{}", env!("CARGO_TARGET_TMPDIR"), *t.name, flags.concat());
+    let asm_file = format!("{}.s", stem);
+    let exe_file = format!("{}.out", stem);
+
+    Command::cargo_bin("drewno_mars").unwrap()
+        .args([input_file.as_str(), "-o", asm_file.as_str()])
+        .args(flags)
+        .assert()
+        .success();
+    Command::new("gcc")
+        .args(["-no-pie", asm_file.as_str(), "src/stddrewno_mars.c", "-o", exe_file.as_str()])
+        .assert()
+        .success();
+
+    exe_file
+}
+
 fn option_from_project(project: u8) -> &'static str {
     match project {
         1 => "-t",